@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anstream::eprintln as println;
+use clap::Args;
+use color_print::cprintln;
+
+use crate::manifest::Manifest;
+use crate::Run;
+
+/// Time the codegen of a file
+#[derive(Args, Debug)]
+pub struct BenchCommand {
+    source: PathBuf,
+
+    /// The number of timed runs, after a warmup run
+    #[arg(short = 'n', long, default_value_t = 10)]
+    runs: usize,
+}
+
+impl Run for BenchCommand {
+    fn run(&self, manifest: &Manifest) {
+        manifest.prepare();
+
+        cprintln!("<b>[BENCH]</b> warming up");
+        self.codegen_time(manifest);
+
+        cprintln!("<b>[BENCH]</b> timing {} runs of {}", self.runs, self.source.display());
+        let mut times =
+            (0..self.runs).filter_map(|_| self.codegen_time(manifest)).collect::<Vec<_>>();
+        if times.is_empty() {
+            return; // dry-run
+        }
+        times.sort();
+
+        println!("{:>12} {:>12} {:>12}", "min", "median", "max");
+        println!(
+            "{:>12.3?} {:>12.3?} {:>12.3?}",
+            times[0],
+            times[times.len() / 2],
+            times[times.len() - 1]
+        );
+    }
+}
+
+impl BenchCommand {
+    /// Compile the source once, returning the time spent in the codegen step
+    ///
+    /// The time is taken from the `codegen_crate` pass reported by `-Z time-passes`,
+    /// so that it excludes the C compiler invoked afterwards.
+    fn codegen_time(&self, manifest: &Manifest) -> Option<Duration> {
+        let out_dir = manifest.out_dir.join("bench");
+        manifest.create_dir_all(&out_dir);
+
+        let mut command = manifest.rustc();
+        command
+            .arg(&self.source)
+            .args(["--crate-type", "bin"])
+            .arg("--out-dir")
+            .arg(&out_dir)
+            .args(["-Z", "time-passes"]);
+        let output = manifest.command_output(&mut command);
+        if manifest.dry_run {
+            return None;
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            output.status.success(),
+            "failed to compile {}:\n{}",
+            self.source.display(),
+            stderr
+        );
+        let pass = regex::Regex::new(r"^time:\s*(?P<secs>[\d.]+);.*\scodegen_crate$").unwrap();
+        let secs = stderr
+            .lines()
+            .find_map(|line| pass.captures(line))
+            .expect("`codegen_crate` pass not found in `-Z time-passes` output")
+            .name("secs")
+            .unwrap()
+            .as_str();
+        Some(Duration::from_secs_f64(secs.parse().unwrap()))
+    }
+}
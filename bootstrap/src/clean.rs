@@ -1,15 +1,84 @@
+use std::path::PathBuf;
+
 use clap::Args;
 
 use crate::manifest::Manifest;
 use crate::Run;
 
 /// Clean the build directory
+///
+/// Without any scope flags, everything is removed.
 #[derive(Args, Debug)]
-pub struct CleanCommand {}
+pub struct CleanCommand {
+    /// Remove the compiled examples
+    #[arg(long)]
+    pub examples: bool,
+
+    /// Remove the compiled tests
+    #[arg(long)]
+    pub tests: bool,
+
+    /// Remove the built codegen backend
+    #[arg(long)]
+    pub backend: bool,
+}
 
 impl Run for CleanCommand {
     fn run(&self, manifest: &Manifest) {
-        let _ = std::fs::remove_dir_all("crates/target");
-        let _ = std::fs::remove_dir_all(&manifest.out_dir);
+        for path in self.paths(manifest) {
+            manifest.remove_dir_all(&path);
+        }
+    }
+}
+
+impl CleanCommand {
+    /// The directories to remove
+    fn paths(&self, manifest: &Manifest) -> Vec<PathBuf> {
+        if !self.examples && !self.tests && !self.backend {
+            return vec![PathBuf::from("crates/target"), manifest.out_dir.clone()];
+        }
+
+        let mut paths = vec![];
+        if self.examples {
+            paths.push(manifest.out_dir.join("examples"));
+        }
+        if self.tests {
+            paths.push(manifest.out_dir.join("tests"));
+        }
+        if self.backend {
+            paths.push(PathBuf::from("crates/target"));
+        }
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_examples_keeps_backend() {
+        let out_dir = crate::test_dir("y_clean_examples");
+        std::fs::create_dir_all(out_dir.join("examples")).unwrap();
+        std::fs::create_dir_all(out_dir.join("tests")).unwrap();
+        let manifest = Manifest { out_dir, ..Default::default() };
+        let clean = CleanCommand { examples: true, tests: false, backend: false };
+
+        let paths = clean.paths(&manifest);
+        assert!(!paths.iter().any(|path| manifest.codegen_backend().starts_with(path)));
+        clean.run(&manifest);
+        assert!(!manifest.out_dir.join("examples").exists());
+        assert!(manifest.out_dir.join("tests").exists());
+    }
+
+    #[test]
+    fn clean_dry_run_keeps_everything() {
+        let out_dir = crate::test_dir("y_clean_dry_run");
+        std::fs::create_dir_all(out_dir.join("examples")).unwrap();
+        let manifest = Manifest { out_dir, dry_run: true, ..Default::default() };
+        let clean = CleanCommand { examples: true, tests: false, backend: false };
+
+        clean.run(&manifest);
+        assert!(manifest.out_dir.join("examples").exists());
     }
 }
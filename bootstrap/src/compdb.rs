@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::manifest::Manifest;
+
+/// The environment variable naming the file to which the codegen backend
+/// appends an entry, as a line of JSON, for each C compiler invocation
+const COMPDB_ENV: &str = "RUSTC_CODEGEN_C_COMPDB";
+
+/// A `compile_commands.json` collecting how each generated C file is compiled,
+/// e.g. for clangd
+pub struct Compdb {
+    path: PathBuf,
+    entries: PathBuf,
+}
+
+impl Compdb {
+    /// Start collecting the entries of the compilation database at `path`
+    pub fn new(manifest: &Manifest, path: &Path) -> Self {
+        let entries = manifest.out_dir.join("compdb.entries");
+        if !manifest.dry_run {
+            // drop the entries of a previous run
+            let _ = std::fs::remove_file(&entries);
+        }
+        Compdb { path: path.to_path_buf(), entries }
+    }
+
+    /// Make the codegen backend record the C compiler invocations of `command`
+    pub fn record(&self, command: &mut Command) {
+        command.env(COMPDB_ENV, std::path::absolute(&self.entries).unwrap());
+    }
+
+    /// Write the recorded entries as a JSON array to the compilation database
+    pub fn finish(&self, manifest: &Manifest) {
+        if manifest.dry_run {
+            return;
+        }
+        let entries = std::fs::read_to_string(&self.entries).unwrap_or_default();
+        let entries: Vec<_> = entries.lines().filter(|line| !line.is_empty()).collect();
+        let compdb = format!("[\n  {}\n]\n", entries.join(",\n  "));
+        std::fs::write(&self.path, compdb).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compdb_has_entry_per_file() {
+        let out_dir = crate::test_dir("rustc_codegen_c_compdb");
+        let manifest = Manifest { out_dir: out_dir.clone(), ..Default::default() };
+        let compdb = Compdb::new(&manifest, &out_dir.join("compile_commands.json"));
+
+        let mut command = Command::new("rustc");
+        compdb.record(&mut command);
+        let entries = command.get_envs().find(|(key, _)| *key == COMPDB_ENV).unwrap().1;
+        assert!(Path::new(entries.unwrap()).is_absolute());
+
+        let entry = |file: &str| {
+            format!(
+                r#"{{"directory": "/tmp", "arguments": ["clang", "{file}"], "file": "{file}"}}"#
+            )
+        };
+        std::fs::write(&compdb.entries, format!("{}\n{}\n", entry("a.c"), entry("b.c"))).unwrap();
+        compdb.finish(&manifest);
+
+        let json = std::fs::read_to_string(out_dir.join("compile_commands.json")).unwrap();
+        assert!(json.starts_with("[\n") && json.ends_with("]\n"), "{}", json);
+        assert_eq!(json.matches(r#""file": "#).count(), 2, "{}", json);
+        assert!(json.contains(&format!("{},\n", entry("a.c"))), "{}", json);
+        assert!(json.contains(r#""file": "b.c"}"#), "{}", json);
+    }
+}
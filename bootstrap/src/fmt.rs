@@ -3,6 +3,7 @@ use std::process::Command;
 use clap::Args;
 use glob::glob;
 
+use crate::manifest::Manifest;
 use crate::Run;
 
 /// Format code, examples and tests
@@ -13,31 +14,38 @@ pub struct FmtCommand {
 }
 
 impl Run for FmtCommand {
-    fn run(&self, _manifest: &crate::manifest::Manifest) {
+    fn run(&self, manifest: &Manifest) {
         self.perform(
+            manifest,
             Command::new("cargo").arg("fmt").args(["--manifest-path", "bootstrap/Cargo.toml"]),
         );
         self.perform(
+            manifest,
             Command::new("cargo")
                 .arg("fmt")
                 .args(["--manifest-path", "crates/Cargo.toml"])
                 .arg("--all"),
         );
         for file in glob("examples/**/*.rs").unwrap() {
-            self.perform(Command::new("rustfmt").args(["--edition", "2021"]).arg(file.unwrap()));
+            self.perform(
+                manifest,
+                Command::new("rustfmt").args(["--edition", &manifest.edition]).arg(file.unwrap()),
+            );
         }
         for file in glob("tests/**/*.rs").unwrap() {
-            self.perform(Command::new("rustfmt").args(["--edition", "2021"]).arg(file.unwrap()));
+            self.perform(
+                manifest,
+                Command::new("rustfmt").args(["--edition", &manifest.edition]).arg(file.unwrap()),
+            );
         }
     }
 }
 
 impl FmtCommand {
-    pub fn perform(&self, command: &mut Command) {
+    pub fn perform(&self, manifest: &Manifest, command: &mut Command) {
         if self.check {
             command.arg("--check");
         }
-        log::debug!("running {:?}", command);
-        assert!(command.status().unwrap().success(), "failed to run {:?}", command);
+        assert!(manifest.command_status(command).success(), "failed to run {:?}", command);
     }
 }
@@ -1,8 +1,12 @@
-use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use clap::{ColorChoice, Parser, Subcommand};
 
 use crate::manifest::Manifest;
 
+mod bench;
 mod clean;
+mod compdb;
 mod fmt;
 mod manifest;
 mod rustc;
@@ -20,9 +24,27 @@ pub struct Cli {
     #[arg(short, long)]
     pub out_dir: Option<String>,
 
-    /// verbose output
-    #[arg(short, long)]
-    pub verbose: bool,
+    /// verbose output, `-vv` also echoes the commands run with their environment
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// The Rust edition of the examples and tests
+    #[arg(long, global = true, default_value = "2021", value_parser = manifest::EDITIONS)]
+    pub edition: String,
+
+    /// Use this prebuilt codegen backend library instead of building it, e.g.
+    /// to compare builds while bisecting
+    #[arg(long, global = true)]
+    pub backend: Option<PathBuf>,
+
+    /// When to use colored output, `auto` disables it when not writing to a
+    /// terminal or when `NO_COLOR` is set
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Print the commands that would be run without running them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
 
     #[command(subcommand)]
     pub command: Command,
@@ -34,6 +56,7 @@ pub enum Command {
     Clean(clean::CleanCommand),
     Rustc(rustc::RustcCommand),
     Fmt(fmt::FmtCommand),
+    Bench(bench::BenchCommand),
 }
 
 trait Run {
@@ -43,16 +66,64 @@ trait Run {
 fn main() {
     env_logger::init();
     let cli = Cli::parse();
+    color_choice(cli.color).write_global();
 
     let manifest = Manifest {
         verbose: cli.verbose,
+        dry_run: cli.dry_run,
         release: cli.release,
         out_dir: cli.out_dir.unwrap_or("build".to_string()).into(),
+        edition: cli.edition,
+        backend: cli.backend,
     };
     match cli.command {
         Command::Test(test) => test.run(&manifest),
         Command::Clean(clean) => clean.run(&manifest),
         Command::Rustc(rustc) => rustc.run(&manifest),
         Command::Fmt(fmt) => fmt.run(&manifest),
+        Command::Bench(bench) => bench.run(&manifest),
+    }
+}
+
+/// The color choice of the output streams, `auto` is left to `anstream`
+fn color_choice(color: ColorChoice) -> anstream::ColorChoice {
+    match color {
+        ColorChoice::Auto => anstream::ColorChoice::Auto,
+        ColorChoice::Always => anstream::ColorChoice::Always,
+        ColorChoice::Never => anstream::ColorChoice::Never,
+    }
+}
+
+/// A fresh temporary directory for a test, unique to the test process so that
+/// concurrent test runs do not race on it
+#[cfg(test)]
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn color_never_strips_escapes() {
+        let cli = Cli::parse_from(["y", "--color", "never", "fmt"]);
+        let mut stream = anstream::AutoStream::new(Vec::new(), color_choice(cli.color));
+        write!(stream, "{}", color_print::cformat!("<g>OK</g>")).unwrap();
+        let output = String::from_utf8(stream.into_inner()).unwrap();
+        assert_eq!(output, "OK");
+    }
+
+    #[test]
+    fn edition_is_validated() {
+        let cli = Cli::parse_from(["y", "--edition", "2018", "fmt"]);
+        assert_eq!(cli.edition, "2018");
+        let err = Cli::try_parse_from(["y", "--edition", "2020", "fmt"]).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::InvalidValue);
     }
 }
@@ -1,31 +1,50 @@
 use anstream::eprintln as println;
 use color_print::cprintln;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, ExitStatus, Output};
+
+/// The Rust editions accepted for the examples and tests
+pub const EDITIONS: [&str; 4] = ["2015", "2018", "2021", "2024"];
 
 pub struct Manifest {
-    pub verbose: bool,
+    /// The verbosity level, i.e. the number of `-v` flags
+    pub verbose: u8,
+    pub dry_run: bool,
     pub release: bool,
     pub out_dir: PathBuf,
+    /// The Rust edition of the examples and tests
+    pub edition: String,
+    /// A prebuilt codegen backend to use instead of building one
+    pub backend: Option<PathBuf>,
+}
+
+/// The defaults of the command line, mostly for tests to override a few fields
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            verbose: 0,
+            dry_run: false,
+            release: false,
+            out_dir: PathBuf::from("build"),
+            edition: "2021".to_string(),
+            backend: None,
+        }
+    }
 }
 
 impl Manifest {
     /// Builds the rustc codegen c library
     pub fn prepare(&self) {
-        cprintln!("<b>[BUILD]</b> codegen backend");
-        let mut command = Command::new("cargo");
-        command.arg("build").args(["--manifest-path", "crates/Cargo.toml"]);
-        if self.verbose {
-            command.args(["-F", "debug"]);
-        }
-        if self.release {
-            command.arg("--release");
+        match self.backend_build_command() {
+            Some(mut command) => {
+                cprintln!("<b>[BUILD]</b> codegen backend");
+                self.command_status(&mut command);
+            }
+            None => cprintln!("<b>[BUILD]</b> using prebuilt {}", self.codegen_backend().display()),
         }
-        log::debug!("running {:?}", command);
-        command.status().unwrap();
 
         cprintln!("<b>[BUILD]</b> librust_runtime");
-        std::fs::create_dir_all(&self.out_dir).unwrap();
+        self.create_dir_all(&self.out_dir);
         let cc = std::env::var("CC").unwrap_or("clang".to_string());
         let mut command = Command::new(&cc);
         command
@@ -33,20 +52,94 @@ impl Manifest {
             .arg("-o")
             .arg(self.out_dir.join("rust_runtime.o"))
             .arg("-c");
-        log::debug!("running {:?}", command);
-        command.status().unwrap();
+        self.command_status(&mut command);
         let mut command = Command::new("ar");
         command
             .arg("rcs")
             .arg(self.out_dir.join("librust_runtime.a"))
             .arg(self.out_dir.join("rust_runtime.o"));
-        log::debug!("running {:?}", command);
-        command.status().unwrap();
+        self.command_status(&mut command);
+    }
+
+    /// Run a command and wait for it to finish
+    ///
+    /// In dry-run mode, the command is only printed and a successful status is returned.
+    pub fn command_status(&self, command: &mut Command) -> ExitStatus {
+        if self.dry_run {
+            cprintln!("<b>[DRY-RUN]</b> {:?}", command);
+            return ExitStatus::default();
+        }
+        self.echo_command(command);
+        command.status().unwrap()
+    }
+
+    /// Run a command and collect its output
+    ///
+    /// In dry-run mode, the command is only printed and a successful, empty output is returned.
+    pub fn command_output(&self, command: &mut Command) -> Output {
+        if self.dry_run {
+            cprintln!("<b>[DRY-RUN]</b> {:?}", command);
+            return Output { status: ExitStatus::default(), stdout: vec![], stderr: vec![] };
+        }
+        self.echo_command(command);
+        command.output().unwrap()
+    }
+
+    /// Print a command about to run in `-vv` mode, otherwise only log it
+    fn echo_command(&self, command: &Command) {
+        if self.verbose >= 2 {
+            cprintln!("<b>[RUN]</b> {}", shell_command(command));
+        } else {
+            log::debug!("running {:?}", command);
+        }
+    }
+
+    /// Create a directory and all its parents, unless in dry-run mode
+    pub fn create_dir_all(&self, path: &Path) {
+        if !self.dry_run {
+            std::fs::create_dir_all(path).unwrap();
+        }
+    }
+
+    /// Remove a directory and all its contents, if it exists
+    ///
+    /// In dry-run mode, the removal is only printed.
+    pub fn remove_dir_all(&self, path: &Path) {
+        if self.dry_run {
+            cprintln!("<b>[DRY-RUN]</b> remove {}", path.display());
+            return;
+        }
+        let _ = std::fs::remove_dir_all(path);
     }
 
-    /// The path to the rustc codegen c library
-    pub fn codegen_backend(&self) -> &'static Path {
+    /// The directory of the compiled auxiliary crates, kept apart from the test
+    /// outputs so that they cannot collide
+    pub fn aux_dir(&self) -> PathBuf {
+        self.out_dir.join("aux")
+    }
+
+    /// The command to build the codegen backend, or `None` if a prebuilt one
+    /// is given with `--backend`
+    fn backend_build_command(&self) -> Option<Command> {
+        if self.backend.is_some() {
+            return None;
+        }
+        let mut command = Command::new("cargo");
+        command.arg("build").args(["--manifest-path", "crates/Cargo.toml"]);
+        if self.verbose > 0 {
+            command.args(["-F", "debug"]);
+        }
         if self.release {
+            command.arg("--release");
+        }
+        Some(command)
+    }
+
+    /// The path to the rustc codegen c library, unless overridden with `--backend`
+    pub fn codegen_backend(&self) -> &Path {
+        if let Some(backend) = &self.backend {
+            backend
+        } else if self.release {
             Path::new("crates/target/release/librustc_codegen_c.so")
         } else {
             Path::new("crates/target/debug/librustc_codegen_c.so")
@@ -57,18 +150,89 @@ impl Manifest {
     pub fn rustc(&self) -> Command {
         let mut command = Command::new("rustc");
         command
-            .args(["--edition", "2021"])
+            .args(["--edition", &self.edition])
             .arg("-Z")
             .arg(format!("codegen-backend={}", self.codegen_backend().display()))
             .args(["-C", "panic=abort"])
             .args(["-C", "lto=false"])
             .arg(format!("-Lall={}", self.out_dir.display()))
+            .arg(format!("-Lall={}", self.aux_dir().display()))
             .env("CFLAGS", "-Irust_runtime")
             .arg("-lc")
             .arg("-lrust_runtime");
-        if self.verbose {
+        if self.verbose > 0 {
             command.env("RUST_BACKTRACE", "full");
         }
         command
     }
 }
+
+/// Format a command as a shell command line, with its environment overrides
+///
+/// The output can be copied into a shell to run the command exactly as spawned.
+pub fn shell_command(command: &Command) -> String {
+    let mut words = vec![];
+    let removed = command.get_envs().filter(|(_, value)| value.is_none()).collect::<Vec<_>>();
+    if !removed.is_empty() {
+        words.push("env".to_string());
+        words.extend(removed.iter().map(|(key, _)| format!("-u {}", key.to_string_lossy())));
+    }
+    for (key, value) in command.get_envs() {
+        if let Some(value) = value {
+            words.push(format!(
+                "{}={}",
+                key.to_string_lossy(),
+                shell_quote(&value.to_string_lossy())
+            ));
+        }
+    }
+    words.push(shell_quote(&command.get_program().to_string_lossy()));
+    words.extend(command.get_args().map(|arg| shell_quote(&arg.to_string_lossy())));
+    words.join(" ")
+}
+
+/// Quote a word for the shell, if needed
+fn shell_quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_=+./:,@%".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_command_has_env() {
+        let manifest = Manifest { verbose: 2, ..Default::default() };
+        let mut command = manifest.rustc();
+        command.arg("hello world.rs");
+        let shell = shell_command(&command);
+        assert!(shell.contains("CFLAGS=-Irust_runtime"), "{}", shell);
+        assert!(shell.contains("RUST_BACKTRACE=full"), "{}", shell);
+        assert!(shell.contains("codegen-backend=crates/target/debug/librustc_codegen_c.so"));
+        assert!(shell.ends_with(" 'hello world.rs'"), "{}", shell);
+    }
+
+    #[test]
+    fn rustc_has_edition() {
+        let manifest = Manifest { edition: "2018".to_string(), ..Default::default() };
+        let shell = shell_command(&manifest.rustc());
+        assert!(shell.contains(" --edition 2018 "), "{}", shell);
+    }
+
+    #[test]
+    fn backend_override() {
+        let manifest = Manifest {
+            dry_run: true,
+            backend: Some(PathBuf::from("/tmp/bisect/librustc_codegen_c.so")),
+            ..Default::default()
+        };
+        let shell = shell_command(&manifest.rustc());
+        assert!(shell.contains("codegen-backend=/tmp/bisect/librustc_codegen_c.so"), "{}", shell);
+        assert!(manifest.backend_build_command().is_none());
+    }
+}
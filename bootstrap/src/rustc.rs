@@ -1,7 +1,9 @@
 use std::path::PathBuf;
+use std::process::Command;
 
 use clap::Args;
 
+use crate::compdb::Compdb;
 use crate::manifest::Manifest;
 use crate::Run;
 
@@ -10,6 +12,14 @@ use crate::Run;
 pub struct RustcCommand {
     source: PathBuf,
 
+    /// Also print the generated C to stdout, e.g. to pipe it into `clang-format`
+    #[arg(long)]
+    emit_stdout: bool,
+
+    /// Write a `compile_commands.json` of the generated C to this path
+    #[arg(long)]
+    compdb: Option<PathBuf>,
+
     #[arg(last = true)]
     slop: Vec<String>,
 }
@@ -17,7 +27,20 @@ pub struct RustcCommand {
 impl Run for RustcCommand {
     fn run(&self, manifest: &Manifest) {
         manifest.prepare();
+        let mut command = self.command(manifest);
+        let compdb = self.compdb.as_ref().map(|path| Compdb::new(manifest, path));
+        if let Some(compdb) = &compdb {
+            compdb.record(&mut command);
+        }
+        manifest.command_status(&mut command);
+        if let Some(compdb) = &compdb {
+            compdb.finish(manifest);
+        }
+    }
+}
 
+impl RustcCommand {
+    fn command(&self, manifest: &Manifest) -> Command {
         let mut command = manifest.rustc();
         command
             .arg(&self.source)
@@ -25,7 +48,30 @@ impl Run for RustcCommand {
             .arg("--out-dir")
             .arg(&manifest.out_dir)
             .args(&self.slop);
-        log::debug!("running {:?}", command);
-        command.status().unwrap();
+        if self.emit_stdout {
+            command.env("RUSTC_CODEGEN_C_EMIT", "stdout");
+        }
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::shell_command;
+
+    #[test]
+    fn emit_stdout_sets_env() {
+        let manifest = Manifest { dry_run: true, ..Default::default() };
+        let rustc = RustcCommand {
+            source: PathBuf::from("examples/basic_math.rs"),
+            emit_stdout: true,
+            compdb: None,
+            slop: vec![],
+        };
+        assert!(shell_command(&rustc.command(&manifest)).contains("RUSTC_CODEGEN_C_EMIT=stdout"));
+
+        let rustc = RustcCommand { emit_stdout: false, ..rustc };
+        assert!(!shell_command(&rustc.command(&manifest)).contains("RUSTC_CODEGEN_C_EMIT"));
     }
 }
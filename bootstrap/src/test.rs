@@ -1,13 +1,18 @@
+use std::fmt::Display;
 use std::fs::File;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
 
 use anstream::{eprint as print, eprintln as println};
 use clap::Args;
-use color_print::{cprint, cprintln};
+use color_print::{cformat, cprint, cprintln};
 use glob::glob;
 use similar::{ChangeTag, TextDiff};
 use which::which;
 
+use crate::compdb::Compdb;
 use crate::manifest::Manifest;
 use crate::Run;
 
@@ -17,105 +22,355 @@ pub struct TestCommand {
     /// Update the blessed output
     #[clap(long)]
     pub bless: bool,
+
+    /// List the discovered test cases and exit without building anything
+    #[clap(long)]
+    pub list: bool,
+
+    /// Only print the failures and the final summary
+    #[clap(short, long)]
+    pub quiet: bool,
+
+    /// Keep the generated C, the objects and the executable of each test in
+    /// `<out_dir>/temps/<test name>`
+    #[clap(long)]
+    pub save_temps: bool,
+
+    /// Write a `compile_commands.json` of the generated C to this path
+    #[clap(long)]
+    pub compdb: Option<PathBuf>,
+
+    /// Only run the previously built executables of the run tests, without
+    /// compiling anything
+    #[clap(long, conflicts_with_all = ["bless", "save_temps", "compdb"])]
+    pub run_only: bool,
 }
 
 impl Run for TestCommand {
     fn run(&self, manifest: &Manifest) {
+        if self.list {
+            for testcase in self.collect_testcases(manifest) {
+                print!("{}", testcase.describe());
+            }
+            return;
+        }
+        if self.run_only {
+            self.run_prebuilt(manifest);
+            return;
+        }
+
         manifest.prepare();
 
-        std::panic::set_hook(Box::new(|info| {
+        let mut reporter = Reporter::new(anstream::stderr(), self.quiet);
+        let failure = reporter.failure.clone();
+        std::panic::set_hook(Box::new(move |info| {
+            let failure = failure.lock().unwrap();
+            // the test being run is not announced in quiet mode
+            if let Some(current) = &failure.current {
+                println!("{}...", current);
+            }
             cprintln!("<r,s>Test failed</r,s>: {}", info);
+            if let Some(temps) = &failure.temps {
+                cprintln!("<b>[TEST]</b> temporaries saved in {}", temps.display());
+            }
         }));
 
-        cprintln!("<b>[TEST]</b> running cargo test");
+        reporter.log(cformat!("<b>[TEST]</b> running cargo test"));
         let mut command = std::process::Command::new("cargo");
         command.args(["test", "--manifest-path", "crates/Cargo.toml"]);
-        log::debug!("running {:?}", command);
-        assert!(command.status().unwrap().success(), "failed to run {:?}", command);
+        if self.quiet {
+            command.arg("--quiet");
+        }
+        assert!(manifest.command_status(&mut command).success(), "failed to run {:?}", command);
 
         let testcases = self.collect_testcases(manifest);
-        cprintln!("<b>[TEST]</b> found {} testcases", testcases.len());
+        reporter.log(cformat!("<b>[TEST]</b> found {} testcases", testcases.len()));
 
+        let compdb = self.compdb.as_ref().map(|path| Compdb::new(manifest, path));
         let filechecker = FileChecker::new();
+        let syntax_checker = SyntaxChecker::new();
+        if syntax_checker.is_none() {
+            reporter.log(cformat!("<b>[TEST]</b> <y>clang not found, skipping syntax checks</y>"));
+        }
         for testcase in testcases {
             match testcase.test {
                 TestType::FileCheck => {
-                    cprint!("File checking {}...", testcase.name);
-                    testcase.build(manifest);
-                    filechecker.run(&testcase);
+                    reporter.start(format!("File checking {}", testcase.name));
+                    self.build(manifest, &testcase, compdb.as_ref(), &mut reporter);
+                    filechecker.run(manifest, &testcase);
                 }
                 TestType::Bless => {
-                    cprint!("Blessing {}...", testcase.name);
-                    testcase.build(manifest);
-                    bless(self.bless, &testcase);
+                    reporter.start(format!("Blessing {}", testcase.name));
+                    self.build(manifest, &testcase, compdb.as_ref(), &mut reporter);
+                    if !manifest.dry_run {
+                        bless(self.bless, &testcase);
+                        testcase.check_deterministic(manifest);
+                    }
                 }
                 TestType::Compile => {
-                    cprint!("Compiling {}...", testcase.name);
-                    testcase.build(manifest);
+                    reporter.start(format!("Compiling {}", testcase.name));
+                    self.build(manifest, &testcase, compdb.as_ref(), &mut reporter);
                 }
                 TestType::CompileLib => {
-                    cprint!("Compiling lib {}...", testcase.name);
-                    testcase.build_lib(manifest);
+                    reporter.start(format!("Compiling lib {}", testcase.name));
+                    self.build(manifest, &testcase, compdb.as_ref(), &mut reporter);
+                }
+                TestType::Run => {
+                    reporter.start(format!("Running {}", testcase.name));
+                    self.build(manifest, &testcase, compdb.as_ref(), &mut reporter);
+                    testcase.check_and_run_directives(manifest);
+                }
+                TestType::SyntaxCheck => {
+                    reporter.start(format!("Syntax checking {}", testcase.name));
+                    let Some(syntax_checker) = &syntax_checker else {
+                        reporter.skip();
+                        continue;
+                    };
+                    syntax_checker.run(manifest, &testcase);
                 }
             }
-            cprintln!("<g>OK</g>");
+            reporter.pass();
+        }
+        if let Some(compdb) = &compdb {
+            compdb.finish(manifest);
+        }
+        reporter.summary();
+    }
+}
+
+/// Reports the progress of the test run
+///
+/// In quiet mode, only the final summary is printed, while the test being run
+/// is kept in `failure` so that it can be reported if it fails.
+struct Reporter<W> {
+    out: W,
+    quiet: bool,
+    passed: usize,
+    skipped: usize,
+    failure: Arc<Mutex<FailureContext>>,
+}
+
+/// What to print about the test being run if it fails
+#[derive(Default)]
+struct FailureContext {
+    /// The test being run, if not announced yet
+    current: Option<String>,
+    /// Where the temporaries of the test are saved
+    temps: Option<PathBuf>,
+}
+
+impl<W: Write> Reporter<W> {
+    fn new(out: W, quiet: bool) -> Self {
+        Reporter { out, quiet, passed: 0, skipped: 0, failure: Arc::default() }
+    }
+
+    /// Print a line of context, unless in quiet mode
+    fn log(&mut self, message: impl Display) {
+        if !self.quiet {
+            writeln!(self.out, "{}", message).unwrap();
+        }
+    }
+
+    /// Announce a test case about to run
+    fn start(&mut self, message: String) {
+        if self.quiet {
+            self.failure.lock().unwrap().current = Some(message);
+        } else {
+            write!(self.out, "{}...", message).unwrap();
+            self.out.flush().unwrap();
+        }
+    }
+
+    /// Record that the current test case passed
+    fn pass(&mut self) {
+        self.passed += 1;
+        *self.failure.lock().unwrap() = FailureContext::default();
+        self.log(cformat!("<g>OK</g>"));
+    }
+
+    /// Record that the current test case was skipped, e.g. for a missing tool
+    fn skip(&mut self) {
+        self.skipped += 1;
+        *self.failure.lock().unwrap() = FailureContext::default();
+        self.log(cformat!("<y>skipped</y>"));
+    }
+
+    /// Record where the temporaries of the current test case are saved
+    fn saved_temps(&mut self, temps: PathBuf) {
+        self.failure.lock().unwrap().temps = Some(temps);
+    }
+
+    /// Print the number of passed and skipped test cases, even in quiet mode
+    fn summary(&mut self) {
+        let mut summary = cformat!("<b>[TEST]</b> <g>{} passed</g>", self.passed);
+        if self.skipped > 0 {
+            summary.push_str(&cformat!(", <y>{} skipped</y>", self.skipped));
         }
+        writeln!(self.out, "{}", summary).unwrap();
     }
 }
 
 impl TestCommand {
-    pub fn collect_testcases(&self, manifest: &Manifest) -> Vec<TestCase> {
-        let mut tests = vec![];
-
-        // Examples
-        for case in glob("examples/*.rs").unwrap() {
-            let case = case.unwrap();
-            let filename = case.file_stem().unwrap();
-            let name = format!("examples/{}", filename.to_string_lossy());
-            let output_file = manifest.out_dir.join("examples").join(filename);
-            tests.push(TestCase { name, source: case, output_file, test: TestType::Compile })
-        }
-
-        // Codegen tests
-        for case in glob("tests/codegen/*.rs").unwrap() {
-            let case = case.unwrap();
-            let filename = case.file_stem().unwrap();
-            let name = format!("codegen/{}", filename.to_string_lossy());
-            let output_file = manifest.out_dir.join("tests/codegen").join(filename);
-            tests.push(TestCase { name, source: case, output_file, test: TestType::FileCheck })
-        }
-
-        // Bless tests - the output should be the same as the last run
-        for case in glob("tests/bless/*.rs").unwrap() {
-            let case = case.unwrap();
-            let filename = case.file_stem().unwrap();
-            let name = format!("bless/{}", filename.to_string_lossy());
-            let output_file = manifest.out_dir.join("tests/bless").join(filename);
-            tests.push(TestCase { name, source: case, output_file, test: TestType::Bless })
-        }
-
-        // Collect test-auxiliary
-        let aux_use = regex::Regex::new(r"^//@\s*aux-build:(?P<fname>.*)").unwrap();
-        let mut auxiliary = vec![];
-        for case in tests.iter() {
-            let source = std::fs::read_to_string(&case.source).unwrap();
-            for cap in aux_use.captures_iter(&source) {
-                let fname = cap.name("fname").unwrap().as_str();
-                let source = Path::new("tests/auxiliary").join(fname);
-                let filename = source.file_stem().unwrap();
-                let name = format!("auxiliary/{}", filename.to_string_lossy());
-                let output_file = manifest.out_dir.join(filename); // aux files are output to the base directory
-                auxiliary.push(TestCase { name, source, output_file, test: TestType::CompileLib })
+    /// Run the executables of the run tests as left by a previous test run,
+    /// skipping the other tests
+    fn run_prebuilt(&self, manifest: &Manifest) {
+        let mut reporter = Reporter::new(anstream::stderr(), self.quiet);
+        for testcase in self.collect_testcases(manifest) {
+            if let TestType::Run = testcase.test {
+                reporter.start(format!("Running {}", testcase.name));
+                testcase.run_prebuilt(manifest);
+                reporter.pass();
+            }
+        }
+        reporter.summary();
+    }
+
+    /// Build the test case, keeping its temporaries with `--save-temps` and
+    /// recording its C compiler invocations with `--compdb`
+    fn build(
+        &self,
+        manifest: &Manifest,
+        testcase: &TestCase,
+        compdb: Option<&Compdb>,
+        reporter: &mut Reporter<impl Write>,
+    ) {
+        let mut command = match testcase.test {
+            TestType::CompileLib => testcase.lib_command(manifest),
+            _ => testcase.bin_command(manifest),
+        };
+        if self.save_temps {
+            command.args(["-C", "save-temps"]);
+        }
+        if let Some(compdb) = compdb {
+            compdb.record(&mut command);
+        }
+        manifest.create_dir_all(testcase.output_file.parent().unwrap());
+        let status = if testcase.emits_stdout() {
+            // only the emitted C is captured, the diagnostics are still shown
+            command.stderr(Stdio::inherit());
+            let output = manifest.command_output(&mut command);
+            if !manifest.dry_run {
+                std::fs::write(testcase.build_stdout(), output.stdout).unwrap();
             }
+            output.status
+        } else {
+            manifest.command_status(&mut command)
+        };
+        if self.save_temps && !manifest.dry_run {
+            reporter.saved_temps(testcase.save_temps(manifest));
+        }
+        if !manifest.dry_run {
+            let build_fail = testcase.directives.contains(&TestDirective::BuildFail);
+            assert_eq!(
+                status.success(),
+                !build_fail,
+                "building {} exited with {}",
+                testcase.name,
+                status
+            );
+        }
+    }
+
+    pub fn collect_testcases(&self, manifest: &Manifest) -> Vec<TestCase> {
+        collect_testcases_in(Path::new(""), manifest)
+    }
+}
+
+/// Collect the test cases of the repository at `root`, which is joined onto
+/// the test sources, e.g. the empty path for the current directory
+fn collect_testcases_in(root: &Path, manifest: &Manifest) -> Vec<TestCase> {
+    let sources = |pattern: &str| glob(&root.join(pattern).to_string_lossy()).unwrap();
+    let mut tests = vec![];
+
+    // Examples
+    for case in sources("examples/*.rs") {
+        let case = case.unwrap();
+        let filename = case.file_stem().unwrap();
+        let name = format!("examples/{}", filename.to_string_lossy());
+        let output_file = manifest.out_dir.join("examples").join(filename);
+        tests.push(TestCase::new(name, case, output_file, TestType::Compile))
+    }
+
+    // Codegen tests
+    for case in sources("tests/codegen/*.rs") {
+        let case = case.unwrap();
+        let filename = case.file_stem().unwrap();
+        let name = format!("codegen/{}", filename.to_string_lossy());
+        let output_file = manifest.out_dir.join("tests/codegen").join(filename);
+        tests.push(TestCase::new(name, case, output_file, TestType::FileCheck))
+    }
+
+    // Bless tests - the output should be the same as the last run
+    for case in sources("tests/bless/*.rs") {
+        let case = case.unwrap();
+        let filename = case.file_stem().unwrap();
+        let name = format!("bless/{}", filename.to_string_lossy());
+        let output_file = manifest.out_dir.join("tests/bless").join(filename);
+        tests.push(TestCase::new(name, case, output_file, TestType::Bless))
+    }
+
+    // Syntax check tests - the blessed C, and the blessed modules of the
+    // AST printer tests, must be accepted by clang
+    let blessed_modules = "crates/rustc_codegen_c_ast/tests/blessed/test_module_*.out";
+    for case in sources("tests/bless/*.c").chain(sources(blessed_modules)) {
+        let case = case.unwrap();
+        let filename = case.file_stem().unwrap();
+        if SYNTAX_CHECK_SKIP.iter().any(|&skip| filename == skip) {
+            continue;
         }
+        let name = format!("syntax/{}", filename.to_string_lossy());
+        let output_file = manifest.out_dir.join("tests/syntax").join(filename);
+        tests.push(TestCase::new(name, case, output_file, TestType::SyntaxCheck))
+    }
 
-        // Compile auxiliary before the tests
-        let mut cases = auxiliary;
-        cases.extend(tests);
-        cases
+    // Run tests - the executable is run and its output checked
+    for case in sources("tests/run/*.rs") {
+        let case = case.unwrap();
+        let filename = case.file_stem().unwrap();
+        let name = format!("run/{}", filename.to_string_lossy());
+        let output_file = manifest.out_dir.join("tests/run").join(filename);
+        tests.push(TestCase::new(name, case, output_file, TestType::Run))
     }
+
+    // Compile auxiliary before the tests
+    let mut cases = collect_auxiliary(root, manifest, &tests);
+    cases.extend(tests);
+    cases
 }
 
+/// The blessed modules of the AST printer tests which are invalid C on purpose
+const SYNTAX_CHECK_SKIP: &[&str] = &[
+    // a failing `_Static_assert`
+    "test_module_enum_fit",
+    // an `#error` directive
+    "test_module_error",
+    // an include of a header which does not exist
+    "test_module_local_include",
+];
+
+/// Collect the auxiliary crates referenced by the `aux-build` directives of the tests
+fn collect_auxiliary(root: &Path, manifest: &Manifest, tests: &[TestCase]) -> Vec<TestCase> {
+    let mut auxiliary = vec![];
+    for case in tests {
+        for directive in &case.directives {
+            let TestDirective::AuxBuild(fname) = directive else { continue };
+            let source = root.join("tests/auxiliary").join(fname);
+            assert!(
+                source.exists(),
+                "auxiliary source not found: {} (referenced by {})",
+                source.display(),
+                case.source.display()
+            );
+            let filename = source.file_stem().unwrap();
+            let name = format!("auxiliary/{}", filename.to_string_lossy());
+            let output_file = manifest.aux_dir().join(filename);
+            auxiliary.push(TestCase::new(name, source, output_file, TestType::CompileLib))
+        }
+    }
+    auxiliary
+}
+
+#[derive(Debug)]
 pub enum TestType {
     /// Test an executable can be compiled
     Compile,
@@ -125,6 +380,74 @@ pub enum TestType {
     FileCheck,
     /// Bless test - the output should be the same as the last run
     Bless,
+    /// Test an executable can be compiled and run, and check its output
+    Run,
+    /// Test that C source is accepted by `clang -fsyntax-only`
+    SyntaxCheck,
+}
+
+/// A directive in a test source, written as `//@ name: value`, or `//@ name`
+/// for a flag
+#[derive(Debug, PartialEq)]
+pub enum TestDirective {
+    /// Build an auxiliary crate from `tests/auxiliary` before the test
+    AuxBuild(String),
+    /// A line of the expected standard output
+    CheckStdout(String),
+    /// A line of the expected standard error
+    CheckStderr(String),
+    /// A regex the standard error must match, for output varying between runs
+    RegexStderr(String),
+    /// The crate type to build an auxiliary crate as, e.g. `rlib` or `dylib`,
+    /// written in the auxiliary source itself. Repeat it for multiple crate types.
+    AuxCrateType(String),
+    /// Split the generated C by top-level module, expecting this many C files
+    SplitModules(usize),
+    /// An environment variable set when building the test, written as
+    /// `NAME=value`, e.g. to enable an option of the codegen backend
+    RustcEnv(String, String),
+    /// Expect the executable to exit unsuccessfully, e.g. by aborting
+    RunFail,
+    /// Expect the build to fail, e.g. on the `#error` of an unsupported
+    /// construct, while the generated C is still file checked
+    BuildFail,
+}
+
+/// Parse the directives of a test source
+pub fn parse_directives(source: &str) -> Vec<TestDirective> {
+    let directive = regex::Regex::new(r"^//@\s*(?P<name>[\w-]+)(:(?P<value>.*))?$").unwrap();
+    let mut directives = vec![];
+    for line in source.lines() {
+        let Some(cap) = directive.captures(line) else { continue };
+        let value = cap.name("value").map_or("", |value| value.as_str());
+        // a single space after the colon is only a separator, the rest is kept as-is
+        let line = value.strip_prefix(' ').unwrap_or(value).to_string();
+        match cap.name("name").unwrap().as_str() {
+            "aux-build" => directives.push(TestDirective::AuxBuild(value.trim().to_string())),
+            "check-stdout" => directives.push(TestDirective::CheckStdout(line)),
+            "check-stderr" => directives.push(TestDirective::CheckStderr(line)),
+            "regex-stderr" => directives.push(TestDirective::RegexStderr(line)),
+            "aux-crate-type" => {
+                directives.push(TestDirective::AuxCrateType(value.trim().to_string()))
+            }
+            "split-modules" => {
+                let files = value.trim().parse().unwrap_or_else(|_| {
+                    panic!("`split-modules` expects a number of files, got `{}`", value.trim())
+                });
+                directives.push(TestDirective::SplitModules(files))
+            }
+            "rustc-env" => {
+                let (name, value) = value.trim().split_once('=').unwrap_or_else(|| {
+                    panic!("`rustc-env` expects `NAME=value`, got `{}`", value.trim())
+                });
+                directives.push(TestDirective::RustcEnv(name.to_string(), value.to_string()))
+            }
+            "run-fail" => directives.push(TestDirective::RunFail),
+            "build-fail" => directives.push(TestDirective::BuildFail),
+            name => panic!("unknown directive `{}`", name),
+        }
+    }
+    directives
 }
 
 pub struct TestCase {
@@ -132,12 +455,96 @@ pub struct TestCase {
     pub source: PathBuf,
     pub output_file: PathBuf,
     pub test: TestType,
+    pub directives: Vec<TestDirective>,
 }
 
 impl TestCase {
+    pub fn new(name: String, source: PathBuf, output_file: PathBuf, test: TestType) -> Self {
+        let directives = parse_directives(&std::fs::read_to_string(&source).unwrap());
+        Self { name, source, output_file, test, directives }
+    }
+
+    /// The expected standard output, joined from the `check-stdout` directives
+    ///
+    /// Returns `None` if there are no such directives, i.e. the output is not checked.
+    pub fn expected_stdout(&self) -> Option<String> {
+        join_lines(self.directives.iter().filter_map(|directive| match directive {
+            TestDirective::CheckStdout(line) => Some(line),
+            _ => None,
+        }))
+    }
+
+    /// The expected standard error, joined from the `check-stderr` directives
+    ///
+    /// Returns `None` if there are no such directives, i.e. the output is not checked.
+    pub fn expected_stderr(&self) -> Option<String> {
+        join_lines(self.directives.iter().filter_map(|directive| match directive {
+            TestDirective::CheckStderr(line) => Some(line),
+            _ => None,
+        }))
+    }
+
+    /// The number of C files expected from the `split-modules` directive, if any
+    pub fn split_modules(&self) -> Option<usize> {
+        self.directives.iter().find_map(|directive| match directive {
+            TestDirective::SplitModules(files) => Some(*files),
+            _ => None,
+        })
+    }
+
+    /// Whether the generated C is also printed to the standard output of the
+    /// build, with `RUSTC_CODEGEN_C_EMIT=stdout` in a `rustc-env` directive, in
+    /// which case the captured output is file checked instead of the C file
+    pub fn emits_stdout(&self) -> bool {
+        self.directives.iter().any(|directive| {
+            matches!(directive, TestDirective::RustcEnv(name, value)
+                if name == "RUSTC_CODEGEN_C_EMIT" && value == "stdout")
+        })
+    }
+
+    /// The file the standard output of the build is captured to, see
+    /// [`Self::emits_stdout`]
+    pub fn build_stdout(&self) -> PathBuf {
+        self.output_file.with_extension("stdout")
+    }
+
+    /// Describe the test case as listed by `--list`
+    pub fn describe(&self) -> String {
+        let mut description = format!(
+            "{} ({:?})\n  source: {}\n  output: {}\n",
+            self.name,
+            self.test,
+            self.source.display(),
+            self.output_file.display()
+        );
+        for directive in &self.directives {
+            description.push_str(&format!("  directive: {:?}\n", directive));
+        }
+        description
+    }
+
+    /// Check the standard error against the `regex-stderr` directives
+    ///
+    /// Returns the first pattern not matching, if any.
+    pub fn unmatched_stderr_regex(&self, stderr: &str) -> Option<&str> {
+        self.directives.iter().find_map(|directive| match directive {
+            TestDirective::RegexStderr(pattern) => {
+                let regex = regex::Regex::new(pattern)
+                    .unwrap_or_else(|e| panic!("invalid regex-stderr in {}: {}", self.name, e));
+                (!regex.is_match(stderr)).then_some(pattern.as_str())
+            }
+            _ => None,
+        })
+    }
+
     pub fn build(&self, manifest: &Manifest) {
-        let output_dir = self.output_file.parent().unwrap();
-        std::fs::create_dir_all(output_dir).unwrap();
+        manifest.create_dir_all(self.output_file.parent().unwrap());
+        manifest.command_status(&mut self.bin_command(manifest));
+    }
+
+    /// The command to build the test case as an executable, split into one C
+    /// file per top-level module with the `split-modules` directive
+    fn bin_command(&self, manifest: &Manifest) -> std::process::Command {
         let mut command = manifest.rustc();
         command
             .args(["--crate-type", "bin"])
@@ -145,38 +552,153 @@ impl TestCase {
             .arg(&self.source)
             .arg("-o")
             .arg(&self.output_file);
-        log::debug!("running {:?}", command);
-        command.status().unwrap();
+        if self.split_modules().is_some() {
+            command.env("RUSTC_CODEGEN_C_SPLIT_MODULES", "1");
+        }
+        for directive in &self.directives {
+            if let TestDirective::RustcEnv(name, value) = directive {
+                command.env(name, value);
+            }
+        }
+        command
     }
 
-    pub fn build_lib(&self, manifest: &Manifest) {
-        let output_dir = self.output_file.parent().unwrap();
-        std::fs::create_dir_all(output_dir).unwrap();
+    /// The command to build the test case as a library, with the crate types
+    /// from the `aux-crate-type` directives, `lib` by default
+    fn lib_command(&self, manifest: &Manifest) -> std::process::Command {
+        let mut crate_types = self
+            .directives
+            .iter()
+            .filter_map(|directive| match directive {
+                TestDirective::AuxCrateType(crate_type) => Some(crate_type.as_str()),
+                _ => None,
+            })
+            .peekable();
         let mut command = manifest.rustc();
+        if crate_types.peek().is_none() {
+            command.args(["--crate-type", "lib"]);
+        }
+        for crate_type in crate_types {
+            command.args(["--crate-type", crate_type]);
+        }
         command
-            .args(["--crate-type", "lib"])
             .arg("-O")
             .arg(&self.source)
             .arg("--out-dir") // we use `--out-dir` to integrate with the default name convention
-            .arg(output_dir); // so here we ignore the filename and just use the directory
-        log::debug!("running {:?}", command);
-        command.status().unwrap();
+            .arg(self.output_file.parent().unwrap()); // so here we ignore the filename and just use the directory
+        command
+    }
+
+    /// Run the compiled executable and check its output against the directives
+    pub fn check_and_run_directives(&self, manifest: &Manifest) {
+        let mut command = std::process::Command::new(&self.output_file);
+        let output = manifest.command_output(&mut command);
+        if manifest.dry_run {
+            return;
+        }
+        let run_fail = self.directives.contains(&TestDirective::RunFail);
+        assert_eq!(
+            output.status.success(),
+            !run_fail,
+            "{} exited with {}",
+            self.name,
+            output.status
+        );
+
+        if let Some(files) = self.split_modules() {
+            let generated = self.generated_files();
+            assert_eq!(
+                generated.len(),
+                files,
+                "{} is expected to be split into {} C files, found {:?}",
+                self.name,
+                files,
+                generated
+            );
+        }
+
+        for (stream, expected, actual) in [
+            ("stdout", self.expected_stdout(), output.stdout),
+            ("stderr", self.expected_stderr(), output.stderr),
+        ] {
+            let Some(expected) = expected else { continue };
+            let actual = String::from_utf8_lossy(&actual);
+            if !print_diff(&expected, &actual, &format!("{} does not match", stream)) {
+                panic!("{} does not match the expected {}", self.name, stream);
+            }
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if let Some(pattern) = self.unmatched_stderr_regex(&stderr) {
+            panic!("stderr of {} does not match `{}`:\n{}", self.name, pattern, stderr);
+        }
+    }
+
+    /// Run the executable built by a previous test run and check its output
+    /// against the directives
+    pub fn run_prebuilt(&self, manifest: &Manifest) {
+        assert!(
+            manifest.dry_run || self.output_file.is_file(),
+            "{} has not been built, run the tests without `--run-only` first: {} is missing",
+            self.name,
+            self.output_file.display()
+        );
+        self.check_and_run_directives(manifest);
+    }
+
+    /// Build the test again and check that the generated C is byte-identical,
+    /// e.g. that the locals are numbered the same way
+    pub fn check_deterministic(&self, manifest: &Manifest) {
+        let first = std::fs::read_to_string(self.generated()).unwrap();
+        self.build(manifest);
+        let second = std::fs::read_to_string(self.generated()).unwrap();
+        if !print_diff(&first, &second, "output differs between two builds") {
+            panic!("{} is not built deterministically", self.name);
+        }
+    }
+
+    /// Copy the generated C files and header, the objects and the executable of the test
+    /// case into `<out_dir>/temps/<name>`, and return that directory
+    pub fn save_temps(&self, manifest: &Manifest) -> PathBuf {
+        let temps = manifest.out_dir.join("temps").join(&self.name);
+        manifest.create_dir_all(&temps);
+        let case = self.source.file_stem().unwrap().to_string_lossy();
+        for entry in std::fs::read_dir(self.output_file.parent().unwrap()).unwrap() {
+            let path = entry.unwrap().path();
+            let filename = path.file_name().unwrap().to_string_lossy();
+            let intermediate = filename.starts_with(case.as_ref())
+                && [".c", ".h", ".o"].iter().any(|ext| filename.ends_with(ext));
+            if intermediate || path == self.output_file {
+                std::fs::copy(&path, temps.join(path.file_name().unwrap())).unwrap();
+            }
+        }
+        temps
     }
 
     /// Get the generated C file f
     pub fn generated(&self) -> PathBuf {
         let case = self.source.file_stem().unwrap().to_string_lossy();
-        let generated = std::fs::read_dir(self.output_file.parent().unwrap())
+        let generated = self.generated_files().into_iter().next();
+
+        assert!(generated.is_some(), "could not find {case}'s generated file");
+        generated.unwrap()
+    }
+
+    /// Get all the generated C files, sorted, e.g. one per top-level module
+    /// with the `split-modules` directive
+    pub fn generated_files(&self) -> Vec<PathBuf> {
+        let case = self.source.file_stem().unwrap().to_string_lossy();
+        let mut generated: Vec<_> = std::fs::read_dir(self.output_file.parent().unwrap())
             .unwrap()
             .filter_map(|entry| entry.ok())
-            .find(|entry| {
-                let filename = entry.file_name();
-                let filename = filename.to_string_lossy();
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let filename = path.file_name().unwrap().to_string_lossy();
                 filename.ends_with(".c") && filename.starts_with(case.as_ref())
-            });
-
-        assert!(generated.is_some(), "could not find {case}'s generated file");
-        generated.unwrap().path()
+            })
+            .collect();
+        generated.sort();
+        generated
     }
 }
 
@@ -201,12 +723,15 @@ impl FileChecker {
         Self { filecheck }
     }
 
-    fn run(&self, case: &TestCase) {
-        let generated = File::open(case.generated()).unwrap();
+    fn run(&self, manifest: &Manifest, case: &TestCase) {
         let mut command = std::process::Command::new(&self.filecheck);
-        command.arg(&case.source).stdin(generated);
-        log::debug!("running {:?}", command);
-        let output = command.output().unwrap();
+        command.arg(&case.source);
+        if !manifest.dry_run {
+            // the generated file only exists if the test case was actually built
+            let checked = if case.emits_stdout() { case.build_stdout() } else { case.generated() };
+            command.stdin(File::open(checked).unwrap());
+        }
+        let output = manifest.command_output(&mut command);
         assert!(
             output.status.success(),
             "failed to run FileCheck on {}",
@@ -215,6 +740,35 @@ impl FileChecker {
     }
 }
 
+/// Checks that C source parses, to catch the printer emitting invalid C, e.g.
+/// a malformed declarator
+struct SyntaxChecker {
+    clang: PathBuf,
+}
+
+impl SyntaxChecker {
+    /// Find clang, returning `None` if it is not installed
+    pub fn new() -> Option<Self> {
+        ["clang-18", "clang-17", "clang-16", "clang-15", "clang-14", "clang"]
+            .into_iter()
+            .find_map(|clang| which(clang).ok())
+            .map(|clang| Self { clang })
+    }
+
+    fn run(&self, manifest: &Manifest, case: &TestCase) {
+        let mut command = std::process::Command::new(&self.clang);
+        // the blessed modules of the AST printer tests have a `.out` extension
+        command.args(["-fsyntax-only", "-x", "c"]).arg(&case.source);
+        let output = manifest.command_output(&mut command);
+        assert!(
+            output.status.success(),
+            "{} is not valid C:\n{}",
+            case.source.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
 fn bless(update: bool, case: &TestCase) {
     let output = case.generated();
     let blessed = case.source.with_extension("c");
@@ -224,18 +778,281 @@ fn bless(update: bool, case: &TestCase) {
         let output = std::fs::read_to_string(output).unwrap();
         let blessed = std::fs::read_to_string(blessed).unwrap();
 
-        let diff = TextDiff::from_lines(&blessed, &output);
-        if diff.ratio() < 1.0 {
-            cprintln!("<r,s>output does not match blessed output</r,s>");
-            for change in diff.iter_all_changes() {
-                let lineno = change.old_index().unwrap_or(change.new_index().unwrap_or(0));
-                match change.tag() {
-                    ChangeTag::Equal => print!(" {:4}| {}", lineno, change),
-                    ChangeTag::Insert => cprint!("<g>+{:4}| {}</g>", lineno, change),
-                    ChangeTag::Delete => cprint!("<r>-{:4}| {}</r>", lineno, change),
-                }
-            }
+        if !print_diff(&blessed, &output, "output does not match blessed output") {
             std::process::exit(1);
         }
     }
 }
+
+/// Join lines into a newline-terminated text
+///
+/// Returns `None` if there are no lines.
+fn join_lines<'a>(lines: impl Iterator<Item = &'a String>) -> Option<String> {
+    let mut text = None;
+    for line in lines {
+        let text = text.get_or_insert_with(String::new);
+        text.push_str(line);
+        text.push('\n');
+    }
+    text
+}
+
+/// Compare the expected and actual text, printing the message and the diff if they differ
+///
+/// Returns whether they are the same.
+fn print_diff(expected: &str, actual: &str, message: &str) -> bool {
+    let diff = TextDiff::from_lines(expected, actual);
+    if diff.ratio() == 1.0 {
+        return true;
+    }
+    cprintln!("<r,s>{}</r,s>", message);
+    for change in diff.iter_all_changes() {
+        let lineno = change.old_index().unwrap_or(change.new_index().unwrap_or(0));
+        match change.tag() {
+            ChangeTag::Equal => print!(" {:4}| {}", lineno, change),
+            ChangeTag::Insert => cprint!("<g>+{:4}| {}</g>", lineno, change),
+            ChangeTag::Delete => cprint!("<r>-{:4}| {}</r>", lineno, change),
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A test case named like `run/hello`, with its source in `tests` and its
+    /// output in `build/tests`, as collected from the repository
+    fn case(name: &str, test: TestType, directives: &str) -> TestCase {
+        TestCase {
+            name: name.to_string(),
+            source: PathBuf::from(format!("tests/{name}.rs")),
+            output_file: PathBuf::from(format!("build/tests/{name}")),
+            test,
+            directives: parse_directives(directives),
+        }
+    }
+
+    #[test]
+    fn check_stdout_accumulates_lines() {
+        let source =
+            "//@ check-stdout: first\n//@ check-stdout:  second\n//@ check-stdout: third\n";
+        let case = case("run/multi_line", TestType::Run, source);
+        assert_eq!(case.expected_stdout().as_deref(), Some("first\n second\nthird\n"));
+        assert_eq!(case.expected_stderr(), None);
+    }
+
+    #[test]
+    fn aux_crate_type_rlib() {
+        let manifest = Manifest { dry_run: true, ..Default::default() };
+        let case = TestCase {
+            output_file: PathBuf::from("build/aux/shared"),
+            ..case("auxiliary/shared", TestType::CompileLib, "//@ aux-crate-type: rlib\n")
+        };
+        let args: Vec<_> =
+            case.lib_command(&manifest).get_args().map(|arg| arg.to_owned()).collect();
+        assert!(args.windows(2).any(|args| args == ["--crate-type", "rlib"]));
+        assert!(!args.iter().any(|arg| arg == "lib"));
+    }
+
+    #[test]
+    fn rustc_env_directive() {
+        let directives = parse_directives("//@ rustc-env: RUSTC_CODEGEN_C_FOO=a=b\n");
+        assert_eq!(
+            directives,
+            [TestDirective::RustcEnv("RUSTC_CODEGEN_C_FOO".to_string(), "a=b".to_string())]
+        );
+    }
+
+    #[test]
+    fn emit_stdout_directive() {
+        let case = |source: &str| case("codegen/emit_stdout", TestType::FileCheck, source);
+        let emitted = case("//@ rustc-env: RUSTC_CODEGEN_C_EMIT=stdout\n");
+        assert!(emitted.emits_stdout());
+        assert_eq!(emitted.build_stdout(), PathBuf::from("build/tests/codegen/emit_stdout.stdout"));
+        assert!(!case("//@ rustc-env: RUSTC_CODEGEN_C_FOO=stdout\n").emits_stdout());
+    }
+
+    #[test]
+    fn run_fail_directive() {
+        let directives = parse_directives("//@ run-fail\n//@ aux-build:mini_core.rs\n");
+        assert_eq!(
+            directives,
+            [TestDirective::RunFail, TestDirective::AuxBuild("mini_core.rs".to_string())]
+        );
+    }
+
+    #[test]
+    fn build_fail_directive() {
+        let directives = parse_directives("//@ build-fail\n//@ aux-build:mini_core.rs\n");
+        assert_eq!(
+            directives,
+            [TestDirective::BuildFail, TestDirective::AuxBuild("mini_core.rs".to_string())]
+        );
+    }
+
+    #[test]
+    fn split_modules_sets_env() {
+        let manifest = Manifest { dry_run: true, ..Default::default() };
+        let case = case("run/split_modules", TestType::Run, "//@ split-modules: 2\n");
+        assert_eq!(case.split_modules(), Some(2));
+        let command = case.bin_command(&manifest);
+        assert!(command
+            .get_envs()
+            .any(|(key, value)| { key == "RUSTC_CODEGEN_C_SPLIT_MODULES" && value.is_some() }));
+    }
+
+    #[test]
+    fn regex_stderr_matches_panic() {
+        let source = "//@ regex-stderr: ^thread 'main' panicked at .+:\\d+:\\d+:\n";
+        let case = case("run/panic", TestType::Run, source);
+        let stderr = "thread 'main' panicked at src/main.rs:4:5:\nexplicit panic\n";
+        assert_eq!(case.unmatched_stderr_regex(stderr), None);
+        assert_eq!(
+            case.unmatched_stderr_regex("explicit panic\n"),
+            Some(r"^thread 'main' panicked at .+:\d+:\d+:")
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "auxiliary source not found: tests/auxiliary/missing.rs (referenced by tests/run/uses_missing.rs)"
+    )]
+    fn missing_auxiliary() {
+        let manifest = Manifest { dry_run: true, ..Default::default() };
+        let case = case("run/uses_missing", TestType::Run, "//@ aux-build: missing.rs\n");
+        collect_auxiliary(Path::new(""), &manifest, &[case]);
+    }
+
+    /// The root of the repository, holding the test sources
+    fn repo_root() -> &'static Path {
+        Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap()
+    }
+
+    #[test]
+    fn list_known_testcases() {
+        let manifest = Manifest { dry_run: true, ..Default::default() };
+        let listing: String =
+            collect_testcases_in(repo_root(), &manifest).iter().map(TestCase::describe).collect();
+
+        let source = repo_root().join("examples/basic_math.rs");
+        assert!(listing.contains("examples/basic_math (Compile)\n"));
+        assert!(listing.contains(&format!("  source: {}\n", source.display())));
+        assert!(listing.contains("  output: build/examples/basic_math\n"));
+        assert!(listing.contains("bless/basic_math (Bless)\n"));
+        assert!(listing.contains("auxiliary/mini_core (CompileLib)\n"));
+        assert!(listing.contains("  directive: AuxBuild(\"mini_core.rs\")\n"));
+    }
+
+    #[test]
+    fn aux_outputs_are_isolated() {
+        let manifest = Manifest { dry_run: true, ..Default::default() };
+        let (aux, tests): (Vec<_>, Vec<_>) = collect_testcases_in(repo_root(), &manifest)
+            .into_iter()
+            .partition(|case| matches!(case.test, TestType::CompileLib));
+
+        assert!(!aux.is_empty());
+        for case in &aux {
+            assert_eq!(case.output_file.parent(), Some(manifest.aux_dir().as_path()));
+        }
+        for case in &tests {
+            assert!(!case.output_file.starts_with(manifest.aux_dir()), "{}", case.name);
+        }
+    }
+
+    #[test]
+    fn quiet_prints_only_summary() {
+        let mut reporter = Reporter::new(Vec::new(), true);
+        reporter.log("context");
+        for name in ["run/a", "run/b"] {
+            reporter.start(format!("Running {}", name));
+            reporter.pass();
+        }
+        reporter.summary();
+        let output = String::from_utf8(reporter.out).unwrap();
+        let summary = cformat!("<b>[TEST]</b> <g>2 passed</g>\n");
+        assert_eq!(output, summary);
+        assert!(reporter.failure.lock().unwrap().current.is_none());
+    }
+
+    #[test]
+    fn summary_counts_skipped() {
+        let mut reporter = Reporter::new(Vec::new(), true);
+        reporter.start("Syntax checking syntax/a".to_string());
+        reporter.pass();
+        reporter.start("Syntax checking syntax/b".to_string());
+        reporter.skip();
+        reporter.summary();
+        let output = String::from_utf8(reporter.out).unwrap();
+        let summary = cformat!("<b>[TEST]</b> <g>1 passed</g>, <y>1 skipped</y>\n");
+        assert_eq!(output, summary);
+    }
+
+    #[test]
+    fn syntax_checks_skip_invalid_modules() {
+        let manifest = Manifest { dry_run: true, ..Default::default() };
+        let names: Vec<_> = collect_testcases_in(repo_root(), &manifest)
+            .into_iter()
+            .filter(|case| matches!(case.test, TestType::SyntaxCheck))
+            .map(|case| case.name)
+            .collect();
+
+        assert!(names.contains(&"syntax/basic_math".to_string()));
+        assert!(names.contains(&"syntax/test_module_pragma".to_string()));
+        for skip in SYNTAX_CHECK_SKIP {
+            assert!(!names.contains(&format!("syntax/{skip}")), "{skip}");
+        }
+    }
+
+    #[test]
+    fn save_temps_keeps_artifacts() {
+        let out_dir = crate::test_dir("rustc_codegen_c_save_temps");
+        let manifest = Manifest { out_dir: out_dir.clone(), ..Default::default() };
+        let case = TestCase {
+            output_file: out_dir.join("tests/run/hello"),
+            ..case("run/hello", TestType::Run, "")
+        };
+        let artifacts = ["hello", "hello.hello.0.c", "hello.hello.0.o"];
+        std::fs::create_dir_all(out_dir.join("tests/run")).unwrap();
+        for file in artifacts.iter().chain(&["other.c"]) {
+            std::fs::write(out_dir.join("tests/run").join(file), "").unwrap();
+        }
+
+        let temps = case.save_temps(&manifest);
+        assert_eq!(temps, out_dir.join("temps/run/hello"));
+        for file in artifacts {
+            assert!(temps.join(file).exists(), "{} was not saved", file);
+        }
+        assert!(!temps.join("other.c").exists());
+    }
+
+    #[test]
+    fn run_only_uses_prebuilt_binary() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let out_dir = crate::test_dir("rustc_codegen_c_run_only");
+        let manifest = Manifest { out_dir: out_dir.clone(), ..Default::default() };
+        // the source does not exist, so building it with rustc would fail
+        let case = TestCase {
+            output_file: out_dir.join("tests/run/prebuilt"),
+            ..case("run/prebuilt", TestType::Run, "//@ check-stdout: prebuilt\n")
+        };
+        std::fs::create_dir_all(out_dir.join("tests/run")).unwrap();
+        std::fs::write(&case.output_file, "#!/bin/sh\necho prebuilt\n").unwrap();
+        std::fs::set_permissions(&case.output_file, std::fs::Permissions::from_mode(0o755))
+            .unwrap();
+
+        case.run_prebuilt(&manifest);
+    }
+
+    #[test]
+    #[should_panic(expected = "run/unbuilt has not been built")]
+    fn run_only_missing_binary() {
+        let manifest = Manifest::default();
+        let case = TestCase {
+            output_file: PathBuf::from("build/tests/run/unbuilt/missing"),
+            ..case("run/unbuilt", TestType::Run, "")
+        };
+        case.run_prebuilt(&manifest);
+    }
+}
@@ -1,5 +1,6 @@
 use std::time::Instant;
 
+use rustc_codegen_c_ast::pretty::IndentStyle;
 use rustc_codegen_c_ast::{ModuleArena, ModuleCtx};
 use rustc_codegen_ssa::mono_item::MonoItemExt;
 use rustc_codegen_ssa::{ModuleCodegen, ModuleKind};
@@ -8,10 +9,15 @@ use rustc_middle::ty::TyCtxt;
 
 use crate::builder::Builder;
 use crate::context::CodegenCx;
+use crate::partition;
 
 /// Needed helper functions
 const HELPER: &str = include_str!("./helper.h");
 
+/// The environment variable selecting the indentation of the generated C,
+/// `tabs` to indent with tabs and align with spaces, spaces otherwise.
+const INDENT_ENV: &str = "RUSTC_CODEGEN_C_INDENT";
+
 // note: parallel
 // it seems this function will be invoked parallelly (if parallel codegen is enabled)
 
@@ -45,6 +51,14 @@ fn module_codegen(tcx: TyCtxt<'_>, cgu_name: rustc_span::Symbol) -> ModuleCodege
     let mcx = ModuleArena::new(HELPER);
     let mcx = ModuleCtx(&mcx);
     let cx = CodegenCx::new(tcx, mcx);
+    if std::env::var(INDENT_ENV).is_ok_and(|indent| indent == "tabs") {
+        mcx.module().set_indent_style(IndentStyle::Tabs);
+    }
+    if partition::split_modules() {
+        let header = partition::header_path(tcx);
+        let header = header.file_name().unwrap().to_string_lossy();
+        mcx.module().push_local_include(mcx.alloc_str(&header));
+    }
 
     let mono_items = cgu.items_in_deterministic_order(tcx);
     for &(mono_item, data) in &mono_items {
@@ -3,8 +3,13 @@
 use std::ops::Deref;
 
 use rustc_abi::{HasDataLayout, TargetDataLayout};
+use rustc_codegen_c_ast::expr::{CExpr, CValue};
 use rustc_codegen_c_ast::func::CFunc;
-use rustc_codegen_ssa::traits::{BackendTypes, BuilderMethods, HasCodegen};
+use rustc_codegen_c_ast::ty::CTy;
+use rustc_codegen_ssa::common::IntPredicate;
+use rustc_codegen_ssa::traits::{BackendTypes, BuilderMethods, HasCodegen, LayoutTypeMethods};
+use rustc_middle::bug;
+use rustc_middle::mir::Location;
 use rustc_middle::ty::layout::{
     FnAbiError, FnAbiOfHelpers, FnAbiRequest, HasParamEnv, HasTyCtxt, LayoutError, LayoutOfHelpers,
     TyAndLayout,
@@ -31,6 +36,86 @@ pub struct Builder<'a, 'tcx, 'mx> {
     bb: CFunc<'mx>,
 }
 
+impl<'a, 'tcx, 'mx> Builder<'a, 'tcx, 'mx> {
+    /// Define a new local variable of type `ty` in the current function,
+    /// initialized with `init`.
+    ///
+    /// The locals are numbered in the order the MIR is lowered, which is
+    /// stable across runs.
+    fn define_local(&mut self, ty: CTy<'mx>, init: CExpr<'mx>) -> CValue<'mx> {
+        let mcx = self.cx.mcx;
+        let val = self.bb.0.next_local_var();
+        self.bb.0.push_stmt(mcx.decl_stmt(mcx.var(val, ty, Some(init))));
+        self.cx.local_tys.borrow_mut().insert((self.bb, val), ty);
+        val
+    }
+
+    /// Get the C type of a parameter or local variable of the current function.
+    fn local_ty(&self, val: CValue<'mx>) -> CTy<'mx> {
+        if let Some(&(ty, _)) = self.bb.0.params.iter().find(|&&(_, param)| param == val) {
+            return ty;
+        }
+        match self.cx.local_tys.borrow().get(&(self.bb, val)) {
+            Some(&ty) => ty,
+            None => bug!("the type of {val:?} is unknown"),
+        }
+    }
+
+    /// Find the MIR location, e.g. `bb2[3]`, of the statement or terminator
+    /// with `span` in the current function, as the builder is only told the
+    /// span of what it lowers.
+    ///
+    /// Several statements may share a span, e.g. those of a desugared
+    /// expression, so the first one after the last annotated location is
+    /// preferred.
+    fn mir_location(&self, span: rustc_span::Span) -> Option<Location> {
+        let instance = self
+            .cx
+            .function_instances
+            .borrow()
+            .iter()
+            .find_map(|(&instance, &func)| (func == self.bb).then_some(instance))?;
+        let body = self.tcx.instance_mir(instance.def);
+        let locations: Vec<_> = body
+            .basic_blocks
+            .iter_enumerated()
+            .flat_map(|(block, data)| {
+                let terminator = data.terminator.as_ref().map(|terminator| terminator.source_info);
+                data.statements
+                    .iter()
+                    .map(|statement| statement.source_info)
+                    .chain(terminator)
+                    .enumerate()
+                    .filter(move |(_, source_info)| source_info.span == span)
+                    .map(move |(statement_index, _)| Location { block, statement_index })
+            })
+            .collect();
+        let last = match *self.cx.last_location.borrow() {
+            Some((func, location)) if func == self.bb => Some(location),
+            _ => None,
+        };
+        let after_last = |location: &&Location| {
+            last.map_or(true, |last| {
+                (location.block, location.statement_index) > (last.block, last.statement_index)
+            })
+        };
+        locations.iter().find(after_last).or(locations.first()).copied()
+    }
+
+    /// Get the C type of the operands of a binary operation, which have the
+    /// same type.
+    ///
+    /// Only locals have a known type, and a constant operand, e.g. `200` in
+    /// `200u8 + x`, takes the type of the other operand.
+    fn binop_ty(&self, lhs: CValue<'mx>, rhs: CValue<'mx>) -> CTy<'mx> {
+        match (lhs, rhs) {
+            (CValue::Local(_), _) => self.local_ty(lhs),
+            (_, CValue::Local(_)) => self.local_ty(rhs),
+            _ => bug!("binary operation on two constants {lhs:?} and {rhs:?}"),
+        }
+    }
+}
+
 impl<'a, 'tcx, 'mx> Deref for Builder<'a, 'tcx, 'mx> {
     type Target = CodegenCx<'tcx, 'mx>;
 
@@ -113,7 +198,21 @@ impl<'a, 'tcx, 'mx> BuilderMethods<'a, 'tcx> for Builder<'a, 'tcx, 'mx> {
         todo!()
     }
 
-    fn set_span(&mut self, _span: rustc_span::Span) {}
+    fn set_span(&mut self, span: rustc_span::Span) {
+        if !self.cx.location_comments {
+            return;
+        }
+        let Some(location) = self.mir_location(span) else { return };
+        // the last location is kept along with its function, so that the first
+        // statement of a function is annotated even if it has the same location
+        // as the last statement of the previous function
+        let mut last_location = self.cx.last_location.borrow_mut();
+        if *last_location != Some((self.bb, location)) {
+            let mcx = self.cx.mcx;
+            self.bb.0.push_stmt(mcx.comment(mcx.alloc_str(&format!("{location:?}"))));
+            *last_location = Some((self.bb, location));
+        }
+    }
 
     fn append_block(cx: &'a Self::CodegenCx, llfn: Self::Function, name: &str) -> Self::BasicBlock {
         // assume there is only one basic block
@@ -134,7 +233,17 @@ impl<'a, 'tcx, 'mx> BuilderMethods<'a, 'tcx> for Builder<'a, 'tcx, 'mx> {
     }
 
     fn ret(&mut self, v: Self::Value) {
-        self.bb.0.push_stmt(self.cx.mcx.ret(Some(self.cx.mcx.value(v))))
+        // a zero-sized return value is never materialized, see `ret_void`
+        if self.bb.0.ty == CTy::Void {
+            bug!("`{}` returns `void`, not a value", self.bb.0.name);
+        }
+        let mcx = self.cx.mcx;
+        let ret = match v {
+            // constants are converted implicitly, and only locals have a known type
+            CValue::Local(_) => mcx.ret_as(self.bb.0.ty, self.local_ty(v), mcx.value(v)),
+            _ => mcx.ret(Some(mcx.value(v))),
+        };
+        self.bb.0.push_stmt(ret)
     }
 
     fn br(&mut self, dest: Self::BasicBlock) {
@@ -175,11 +284,13 @@ impl<'a, 'tcx, 'mx> BuilderMethods<'a, 'tcx> for Builder<'a, 'tcx, 'mx> {
     }
 
     fn unreachable(&mut self) {
-        todo!()
+        self.bb.0.push_stmt(self.cx.mcx.unreachable());
     }
 
     fn add(&mut self, lhs: Self::Value, rhs: Self::Value) -> Self::Value {
-        todo!()
+        let mcx = self.cx.mcx;
+        let ty = self.binop_ty(lhs, rhs);
+        self.define_local(ty, mcx.wrapping_binary(ty, mcx.value(lhs), mcx.value(rhs), "+"))
     }
 
     fn fadd(&mut self, lhs: Self::Value, rhs: Self::Value) -> Self::Value {
@@ -195,7 +306,9 @@ impl<'a, 'tcx, 'mx> BuilderMethods<'a, 'tcx> for Builder<'a, 'tcx, 'mx> {
     }
 
     fn sub(&mut self, lhs: Self::Value, rhs: Self::Value) -> Self::Value {
-        todo!()
+        let mcx = self.cx.mcx;
+        let ty = self.binop_ty(lhs, rhs);
+        self.define_local(ty, mcx.wrapping_binary(ty, mcx.value(lhs), mcx.value(rhs), "-"))
     }
 
     fn fsub(&mut self, lhs: Self::Value, rhs: Self::Value) -> Self::Value {
@@ -211,7 +324,9 @@ impl<'a, 'tcx, 'mx> BuilderMethods<'a, 'tcx> for Builder<'a, 'tcx, 'mx> {
     }
 
     fn mul(&mut self, lhs: Self::Value, rhs: Self::Value) -> Self::Value {
-        todo!()
+        let mcx = self.cx.mcx;
+        let ty = self.binop_ty(lhs, rhs);
+        self.define_local(ty, mcx.wrapping_binary(ty, mcx.value(lhs), mcx.value(rhs), "*"))
     }
 
     fn fmul(&mut self, lhs: Self::Value, rhs: Self::Value) -> Self::Value {
@@ -279,11 +394,17 @@ impl<'a, 'tcx, 'mx> BuilderMethods<'a, 'tcx> for Builder<'a, 'tcx, 'mx> {
     }
 
     fn lshr(&mut self, lhs: Self::Value, rhs: Self::Value) -> Self::Value {
-        todo!()
+        let mcx = self.cx.mcx;
+        // the shift amount is cast to the type of `lhs` beforehand, so a
+        // constant `lhs`, e.g. `0x80u32 >> n`, takes the type of `rhs`
+        let ty = self.binop_ty(lhs, rhs);
+        self.define_local(ty, mcx.shr(ty, mcx.value(lhs), mcx.value(rhs), false))
     }
 
     fn ashr(&mut self, lhs: Self::Value, rhs: Self::Value) -> Self::Value {
-        todo!()
+        let mcx = self.cx.mcx;
+        let ty = self.binop_ty(lhs, rhs);
+        self.define_local(ty, mcx.shr(ty, mcx.value(lhs), mcx.value(rhs), true))
     }
 
     fn unchecked_sadd(&mut self, lhs: Self::Value, rhs: Self::Value) -> Self::Value {
@@ -511,35 +632,56 @@ impl<'a, 'tcx, 'mx> BuilderMethods<'a, 'tcx> for Builder<'a, 'tcx, 'mx> {
     /// result to a signed integer.
     fn intcast(&mut self, val: Self::Value, dest_ty: Self::Type, is_signed: bool) -> Self::Value {
         let mcx = self.cx.mcx;
-        let ret = self.bb.0.next_local_var();
-
-        let mut cast = mcx.cast(dest_ty, mcx.value(val));
-        if dest_ty.is_signed() {
-            cast = mcx.call(
+        let cast = if dest_ty.is_signed() {
+            let unsigned = dest_ty.to_unsigned();
+            mcx.call(
                 mcx.raw("__rust_utos"),
                 vec![
-                    mcx.raw(dest_ty.to_unsigned().to_str()),
+                    mcx.raw(unsigned.to_str()),
                     mcx.raw(dest_ty.to_str()),
-                    cast,
+                    mcx.cast(unsigned, mcx.value(val)),
                     mcx.raw(dest_ty.max_value()),
                 ],
-            );
-        }
-        self.bb.0.push_stmt(mcx.decl_stmt(mcx.var(ret, dest_ty, Some(cast))));
-        ret
+            )
+        } else {
+            mcx.cast(dest_ty, mcx.value(val))
+        };
+        self.define_local(dest_ty, cast)
     }
 
     fn pointercast(&mut self, val: Self::Value, dest_ty: Self::Type) -> Self::Value {
         todo!()
     }
 
-    fn icmp(
-        &mut self,
-        op: rustc_codegen_ssa::common::IntPredicate,
-        lhs: Self::Value,
-        rhs: Self::Value,
-    ) -> Self::Value {
-        todo!()
+    /// Compares two integers or pointers.
+    ///
+    /// The predicate, not the operand types, determines the signedness of the
+    /// comparison, so the operands are cast where their C types disagree with
+    /// it, see `ModuleCtx::compare`.
+    fn icmp(&mut self, op: IntPredicate, lhs: Self::Value, rhs: Self::Value) -> Self::Value {
+        let mcx = self.cx.mcx;
+        // only locals have a known type, and constants take the type of the
+        // other operand
+        let known_ty = |val| matches!(val, CValue::Local(_)).then(|| self.local_ty(val));
+        let (lhs_ty, rhs_ty) = match (known_ty(lhs), known_ty(rhs)) {
+            (Some(lhs_ty), Some(rhs_ty)) => (lhs_ty, rhs_ty),
+            (Some(ty), None) | (None, Some(ty)) => (ty, ty),
+            (None, None) => bug!("comparing two constants {lhs:?} and {rhs:?}"),
+        };
+        let (op, signed) = match op {
+            IntPredicate::IntEQ => ("==", lhs_ty.is_signed()),
+            IntPredicate::IntNE => ("!=", lhs_ty.is_signed()),
+            IntPredicate::IntUGT => (">", false),
+            IntPredicate::IntUGE => (">=", false),
+            IntPredicate::IntULT => ("<", false),
+            IntPredicate::IntULE => ("<=", false),
+            IntPredicate::IntSGT => (">", true),
+            IntPredicate::IntSGE => (">=", true),
+            IntPredicate::IntSLT => ("<", true),
+            IntPredicate::IntSLE => ("<=", true),
+        };
+        let cmp = mcx.compare(lhs_ty, mcx.value(lhs), rhs_ty, mcx.value(rhs), op, signed);
+        self.define_local(CTy::Bool, cmp)
     }
 
     fn fcmp(
@@ -551,6 +693,8 @@ impl<'a, 'tcx, 'mx> BuilderMethods<'a, 'tcx> for Builder<'a, 'tcx, 'mx> {
         todo!()
     }
 
+    /// Copies memory between two non-overlapping regions, as guaranteed by the
+    /// caller, e.g. `ptr::copy_nonoverlapping`.
     fn memcpy(
         &mut self,
         dst: Self::Value,
@@ -560,9 +704,12 @@ impl<'a, 'tcx, 'mx> BuilderMethods<'a, 'tcx> for Builder<'a, 'tcx, 'mx> {
         size: Self::Value,
         flags: rustc_codegen_ssa::MemFlags,
     ) {
-        todo!()
+        let mcx = self.cx.mcx;
+        let copy = mcx.copy_bytes(mcx.value(dst), mcx.value(src), mcx.value(size), true);
+        self.bb.0.push_stmt(mcx.expr_stmt(copy));
     }
 
+    /// Copies memory between two possibly overlapping regions, e.g. `ptr::copy`.
     fn memmove(
         &mut self,
         dst: Self::Value,
@@ -572,7 +719,9 @@ impl<'a, 'tcx, 'mx> BuilderMethods<'a, 'tcx> for Builder<'a, 'tcx, 'mx> {
         size: Self::Value,
         flags: rustc_codegen_ssa::MemFlags,
     ) {
-        todo!()
+        let mcx = self.cx.mcx;
+        let copy = mcx.copy_bytes(mcx.value(dst), mcx.value(src), mcx.value(size), false);
+        self.bb.0.push_stmt(mcx.expr_stmt(copy));
     }
 
     fn memset(
@@ -714,7 +863,15 @@ impl<'a, 'tcx, 'mx> BuilderMethods<'a, 'tcx> for Builder<'a, 'tcx, 'mx> {
         funclet: Option<&Self::Funclet>,
         instance: Option<rustc_middle::ty::Instance<'tcx>>,
     ) -> Self::Value {
-        todo!()
+        let mcx = self.cx.mcx;
+        let Some(fn_abi) = fn_abi else { bug!("call to `{llfn:?}` without an ABI") };
+        let call = mcx.call(mcx.value(llfn), args.iter().map(|&arg| mcx.value(arg)).collect());
+        // a zero-sized return value is never read, see `ret_void`
+        if fn_abi.ret.is_ignore() {
+            self.bb.0.push_stmt(mcx.expr_stmt(call));
+            return CValue::Scalar(0);
+        }
+        self.define_local(self.cx.immediate_backend_type(fn_abi.ret.layout), call)
     }
 
     fn zext(&mut self, val: Self::Value, dest_ty: Self::Type) -> Self::Value {
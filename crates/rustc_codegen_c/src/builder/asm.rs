@@ -15,6 +15,7 @@ impl<'tcx, 'mx> AsmBuilderMethods<'tcx> for Builder<'_, 'tcx, 'mx> {
         dest: Option<Self::BasicBlock>,
         catch_funclet: Option<(Self::BasicBlock, Option<&Self::Funclet>)>,
     ) {
-        todo!()
+        // the destination is the current block, see `append_block`
+        self.cx.unsupported("inline assembly", line_spans.first().copied());
     }
 }
@@ -18,7 +18,7 @@ impl<'tcx, 'mx> IntrinsicCallMethods<'tcx> for Builder<'_, 'tcx, 'mx> {
     }
 
     fn abort(&mut self) {
-        todo!()
+        self.bb.0.push_stmt(self.cx.mcx.trap());
     }
 
     fn assume(&mut self, val: Self::Value) {
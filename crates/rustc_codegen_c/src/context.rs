@@ -9,11 +9,13 @@ use rustc_codegen_c_ast::ty::CTy;
 use rustc_codegen_c_ast::ModuleCtx;
 use rustc_codegen_ssa::traits::BackendTypes;
 use rustc_hash::FxHashMap;
+use rustc_middle::mir::Location;
 use rustc_middle::ty::layout::{
     FnAbiError, FnAbiOfHelpers, FnAbiRequest, HasParamEnv, HasTyCtxt, LayoutError, LayoutOfHelpers,
     TyAndLayout,
 };
 use rustc_middle::ty::{Instance, ParamEnv, Ty, TyCtxt};
+use rustc_span::Span;
 use rustc_target::abi::call::FnAbi;
 use rustc_target::spec::{HasTargetSpec, Target};
 
@@ -37,12 +39,41 @@ pub struct CodegenCx<'tcx, 'mx> {
     pub mcx: ModuleCtx<'mx>,
     /// Mapping from Rust function instances to their corresponding C functions.
     pub function_instances: RefCell<FxHashMap<Instance<'tcx>, CFunc<'mx>>>,
+    /// The C types of the local variables defined by the builder in each function.
+    pub local_tys: RefCell<FxHashMap<(CFunc<'mx>, CValue<'mx>), CTy<'mx>>>,
+    /// Whether to annotate the generated statements with comments of the MIR
+    /// locations they come from, e.g. `// bb2[3]`, enabled by setting the
+    /// `RUSTC_CODEGEN_C_LOCATION_COMMENTS` environment variable.
+    pub location_comments: bool,
+    /// The MIR location of the last location comment, and the function it is in.
+    pub last_location: RefCell<Option<(CFunc<'mx>, Location)>>,
 }
 
 impl<'tcx, 'mx> CodegenCx<'tcx, 'mx> {
     pub fn new(tcx: TyCtxt<'tcx>, mcx: ModuleCtx<'mx>) -> Self {
         mcx.module().push_include("stdint.h");
-        Self { tcx, mcx, function_instances: RefCell::new(FxHashMap::default()) }
+        Self {
+            tcx,
+            mcx,
+            function_instances: RefCell::new(FxHashMap::default()),
+            local_tys: RefCell::new(FxHashMap::default()),
+            location_comments: std::env::var_os("RUSTC_CODEGEN_C_LOCATION_COMMENTS").is_some(),
+            last_location: RefCell::new(None),
+        }
+    }
+
+    /// Emit an `#error` for a construct which cannot be lowered, e.g. inline
+    /// assembly, with the source location of `span`, so that compiling the C
+    /// fails there instead of the backend panicking.
+    pub fn unsupported(&self, what: &str, span: Option<Span>) {
+        let message = match span {
+            Some(span) => {
+                let location = self.tcx.sess.source_map().span_to_embeddable_string(span);
+                format!("{what} at {location}")
+            }
+            None => what.to_string(),
+        };
+        self.mcx.module().push_error(self.mcx.alloc_str(&message));
     }
 }
 
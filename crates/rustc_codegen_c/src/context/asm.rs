@@ -11,6 +11,6 @@ impl<'tcx, 'mx> AsmMethods<'tcx> for CodegenCx<'tcx, 'mx> {
         options: InlineAsmOptions,
         line_spans: &[rustc_span::Span],
     ) {
-        todo!()
+        self.unsupported("global assembly", line_spans.first().copied());
     }
 }
@@ -1,3 +1,4 @@
+use rustc_codegen_c_ast::ty::CTy;
 use rustc_codegen_ssa::traits::BaseTypeMethods;
 
 use crate::context::CodegenCx;
@@ -56,7 +57,7 @@ impl<'tcx, 'mx> BaseTypeMethods<'tcx> for CodegenCx<'tcx, 'mx> {
     }
 
     fn type_ptr(&self) -> Self::Type {
-        todo!()
+        self.mcx.ptr(CTy::Void)
     }
 
     fn type_ptr_ext(&self, address_space: rustc_abi::AddressSpace) -> Self::Type {
@@ -10,7 +10,9 @@ impl<'tcx, 'mx> ConstMethods<'tcx> for CodegenCx<'tcx, 'mx> {
     }
 
     fn const_undef(&self, t: Self::Type) -> Self::Value {
-        todo!()
+        // an undefined value is never read, e.g. the unused result place of an
+        // intrinsic, so any constant will do
+        CValue::Scalar(0)
     }
 
     fn const_poison(&self, t: Self::Type) -> Self::Value {
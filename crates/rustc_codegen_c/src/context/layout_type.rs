@@ -1,14 +1,22 @@
 use rustc_abi::Abi;
+use rustc_codegen_c_ast::ty::CTy;
 use rustc_codegen_ssa::traits::LayoutTypeMethods;
-use rustc_middle::ty::layout::TyAndLayout;
+use rustc_middle::ty::layout::{LayoutOf, TyAndLayout};
 use rustc_middle::ty::Ty;
-use rustc_target::abi::call::FnAbi;
+use rustc_target::abi::call::{FnAbi, PassMode};
 use rustc_type_ir::TyKind;
 
 use crate::context::CodegenCx;
 
 impl<'tcx, 'mx> LayoutTypeMethods<'tcx> for CodegenCx<'tcx, 'mx> {
     fn backend_type(&self, layout: TyAndLayout<'tcx>) -> Self::Type {
+        // a zero-sized value, e.g. `()` or `!`, is never materialized
+        if layout.is_zst() {
+            return CTy::Void;
+        }
+        if self.is_backend_immediate(layout) {
+            return self.immediate_backend_type(layout);
+        }
         todo!()
     }
 
@@ -17,7 +25,8 @@ impl<'tcx, 'mx> LayoutTypeMethods<'tcx> for CodegenCx<'tcx, 'mx> {
     }
 
     fn fn_decl_backend_type(&self, fn_abi: &FnAbi<'tcx, Ty<'tcx>>) -> Self::Type {
-        todo!()
+        let (ret, params) = self.fn_signature(fn_abi);
+        self.mcx.fn_ty(ret, params)
     }
 
     fn fn_ptr_backend_type(&self, fn_abi: &FnAbi<'tcx, Ty<'tcx>>) -> Self::Type {
@@ -30,8 +39,15 @@ impl<'tcx, 'mx> LayoutTypeMethods<'tcx> for CodegenCx<'tcx, 'mx> {
 
     fn immediate_backend_type(&self, layout: TyAndLayout<'tcx>) -> Self::Type {
         match layout.ty.kind() {
+            TyKind::Bool => CTy::Bool,
             TyKind::Int(int) => self.mcx.get_int_type(*int),
             TyKind::Uint(uint) => self.mcx.get_uint_type(*uint),
+            TyKind::Float(float) => self.mcx.get_float_type(*float),
+            // a reference to a sized type is a thin pointer
+            TyKind::Ref(_, pointee, mutbl) => {
+                let pointee = self.immediate_backend_type(self.layout_of(*pointee));
+                self.mcx.ref_ptr(pointee, mutbl.is_mut())
+            }
             _ => todo!(),
         }
     }
@@ -44,7 +60,7 @@ impl<'tcx, 'mx> LayoutTypeMethods<'tcx> for CodegenCx<'tcx, 'mx> {
     }
 
     fn is_backend_scalar_pair(&self, layout: TyAndLayout<'tcx>) -> bool {
-        todo!()
+        matches!(layout.abi, Abi::ScalarPair(..))
     }
 
     fn scalar_pair_element_backend_type(
@@ -53,6 +69,44 @@ impl<'tcx, 'mx> LayoutTypeMethods<'tcx> for CodegenCx<'tcx, 'mx> {
         index: usize,
         immediate: bool,
     ) -> Self::Type {
-        todo!()
+        match layout.ty.kind() {
+            // a slice reference is a pair of the data pointer and the length
+            TyKind::Ref(_, pointee, mutbl) => match pointee.kind() {
+                TyKind::Slice(elem) => {
+                    let elem = self.immediate_backend_type(self.layout_of(*elem));
+                    let (data, len) = self.mcx.slice_parts(elem, mutbl.is_mut());
+                    [data, len][index]
+                }
+                _ => todo!(),
+            },
+            _ => todo!(),
+        }
+    }
+}
+
+impl<'tcx, 'mx> CodegenCx<'tcx, 'mx> {
+    /// The return type and the parameter types of a function with `fn_abi`.
+    pub(crate) fn fn_signature(&self, fn_abi: &FnAbi<'tcx, Ty<'tcx>>) -> (CTy<'mx>, Vec<CTy<'mx>>) {
+        // a scalar pair, e.g. a slice, is passed as two parameters, and a
+        // zero-sized argument, e.g. `()`, is not passed at all
+        let params = fn_abi
+            .args
+            .iter()
+            .flat_map(|arg| match arg.mode {
+                PassMode::Ignore => vec![],
+                PassMode::Pair(..) => vec![
+                    self.scalar_pair_element_backend_type(arg.layout, 0, true),
+                    self.scalar_pair_element_backend_type(arg.layout, 1, true),
+                ],
+                _ => vec![self.immediate_backend_type(arg.layout)],
+            })
+            .collect();
+        // a function returning `()`, or another zero-sized type, returns `void`
+        let ret = if fn_abi.ret.is_ignore() {
+            CTy::Void
+        } else {
+            self.immediate_backend_type(fn_abi.ret.layout)
+        };
+        (ret, params)
     }
 }
@@ -1,9 +1,11 @@
 use std::cell::RefCell;
 
-use rustc_codegen_ssa::traits::MiscMethods;
+use rustc_codegen_c_ast::expr::CValue;
+use rustc_codegen_ssa::traits::{LayoutTypeMethods, MiscMethods};
 use rustc_hash::FxHashMap;
 use rustc_middle::mir::mono::CodegenUnit;
-use rustc_middle::ty::{Instance, PolyExistentialTraitRef, Ty};
+use rustc_middle::ty::layout::FnAbiOf;
+use rustc_middle::ty::{self, Instance, PolyExistentialTraitRef, Ty};
 
 use crate::context::CodegenCx;
 
@@ -19,7 +21,13 @@ impl<'tcx, 'mx> MiscMethods<'tcx> for CodegenCx<'tcx, 'mx> {
     }
 
     fn get_fn_addr(&self, instance: Instance<'tcx>) -> Self::Value {
-        todo!()
+        if let Some(func) = self.function_instances.borrow().get(&instance) {
+            return CValue::Func(func.0.name);
+        }
+        // defined by another codegen unit or crate
+        let fn_abi = self.fn_abi_of_instance(instance, ty::List::empty());
+        let name = self.mcx.alloc_str(self.tcx.symbol_name(instance).name);
+        self.mcx.declare_extern(name, self.fn_decl_backend_type(fn_abi))
     }
 
     fn eh_personality(&self) -> Self::Value {
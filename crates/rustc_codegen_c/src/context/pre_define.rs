@@ -1,10 +1,17 @@
-use rustc_codegen_c_ast::func::CFuncKind;
+use rustc_attr::InlineAttr;
+use rustc_codegen_c_ast::func::{CFuncAttr, CFuncKind};
 use rustc_codegen_ssa::traits::{LayoutTypeMethods, PreDefineMethods};
 use rustc_data_structures::intern::Interned;
+use rustc_hash::FxHashSet;
 use rustc_hir::def_id::DefId;
+use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
 use rustc_middle::mir::mono::{Linkage, Visibility};
-use rustc_middle::ty::layout::FnAbiOf;
-use rustc_middle::ty::{self, Instance};
+use rustc_middle::mir::visit::{PlaceContext, Visitor};
+use rustc_middle::mir::{Local, Location};
+use rustc_middle::ty::layout::{FnAbiOf, LayoutOf};
+use rustc_middle::ty::{self, Instance, Ty};
+use rustc_span::sym;
+use rustc_target::abi::call::{ArgAbi, PassMode};
 
 use crate::context::CodegenCx;
 
@@ -16,7 +23,17 @@ impl<'tcx, 'mx> PreDefineMethods<'tcx> for CodegenCx<'tcx, 'mx> {
         visibility: Visibility,
         symbol_name: &str,
     ) {
-        todo!()
+        let instance = Instance::mono(self.tcx, def_id);
+        let ty = instance.ty(self.tcx, ty::ParamEnv::reveal_all());
+        let ty = self.immediate_backend_type(self.layout_of(ty));
+
+        // `symbol_name` is already unmangled for a `#[no_mangle]` static, which
+        // must also keep external linkage to be visible to other objects.
+        let exported = !matches!(linkage, Linkage::Internal | Linkage::Private);
+        let hidden = visibility == Visibility::Hidden;
+        // FIXME: the initializer is only known once `codegen_static` is implemented,
+        // until then this is a tentative definition which is zero-initialized
+        self.mcx.define_static(self.mcx.alloc_str(symbol_name), ty, None, exported, hidden);
     }
 
     fn predefine_fn(
@@ -27,13 +44,78 @@ impl<'tcx, 'mx> PreDefineMethods<'tcx> for CodegenCx<'tcx, 'mx> {
         symbol_name: &str,
     ) {
         let fn_abi = self.fn_abi_of_instance(instance, ty::List::empty());
+        let (ret, args) = self.fn_signature(fn_abi);
+
+        let func = self.mcx.func(CFuncKind::new(self.mcx.alloc_str(symbol_name), ret, args));
+        if self.tcx.has_attr(instance.def_id(), sym::must_use) {
+            func.set_must_use();
+        }
+        let attrs = self.tcx.codegen_fn_attrs(instance.def_id());
+        if attrs.flags.contains(CodegenFnAttrFlags::COLD) {
+            func.add_attr(CFuncAttr::Cold);
+        }
+        if let Some(align) = attrs.alignment {
+            func.add_attr(CFuncAttr::Aligned(align.bytes()));
+        }
+        if visibility == Visibility::Hidden {
+            func.add_attr(CFuncAttr::Hidden);
+        }
+        match attrs.inline {
+            InlineAttr::Always => func.add_attr(CFuncAttr::AlwaysInline),
+            InlineAttr::Never => func.add_attr(CFuncAttr::NoInline),
+            InlineAttr::None | InlineAttr::Hint => {}
+        }
+
+        // A reference is never null, and neither is the data pointer of a slice
+        // reference, which is the first parameter of its pair.
+        let mut param = 0;
+        for arg in fn_abi.args.iter() {
+            if arg.layout.ty.is_ref() && !arg.is_ignore() {
+                func.add_attr(CFuncAttr::NonNull(param + 1));
+            }
+            param += param_count(arg);
+        }
 
-        let args = fn_abi.args.iter().map(|arg| self.immediate_backend_type(arg.layout));
-        let ret = self.immediate_backend_type(fn_abi.ret.layout);
+        // Discard the parameters which are never read, to keep `-Wunused-parameter` quiet.
+        // FIXME: this assumes that each MIR argument has an argument ABI, which
+        // does not hold for the untupled arguments of the "rust-call" ABI
+        let mir = self.tcx.instance_mir(instance.def);
+        let mut used_locals = UsedLocals::default();
+        used_locals.visit_body(mir);
+        let mut param = 0;
+        for (local, arg) in mir.args_iter().zip(fn_abi.args.iter()) {
+            let count = param_count(arg);
+            if !used_locals.0.contains(&local) {
+                for i in param..(param + count).min(func.params.len()) {
+                    func.mark_param_unused(i);
+                }
+            }
+            param += count;
+        }
 
-        let func = CFuncKind::new(self.mcx.alloc_str(symbol_name), ret, args);
-        let func = Interned::new_unchecked(self.mcx.func(func));
+        let func = Interned::new_unchecked(func);
         self.mcx.module().push_func(func);
         self.function_instances.borrow_mut().insert(instance, func);
     }
 }
+
+/// The number of C parameters an argument is passed as, see `predefine_fn`.
+fn param_count(arg: &ArgAbi<'_, Ty<'_>>) -> usize {
+    match arg.mode {
+        PassMode::Ignore => 0,
+        PassMode::Pair(..) => 2,
+        _ => 1,
+    }
+}
+
+/// Collects the MIR locals which are actually used, ignoring debuginfo and storage markers.
+#[derive(Default)]
+struct UsedLocals(FxHashSet<Local>);
+
+impl<'tcx> Visitor<'tcx> for UsedLocals {
+    fn visit_local(&mut self, local: Local, context: PlaceContext, _location: Location) {
+        if context.is_use() {
+            self.0.insert(local);
+        }
+    }
+}
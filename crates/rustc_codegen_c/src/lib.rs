@@ -2,6 +2,7 @@
 
 extern crate rustc_abi;
 extern crate rustc_ast;
+extern crate rustc_attr;
 extern crate rustc_codegen_ssa;
 extern crate rustc_const_eval;
 extern crate rustc_data_structures;
@@ -46,6 +47,7 @@ mod archive;
 mod base;
 mod builder;
 mod context;
+mod partition;
 mod write;
 
 rustc_fluent_macro::fluent_messages! { "../messages.ftl" }
@@ -59,7 +61,10 @@ impl CodegenBackend for CCodegen {
     }
 
     fn provide(&self, providers: &mut Providers) {
-        providers.global_backend_features = |_tcx, ()| vec![]
+        providers.global_backend_features = |_tcx, ()| vec![];
+        if partition::split_modules() {
+            partition::provide(providers);
+        }
     }
 
     fn codegen_crate(
@@ -74,6 +79,9 @@ impl CodegenBackend for CCodegen {
         }
         .to_owned();
 
+        if partition::split_modules() {
+            partition::write_header(tcx);
+        }
         let ongoing_codegen =
             codegen_crate(self.clone(), tcx, target_cpu, metadata, need_metadata_module);
         Box::new(ongoing_codegen)
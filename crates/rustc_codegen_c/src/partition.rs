@@ -0,0 +1,139 @@
+//! Splitting of the generated C into one file per top-level module of the
+//! crate, enabled by setting the `RUSTC_CODEGEN_C_SPLIT_MODULES` environment
+//! variable.
+//!
+//! The codegen units partitioned by rustc are regrouped by the top-level
+//! module their items are defined in, so that each of them is emitted as a C
+//! file of its own. A header declaring the functions of all of them is
+//! written next to the C files, and included by each of them.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use rustc_codegen_c_ast::{ModuleArena, ModuleCtx};
+use rustc_codegen_ssa::mono_item::MonoItemExt;
+use rustc_data_structures::fx::FxIndexMap;
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{DefId, DefIdSet, LOCAL_CRATE};
+use rustc_middle::mir::mono::{CodegenUnit, Linkage, MonoItem, MonoItemData, Visibility};
+use rustc_middle::ty::TyCtxt;
+use rustc_middle::util::Providers;
+use rustc_span::Symbol;
+
+use crate::builder::Builder;
+use crate::context::CodegenCx;
+
+/// The environment variable enabling the split of the generated C.
+const SPLIT_MODULES_ENV: &str = "RUSTC_CODEGEN_C_SPLIT_MODULES";
+
+type PartitionFn = for<'tcx> fn(TyCtxt<'tcx>, ()) -> (&'tcx DefIdSet, &'tcx [CodegenUnit<'tcx>]);
+
+/// The partitioning of rustc, whose codegen units are regrouped.
+static DEFAULT_PARTITION: OnceLock<PartitionFn> = OnceLock::new();
+
+/// Whether the generated C is split by module.
+pub(crate) fn split_modules() -> bool {
+    std::env::var_os(SPLIT_MODULES_ENV).is_some()
+}
+
+pub(crate) fn provide(providers: &mut Providers) {
+    DEFAULT_PARTITION.get_or_init(|| providers.collect_and_partition_mono_items);
+    providers.collect_and_partition_mono_items = partition_by_module;
+}
+
+fn partition_by_module<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    (): (),
+) -> (&'tcx DefIdSet, &'tcx [CodegenUnit<'tcx>]) {
+    let (items, cgus) = DEFAULT_PARTITION.get().unwrap()(tcx, ());
+
+    let mut modules: FxIndexMap<Symbol, CodegenUnit<'tcx>> = FxIndexMap::default();
+    for cgu in cgus {
+        for (&item, &data) in cgu.items() {
+            let name = cgu_name(tcx, item);
+            let cgu = modules.entry(name).or_insert_with(|| CodegenUnit::new(name));
+            // the users of an internal item may end up in another file, and an
+            // item copied into multiple codegen units is now defined only once
+            let data = match data.linkage {
+                Linkage::Internal | Linkage::Private => MonoItemData {
+                    linkage: Linkage::External,
+                    visibility: Visibility::Hidden,
+                    ..data
+                },
+                _ => data,
+            };
+            cgu.items_mut().insert(item, data);
+        }
+    }
+
+    let mut cgus: Vec<_> = modules.into_values().collect();
+    cgus.sort_by(|a, b| a.name().as_str().cmp(b.name().as_str()));
+    for cgu in &mut cgus {
+        cgu.compute_size_estimate();
+    }
+    (items, tcx.arena.alloc_from_iter(cgus))
+}
+
+/// The name of the codegen unit of `item`, e.g. `foo-a` for the items of the
+/// crate `foo` in the module `a` and its submodules, or `foo` for the items
+/// at the crate root and those defined in other crates.
+fn cgu_name(tcx: TyCtxt<'_>, item: MonoItem<'_>) -> Symbol {
+    let def_id = match item {
+        MonoItem::Fn(instance) => instance.def_id(),
+        MonoItem::Static(def_id) => def_id,
+        MonoItem::GlobalAsm(item_id) => item_id.owner_id.to_def_id(),
+    };
+    let crate_name = tcx.crate_name(LOCAL_CRATE);
+    match top_level_module(tcx, def_id) {
+        Some(module) => Symbol::intern(&format!("{crate_name}-{module}")),
+        None => crate_name,
+    }
+}
+
+/// The module directly under the crate root which `def_id` is defined in, if any.
+fn top_level_module(tcx: TyCtxt<'_>, mut def_id: DefId) -> Option<Symbol> {
+    if !def_id.is_local() {
+        return None;
+    }
+    loop {
+        let parent = tcx.opt_parent(def_id)?;
+        if parent.is_crate_root() {
+            break;
+        }
+        def_id = parent;
+    }
+    (tcx.def_kind(def_id) == DefKind::Mod).then(|| tcx.item_name(def_id))
+}
+
+/// The path of the header shared by the C files, next to them.
+pub(crate) fn header_path(tcx: TyCtxt<'_>) -> PathBuf {
+    tcx.output_filenames(()).temp_path_ext("h", None)
+}
+
+/// Write the header declaring the functions of all the codegen units, so that
+/// the C files can call each other.
+///
+/// Signatures needing declarations, e.g. of the structs passing aggregates,
+/// are rejected, as each C file defines its own declarations.
+pub(crate) fn write_header(tcx: TyCtxt<'_>) {
+    let mcx = ModuleArena::new("");
+    let mcx = ModuleCtx(&mcx);
+    let cx = CodegenCx::new(tcx, mcx);
+    for cgu in tcx.collect_and_partition_mono_items(()).1 {
+        for (item, data) in cgu.items_in_deterministic_order(tcx) {
+            if let MonoItem::Fn(_) = item {
+                item.predefine::<Builder<'_, '_, '_>>(&cx, data.linkage, data.visibility);
+            }
+        }
+    }
+
+    if !mcx.module().decls.borrow().is_empty() {
+        tcx.dcx().fatal("split modules are unsupported for signatures needing declarations");
+    }
+
+    let guard = format!("__RUST_{}_H", tcx.crate_name(LOCAL_CRATE).as_str().to_uppercase());
+    let path = header_path(tcx);
+    if let Err(e) = std::fs::write(&path, mcx.to_header_string(&guard)) {
+        tcx.dcx().fatal(format!("failed to write {}: {}", path.display(), e));
+    }
+}
@@ -1,5 +1,6 @@
 use std::fs;
 use std::io::Write;
+use std::path::Path;
 use std::process::Stdio;
 
 use rustc_codegen_ssa::back::command::Command;
@@ -9,9 +10,17 @@ use rustc_errors::{DiagCtxtHandle, FatalError};
 use rustc_session::config::OutputType;
 use tracing::error;
 
+/// The environment variable selecting where the generated C is emitted besides
+/// the output directory, currently only `stdout` is supported.
+const EMIT_ENV: &str = "RUSTC_CODEGEN_C_EMIT";
+
+/// The environment variable naming a file to which an entry of a
+/// `compile_commands.json` is appended, as a line, for each C compilation.
+const COMPDB_ENV: &str = "RUSTC_CODEGEN_C_COMPDB";
+
 pub(crate) unsafe fn codegen(
     cgcx: &CodegenContext<crate::CCodegen>,
-    _dcx: DiagCtxtHandle<'_>,
+    dcx: DiagCtxtHandle<'_>,
     module: ModuleCodegen<String>,
     _config: &ModuleConfig,
 ) -> Result<CompiledModule, FatalError> {
@@ -24,6 +33,12 @@ pub(crate) unsafe fn codegen(
     let c_out_file = fs::File::create(&c_out).map_err(|_| FatalError)?;
     writeln!(&c_out_file, "// file: {}.c", module.name).map_err(|_| FatalError)?;
     write!(&c_out_file, "{}", module.module_llvm).map_err(|_| FatalError)?;
+    if std::env::var(EMIT_ENV).is_ok_and(|emit| emit == "stdout") {
+        // the file is still written above, as the input of the C compiler
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "// file: {}.c", module.name).map_err(|_| FatalError)?;
+        write!(stdout, "{}", module.module_llvm).map_err(|_| FatalError)?;
+    }
 
     // invoke cc to compile
     // FIXME: configure cc
@@ -32,6 +47,10 @@ pub(crate) unsafe fn codegen(
     let mut cmd = Command::new("clang");
     cmd.arg(&c_out).arg("-o").arg(&obj_out).arg("-c");
     let mut cmd = cmd.command();
+    if let Err(e) = record_compdb_entry(&cmd, &c_out, &obj_out) {
+        error!("failed to record the C compiler invocation: {}", e);
+        return Err(FatalError);
+    }
     let output = match cmd
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -46,14 +65,61 @@ pub(crate) unsafe fn codegen(
     };
 
     if !output.status.success() {
-        error!("compiler stderr:\n{}", String::from_utf8_lossy(&output.stderr));
-        error!("compiler stdout:\n{}", String::from_utf8_lossy(&output.stdout));
+        // a diagnostic, rather than only a log, makes rustc abort with the
+        // errors of the C compiler, e.g. the `#error` of an unsupported construct
+        dcx.err(format!(
+            "failed to compile {}:\n{}",
+            c_out.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
         return Err(FatalError);
     }
 
     Ok(module.into_compiled_module(true, false, false, false, false, &cgcx.output_filenames))
 }
 
+/// Append the compilation database entry of the C compiler invocation `cmd`
+/// to the file named by [`COMPDB_ENV`], if set.
+fn record_compdb_entry(
+    cmd: &std::process::Command,
+    file: &Path,
+    output: &Path,
+) -> std::io::Result<()> {
+    let Some(path) = std::env::var_os(COMPDB_ENV) else { return Ok(()) };
+    let arguments = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| json_string(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let directory = std::env::current_dir()?;
+    let entry = format!(
+        "{{\"directory\": {}, \"arguments\": [{}], \"file\": {}, \"output\": {}}}\n",
+        json_string(&directory.to_string_lossy()),
+        arguments,
+        json_string(&file.to_string_lossy()),
+        json_string(&output.to_string_lossy()),
+    );
+    // codegen units are compiled in parallel, and a single write to a file
+    // opened for appending is not interleaved with the writes of other threads
+    let mut entries = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    entries.write_all(entry.as_bytes())
+}
+
+/// Quote a string as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
 pub(crate) fn link(
     _cgcx: &CodegenContext<crate::CCodegen>,
     _dcx: DiagCtxtHandle<'_>,
@@ -0,0 +1,275 @@
+//! This module defines helpers for calling C library functions and compiler builtins.
+//!
+//! The helpers take care of registering the headers which declare the functions.
+
+use crate::expr::{CExpr, CValue};
+use crate::func::{CFunc, CFuncKind};
+use crate::stmt::CStmt;
+use crate::ty::{CIntNames, CTy, CUintTy};
+use crate::ModuleCtx;
+
+impl<'mx> ModuleCtx<'mx> {
+    /// Create a `memcpy(dst, src, size)` call.
+    ///
+    /// `memcpy` requires that the source and destination do not overlap, the
+    /// behavior is undefined otherwise. Use [`ModuleCtx::memmove`] when this
+    /// cannot be proven.
+    pub fn memcpy(&self, dst: CExpr<'mx>, src: CExpr<'mx>, size: CExpr<'mx>) -> CExpr<'mx> {
+        self.module().require_include("string.h");
+        self.call(self.raw("memcpy"), vec![dst, src, size])
+    }
+
+    /// Create a `memmove(dst, src, size)` call, which allows the source and
+    /// destination to overlap.
+    pub fn memmove(&self, dst: CExpr<'mx>, src: CExpr<'mx>, size: CExpr<'mx>) -> CExpr<'mx> {
+        self.module().require_include("string.h");
+        self.call(self.raw("memmove"), vec![dst, src, size])
+    }
+
+    /// Copy `size` bytes from `src` to `dst`.
+    ///
+    /// `disjoint` must only be set when the two memory regions are known not to
+    /// overlap, which allows the faster `memcpy` to be used instead of `memmove`.
+    pub fn copy_bytes(
+        &self,
+        dst: CExpr<'mx>,
+        src: CExpr<'mx>,
+        size: CExpr<'mx>,
+        disjoint: bool,
+    ) -> CExpr<'mx> {
+        if disjoint {
+            self.memcpy(dst, src, size)
+        } else {
+            self.memmove(dst, src, size)
+        }
+    }
+
+    /// Copy `count` elements of type `elem_ty` from `src` to `dst`, which must
+    /// not overlap, matching `ptr::copy_nonoverlapping`.
+    pub fn copy_nonoverlapping(
+        &self,
+        dst: CExpr<'mx>,
+        src: CExpr<'mx>,
+        count: CExpr<'mx>,
+        elem_ty: CTy<'mx>,
+    ) -> CExpr<'mx> {
+        self.memcpy(dst, src, self.binary(count, self.sizeof(elem_ty), "*"))
+    }
+
+    /// Copy `count` elements of type `elem_ty` from `src` to `dst`, which may
+    /// overlap, matching `ptr::copy`.
+    pub fn copy(
+        &self,
+        dst: CExpr<'mx>,
+        src: CExpr<'mx>,
+        count: CExpr<'mx>,
+        elem_ty: CTy<'mx>,
+    ) -> CExpr<'mx> {
+        self.memmove(dst, src, self.binary(count, self.sizeof(elem_ty), "*"))
+    }
+
+    /// Create a `__builtin_prefetch(addr, rw, locality);` statement, hinting
+    /// that `addr` is about to be read, or written if `write` is set.
+    ///
+    /// `locality` ranges from 0, no temporal locality, to 3, high temporal
+    /// locality. The hint is a GNU extension, without which the statement is
+    /// printed empty.
+    pub fn prefetch(&self, addr: CExpr<'mx>, write: bool, locality: u8) -> CStmt<'mx> {
+        assert!(locality <= 3, "prefetch locality {locality} is out of range");
+        let callee = self.value(CValue::Func("__builtin_prefetch"));
+        let args = vec![
+            addr,
+            self.value(CValue::Scalar(write as i128)),
+            self.value(CValue::Scalar(locality as i128)),
+        ];
+        self.expr_stmt(self.gnu(self.call(callee, args), None))
+    }
+
+    /// Create a statement which abnormally terminates the program, matching
+    /// `core::intrinsics::abort`.
+    ///
+    /// This is `__builtin_trap();` with GNU extensions, and the portable but
+    /// heavier `abort();` from `<stdlib.h>` otherwise, which is included either
+    /// way as the standard is only known when printing.
+    pub fn trap(&self) -> CStmt<'mx> {
+        self.module().require_include("stdlib.h");
+        let trap = self.call(self.value(CValue::Func("__builtin_trap")), vec![]);
+        self.expr_stmt(self.gnu(trap, Some(self.call(self.raw("abort"), vec![]))))
+    }
+
+    /// Create a statement marking the end of a block as never reached, e.g.
+    /// after an abort, matching `core::intrinsics::unreachable`.
+    ///
+    /// This is `__builtin_unreachable();` with GNU extensions, without which
+    /// the statement is printed empty.
+    pub fn unreachable(&self) -> CStmt<'mx> {
+        let unreachable = self.call(self.value(CValue::Func("__builtin_unreachable")), vec![]);
+        self.expr_stmt(self.gnu(unreachable, None))
+    }
+
+    /// Count the leading zeros of `expr`, an integer of type `ty`, matching
+    /// `leading_zeros`. The width of `ty` is returned for zero.
+    ///
+    /// With GNU extensions this is `__builtin_clz` of the width of `ty`, whose
+    /// undefined result for zero is avoided by setting the lowest bit of a zero
+    /// input, as `__builtin_clz(x | (x == 0)) + (x == 0)`. `expr` is evaluated
+    /// multiple times, so it must not have side effects. Otherwise, a portable
+    /// `static` helper is defined and called.
+    ///
+    /// `size_t` is counted as an `unsigned long long`, which is at least as
+    /// wide on all common data models, unlike `unsigned long` on LLP64.
+    pub fn ctlz(&self, expr: CExpr<'mx>, ty: CTy<'mx>) -> CExpr<'mx> {
+        let (x, uty) = self.bit_count_operand(expr, ty);
+        if !self.module().standard().has_gnu() {
+            return self.call(self.ctlz_fallback(uty), vec![x]);
+        }
+        let zero = self.binary(x, self.value(CValue::Scalar(0)), "==");
+        let builtin = by_width(uty, ["__builtin_clz", "__builtin_clzll"]);
+        let clz = self.call(self.value(CValue::Func(builtin)), vec![self.binary(x, zero, "|")]);
+        let clz = self.binary(clz, zero, "+");
+        // the narrow types are counted as an `unsigned int`, and `size_t` as an
+        // `unsigned long long`, which may be wider
+        match (uty, promoted_bits(uty)) {
+            (CUintTy::Usize, _) => {
+                let bits = self.binary(self.value(CValue::Scalar(64)), self.bit_width(uty), "-");
+                self.binary(clz, bits, "-")
+            }
+            (_, 0) => clz,
+            (_, bits) => self.binary(clz, self.value(CValue::Scalar(bits)), "-"),
+        }
+    }
+
+    /// Count the trailing zeros of `expr`, an integer of type `ty`, matching
+    /// `trailing_zeros`. The width of `ty` is returned for zero.
+    ///
+    /// With GNU extensions this is `__builtin_ctz` of the width of `ty`, guarded
+    /// as `__builtin_ctz(x | (x == 0)) + (x == 0) * width`, and otherwise the
+    /// population count of the mask of the trailing zeros, see [`ModuleCtx::ctlz`].
+    pub fn cttz(&self, expr: CExpr<'mx>, ty: CTy<'mx>) -> CExpr<'mx> {
+        let (x, uty) = self.bit_count_operand(expr, ty);
+        if !self.module().standard().has_gnu() {
+            let one = self.value(CValue::Scalar(1));
+            let mask = self.binary(self.unary("~", x), self.binary(x, one, "-"), "&");
+            return self.call(self.ctpop_fallback(uty), vec![self.cast(CTy::UInt(uty), mask)]);
+        }
+        let zero = self.binary(x, self.value(CValue::Scalar(0)), "==");
+        let builtin = by_width(uty, ["__builtin_ctz", "__builtin_ctzll"]);
+        let ctz = self.call(self.value(CValue::Func(builtin)), vec![self.binary(x, zero, "|")]);
+        self.binary(ctz, self.binary(zero, self.bit_width(uty), "*"), "+")
+    }
+
+    /// Count the ones of `expr`, an integer of type `ty`, matching `count_ones`.
+    ///
+    /// This is `__builtin_popcount` of the width of `ty` with GNU extensions,
+    /// see [`ModuleCtx::ctlz`].
+    pub fn ctpop(&self, expr: CExpr<'mx>, ty: CTy<'mx>) -> CExpr<'mx> {
+        let (x, uty) = self.bit_count_operand(expr, ty);
+        if !self.module().standard().has_gnu() {
+            return self.call(self.ctpop_fallback(uty), vec![x]);
+        }
+        let builtin = by_width(uty, ["__builtin_popcount", "__builtin_popcountll"]);
+        self.call(self.value(CValue::Func(builtin)), vec![x])
+    }
+
+    /// Reinterpret the operand of a bit counting helper as unsigned, so that
+    /// the narrow signed types are not sign extended.
+    fn bit_count_operand(&self, expr: CExpr<'mx>, ty: CTy<'mx>) -> (CExpr<'mx>, CUintTy) {
+        match ty {
+            CTy::UInt(uty) => (expr, uty),
+            CTy::Int(ity) => (self.cast(CTy::UInt(ity.to_unsigned()), expr), ity.to_unsigned()),
+            _ => panic!("cannot count the bits of `{}`", ty.to_str()),
+        }
+    }
+
+    /// The width in bits of `uty`, which is `sizeof(size_t) * 8` for `size_t`.
+    fn bit_width(&self, uty: CUintTy) -> CExpr<'mx> {
+        let bits = match uty {
+            CUintTy::U8 => 8,
+            CUintTy::U16 => 16,
+            CUintTy::U32 => 32,
+            CUintTy::U64 => 64,
+            CUintTy::Usize => {
+                let size = self.sizeof(CTy::UInt(CUintTy::Usize));
+                return self.binary(size, self.value(CValue::Scalar(8)), "*");
+            }
+        };
+        self.value(CValue::Scalar(bits))
+    }
+
+    /// The portable helper counting the leading zeros of a `uty`, e.g.
+    /// `__rust_ctlz_u32`, shifting the input right until it is zero.
+    fn ctlz_fallback(&self, uty: CUintTy) -> CExpr<'mx> {
+        self.bit_count_fallback("ctlz", uty, |func| {
+            let (x, count) = (self.value(CValue::Local(0)), func.next_local_var());
+            func.push_stmt(self.decl_stmt(self.var(count, func.ty, Some(self.bit_width(uty)))));
+            let one = self.value(CValue::Scalar(1));
+            let count = self.value(count);
+            let body = vec![
+                self.expr_stmt(self.binary(x, self.binary(x, one, ">>"), "=")),
+                self.expr_stmt(self.binary(count, self.binary(count, one, "-"), "=")),
+            ];
+            func.push_stmt(self.while_stmt(x, self.compound(body)));
+            func.push_stmt(self.ret(Some(count)));
+        })
+    }
+
+    /// The portable helper counting the ones of a `uty`, e.g. `__rust_ctpop_u32`,
+    /// adding up the lowest bit while shifting the input right until it is zero.
+    fn ctpop_fallback(&self, uty: CUintTy) -> CExpr<'mx> {
+        self.bit_count_fallback("ctpop", uty, |func| {
+            let (x, count) = (self.value(CValue::Local(0)), func.next_local_var());
+            let zero = self.value(CValue::Scalar(0));
+            func.push_stmt(self.decl_stmt(self.var(count, func.ty, Some(zero))));
+            let one = self.value(CValue::Scalar(1));
+            let count = self.value(count);
+            let bit = self.cast(func.ty, self.binary(x, one, "&"));
+            let body = vec![
+                self.expr_stmt(self.binary(count, self.binary(count, bit, "+"), "=")),
+                self.expr_stmt(self.binary(x, self.binary(x, one, ">>"), "=")),
+            ];
+            func.push_stmt(self.while_stmt(x, self.compound(body)));
+            func.push_stmt(self.ret(Some(count)));
+        })
+    }
+
+    /// The helper `__rust_{op}_{uty}` taking a `uty` and returning a `uint32_t`,
+    /// defined by `build` the first time it is requested.
+    fn bit_count_fallback(
+        &self,
+        op: &str,
+        uty: CUintTy,
+        build: impl FnOnce(&'mx CFuncKind<'mx>),
+    ) -> CExpr<'mx> {
+        let name = format!("__rust_{op}_{}", CTy::UInt(uty).to_str_with(CIntNames::Rust));
+        let name = self.alloc_str(&name);
+        if !self.module().funcs.borrow().iter().any(|func| func.0.name == name) {
+            let u32 = CTy::UInt(CUintTy::U32);
+            let func = self.func(CFuncKind::new(name, u32, [CTy::UInt(uty)]));
+            func.mark_internal();
+            build(func);
+            self.module().push_func(CFunc::new_unchecked(func));
+        }
+        self.value(CValue::Func(name))
+    }
+}
+
+/// Choose the builtin for `unsigned int` or `unsigned long long` from
+/// `builtins`, by the width of `uty`.
+///
+/// `size_t` is not always an `unsigned long`, e.g. on LLP64, so it takes the
+/// `unsigned long long` builtin, which is at least as wide.
+fn by_width(uty: CUintTy, builtins: [&'static str; 2]) -> &'static str {
+    match uty {
+        CUintTy::U8 | CUintTy::U16 | CUintTy::U32 => builtins[0],
+        CUintTy::U64 | CUintTy::Usize => builtins[1],
+    }
+}
+
+/// The number of bits `uty` is widened by when passed as an `unsigned int`.
+fn promoted_bits(uty: CUintTy) -> i128 {
+    match uty {
+        CUintTy::U8 => 24,
+        CUintTy::U16 => 16,
+        CUintTy::U32 | CUintTy::U64 | CUintTy::Usize => 0,
+    }
+}
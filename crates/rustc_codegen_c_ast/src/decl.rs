@@ -1,7 +1,9 @@
 //! This module defines AST nodes for C declarations.
 
-use crate::expr::{CExpr, CValue};
+use crate::expr::{CExpr, CExprKind, CValue};
+use crate::func::{print_attrs, CFuncAttr};
 use crate::pretty::{Print, PrinterCtx, INDENT};
+use crate::stmt::print_block;
 use crate::ty::{print_declarator, CTy};
 use crate::ModuleCtx;
 
@@ -9,14 +11,89 @@ use crate::ModuleCtx;
 pub type CDecl<'mx> = &'mx CDeclKind<'mx>;
 
 /// C declaration kinds.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CDeclKind<'mx> {
-    /// Variable declaration consisting of a name, type, and optional initializer.
+    /// Variable declaration consisting of a name, type, optional initializer,
+    /// optional storage class, whether it is thread-local, and attributes, e.g.
+    /// of a declared function or the visibility of a global.
     ///
     /// Example:
     /// - `int foo;` // `ty val`
     /// - `int foo = bar` `ty val = expr`
-    Var { name: CValue<'mx>, ty: CTy<'mx>, init: Option<CExpr<'mx>> },
+    /// - `static int foo = bar` `storage ty val = expr`
+    /// - `static _Thread_local int foo = bar` `storage thread_local ty val = expr`
+    /// - `__attribute__((cold)) extern void foo(void)` `attrs storage ty val`
+    Var {
+        name: CValue<'mx>,
+        ty: CTy<'mx>,
+        init: Option<CExpr<'mx>>,
+        storage: Option<CStorageClass>,
+        thread_local: bool,
+        attrs: Vec<CFuncAttr>,
+    },
+    /// Static assertion, checked by the C compiler.
+    ///
+    /// Example:
+    /// - `_Static_assert(sizeof(int) == 4, "msg");` // C11 and later
+    /// - `extern char __rust_static_assert[(sizeof(int) == 4) ? 1 : -1];` // C99
+    StaticAssert { cond: CExpr<'mx>, msg: &'mx str },
+    /// Struct definition consisting of a tag and fields.
+    ///
+    /// Fields may be qualified, e.g. `volatile` for a block of memory-mapped
+    /// registers.
+    ///
+    /// Example:
+    /// - `struct foo { int32_t data[4]; };`
+    /// - `struct regs { volatile uint32_t STATUS; };`
+    Struct { tag: &'mx str, fields: Vec<(CTy<'mx>, &'mx str)> },
+    /// Enum definition consisting of a tag, an optional fixed underlying type,
+    /// and the variants with their values.
+    ///
+    /// Example:
+    /// - `enum foo { A = 0, B = 1, };`
+    /// - `enum foo : uint8_t { A = 0, B = 1, };` // C23
+    /// - `typedef uint8_t foo;` and a `#define` for each variant // before C23
+    Enum { tag: &'mx str, underlying: Option<CTy<'mx>>, variants: Vec<(&'mx str, i128)> },
+    /// Type definition consisting of a name and the aliased type.
+    ///
+    /// Example:
+    /// - `typedef int32_t (*cmp)(const void *, const void *);`
+    Typedef { name: &'mx str, ty: CTy<'mx> },
+    /// Named integer constant, which needs no storage unlike a variable.
+    ///
+    /// Example:
+    /// - `enum { FOO = 42 };` // `CConstStyle::Enum`
+    /// - `#define FOO 42` // `CConstStyle::Define`
+    Const { name: &'mx str, value: i128, style: CConstStyle },
+}
+
+/// How a named integer constant is declared, see [`CDeclKind::Const`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CConstStyle {
+    /// A constant of an anonymous enum, which is an `int` and visible to a
+    /// debugger, but limited to the range of `int`.
+    Enum,
+    /// A macro, which can have any value but is only substituted as text.
+    Define,
+}
+
+/// C storage class specifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CStorageClass {
+    /// `static`
+    Static,
+    /// `extern`
+    Extern,
+}
+
+impl CStorageClass {
+    /// Get the corresponding C keyword.
+    pub fn to_str(self) -> &'static str {
+        match self {
+            CStorageClass::Static => "static",
+            CStorageClass::Extern => "extern",
+        }
+    }
 }
 
 impl<'mx> ModuleCtx<'mx> {
@@ -27,24 +104,239 @@ impl<'mx> ModuleCtx<'mx> {
 
     /// Create a new variable declaration.
     pub fn var(self, name: CValue<'mx>, ty: CTy<'mx>, init: Option<CExpr<'mx>>) -> CDecl<'mx> {
-        self.decl(CDeclKind::Var { name, ty, init })
+        self.decl(CDeclKind::Var {
+            name,
+            ty,
+            init,
+            storage: None,
+            thread_local: false,
+            attrs: vec![],
+        })
+    }
+
+    /// Create a new variable declaration with a storage class.
+    pub fn var_with_storage(
+        self,
+        storage: CStorageClass,
+        name: CValue<'mx>,
+        ty: CTy<'mx>,
+        init: Option<CExpr<'mx>>,
+    ) -> CDecl<'mx> {
+        self.decl(CDeclKind::Var {
+            name,
+            ty,
+            init,
+            storage: Some(storage),
+            thread_local: false,
+            attrs: vec![],
+        })
+    }
+
+    /// Create a new zero-initialized variable declaration, e.g.
+    /// `struct foo _1 = {0};`, for an otherwise uninitialized local.
+    pub fn zeroed_var(self, name: CValue<'mx>, ty: CTy<'mx>) -> CDecl<'mx> {
+        self.var(name, ty, Some(self.init_list(vec![self.value(CValue::Scalar(0))])))
+    }
+
+    /// Create a new thread-local variable declaration, e.g. for a Rust
+    /// `#[thread_local]` static.
+    pub fn thread_local_var(
+        self,
+        storage: Option<CStorageClass>,
+        name: CValue<'mx>,
+        ty: CTy<'mx>,
+        init: Option<CExpr<'mx>>,
+    ) -> CDecl<'mx> {
+        self.decl(CDeclKind::Var { name, ty, init, storage, thread_local: true, attrs: vec![] })
+    }
+
+    /// Create a new struct definition.
+    pub fn struct_decl(self, tag: &'mx str, fields: Vec<(CTy<'mx>, &'mx str)>) -> CDecl<'mx> {
+        self.decl(CDeclKind::Struct { tag, fields })
+    }
+
+    /// Create a copy of a variable declaration with an additional attribute,
+    /// e.g. [`CFuncAttr::Hidden`] for a global which is not exported.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if the declaration is not a variable declaration.
+    pub fn with_attr(self, decl: CDecl<'mx>, attr: CFuncAttr) -> CDecl<'mx> {
+        let mut decl = decl.clone();
+        match &mut decl {
+            CDeclKind::Var { attrs, .. } => attrs.push(attr),
+            _ => panic!("only variable declarations can have attributes"),
+        }
+        self.decl(decl)
+    }
+
+    /// Create a new enum definition.
+    pub fn enum_decl(
+        self,
+        tag: &'mx str,
+        underlying: Option<CTy<'mx>>,
+        variants: Vec<(&'mx str, i128)>,
+    ) -> CDecl<'mx> {
+        self.decl(CDeclKind::Enum { tag, underlying, variants })
+    }
+
+    /// Create a new type definition.
+    pub fn typedef(self, name: &'mx str, ty: CTy<'mx>) -> CDecl<'mx> {
+        self.decl(CDeclKind::Typedef { name, ty })
+    }
+
+    /// Create a new named integer constant.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if an enum constant does not fit an `int`, which C requires.
+    pub fn const_decl(self, name: &'mx str, value: i128, style: CConstStyle) -> CDecl<'mx> {
+        if style == CConstStyle::Enum {
+            assert!(
+                i32::try_from(value).is_ok(),
+                "enum constant {name} = {value} does not fit int"
+            );
+        }
+        self.decl(CDeclKind::Const { name, value, style })
+    }
+
+    /// Create a new static assertion.
+    ///
+    /// The message is printed as a string literal as-is, so it should not contain
+    /// characters that need escaping.
+    pub fn static_assert(self, cond: CExpr<'mx>, msg: &'mx str) -> CDecl<'mx> {
+        self.decl(CDeclKind::StaticAssert { cond, msg })
     }
 }
 
 impl Print for CDecl<'_> {
     fn print_to(&self, ctx: &mut PrinterCtx) {
         match self {
-            CDeclKind::Var { name, ty, init } => {
+            CDeclKind::Var { name, ty, init, storage, thread_local, attrs } => {
                 ctx.ibox(INDENT, |ctx| {
-                    print_declarator(*ty, Some(*name), ctx);
+                    print_attrs(attrs, ctx);
+                    if let Some(storage) = storage {
+                        ctx.word(storage.to_str());
+                        ctx.nbsp();
+                    }
+                    if *thread_local {
+                        // C99 has no thread storage, fall back to the GNU keyword
+                        if ctx.standard().has_c11() {
+                            ctx.word("_Thread_local");
+                        } else {
+                            ctx.word("__thread");
+                        }
+                        ctx.nbsp();
+                    }
+                    print_declarator(*ty, Some(name), ctx);
                     if let Some(init) = init {
                         ctx.word(" =");
                         ctx.softbreak();
-                        init.print_to(ctx);
+                        if *ty == CTy::Bool
+                            && !init.is_bool()
+                            && !matches!(init, CExprKind::InitList(_))
+                        {
+                            // normalize an integer to 0 or 1 explicitly, instead of
+                            // relying on the implicit conversion
+                            ctx.word("(_Bool) (");
+                            init.print_to(ctx);
+                            ctx.word(" != 0)");
+                        } else {
+                            init.print_to(ctx);
+                        }
                     }
                     ctx.word(";");
                 });
             }
+            CDeclKind::StaticAssert { cond, msg } => {
+                ctx.ibox(INDENT, |ctx| {
+                    if ctx.standard().has_c11() {
+                        ctx.word("_Static_assert");
+                        ctx.cbox_delim(INDENT, ("(", ")"), 0, |ctx| {
+                            cond.print_to(ctx);
+                            ctx.word(",");
+                            ctx.softbreak();
+                            ctx.word(format!("\"{}\"", msg));
+                        });
+                    } else {
+                        // C99 has no static assertions, fall back to an array
+                        // declaration which is ill-formed if the size is negative.
+                        ctx.word("extern char __rust_static_assert[");
+                        cond.print_to(ctx);
+                        ctx.word(" ? 1 : -1]");
+                    }
+                    ctx.word(";");
+                });
+            }
+            CDeclKind::Struct { tag, fields } => {
+                struct Field<'a, 'mx>(&'a (CTy<'mx>, &'mx str));
+                impl Print for Field<'_, '_> {
+                    fn print_to(&self, ctx: &mut PrinterCtx) {
+                        let (ty, name) = self.0;
+                        ctx.ibox(INDENT, |ctx| {
+                            print_declarator(*ty, Some(name), ctx);
+                            ctx.word(";");
+                        });
+                    }
+                }
+
+                ctx.word(format!("struct {tag} "));
+                let fields: Vec<_> = fields.iter().map(Field).collect();
+                print_block(fields.iter().map(|field| field as &dyn Print), ctx);
+                ctx.word(";");
+            }
+            CDeclKind::Enum { tag, underlying, variants } => {
+                struct Variant<'a, 'mx>(&'a (&'mx str, i128));
+                impl Print for Variant<'_, '_> {
+                    fn print_to(&self, ctx: &mut PrinterCtx) {
+                        let (name, value) = self.0;
+                        ctx.word(format!("{name} = {value},"));
+                    }
+                }
+
+                match underlying {
+                    Some(ty) if !ctx.standard().has_c23() => {
+                        // without a fixed underlying type, the enum becomes a
+                        // typedef of the integer type and its variants macros
+                        ctx.word("typedef ");
+                        print_declarator(*ty, Some(tag), ctx);
+                        ctx.word(";");
+                        for (name, value) in variants {
+                            ctx.hardbreak();
+                            ctx.word(format!("#define {name} {value}"));
+                        }
+                    }
+                    _ => {
+                        ctx.word(format!("enum {tag} "));
+                        if let Some(ty) = underlying {
+                            ctx.word(": ");
+                            print_declarator(*ty, None, ctx);
+                            ctx.nbsp();
+                        }
+                        let variants: Vec<_> = variants.iter().map(Variant).collect();
+                        print_block(variants.iter().map(|variant| variant as &dyn Print), ctx);
+                        ctx.word(";");
+                    }
+                }
+            }
+            CDeclKind::Typedef { name, ty } => {
+                ctx.ibox(INDENT, |ctx| {
+                    ctx.word("typedef ");
+                    print_declarator(*ty, Some(name), ctx);
+                    ctx.word(";");
+                });
+            }
+            CDeclKind::Const { name, value, style: CConstStyle::Enum } => {
+                ctx.word(format!("enum {{ {name} = {value} }};"));
+            }
+            // a negative value is parenthesized, so that e.g. `x-FOO` does not
+            // become `x--1`
+            CDeclKind::Const { name, value, style: CConstStyle::Define } if *value < 0 => {
+                ctx.word(format!("#define {name} ({value})"));
+            }
+            CDeclKind::Const { name, value, style: CConstStyle::Define } => {
+                ctx.word(format!("#define {name} {value}"));
+            }
         }
     }
 }
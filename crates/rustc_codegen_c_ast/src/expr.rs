@@ -1,7 +1,7 @@
 //! This module defines the AST nodes for C expressions.
 
 use crate::pretty::{Print, PrinterCtx, INDENT};
-use crate::ty::{print_declarator, CTy};
+use crate::ty::{print_declarator, CFloatTy, CTy, CUintTy};
 use crate::ModuleCtx;
 
 /// Represents the values of C variables, parameters, and scalars.
@@ -12,28 +12,61 @@ use crate::ModuleCtx;
 pub enum CValue<'mx> {
     /// A constant scalar
     Scalar(i128),
+    /// A floating-point constant of the given type, stored as the bits of an
+    /// `f64` so that values can be compared and hashed, see [`ModuleCtx::float`].
+    Float(u64, CFloatTy),
     /// A local variable indexed by a number, in the form `_0`, `_1`, etc.
     Local(usize),
     /// A function name
     Func(&'mx str),
+    /// A global variable name
+    Global(&'mx str),
+    /// A named constant, e.g. an enum constant or a macro, see
+    /// [`ModuleCtx::define_const`].
+    Const(&'mx str),
 }
 
 /// C expressions.
 pub type CExpr<'mx> = &'mx CExprKind<'mx>;
 
 /// C expressions.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CExprKind<'mx> {
     /// A "raw" C expression, simply a string of C code, which is printed as-is.
     Raw(&'static str),
     /// A value, such as a constant, variable, or function name.
     Value(CValue<'mx>),
+    /// A string literal, e.g. `"foo"`.
+    ///
+    /// The content is arbitrary bytes, e.g. UTF-8 encoded text, and is escaped
+    /// when printed so that the output is plain ASCII.
+    Str(&'mx [u8]),
     /// A binary operation expression, e.g. `lhs + rhs`.
     Binary { lhs: CExpr<'mx>, rhs: CExpr<'mx>, op: &'static str },
+    /// A unary operation expression, e.g. `++x` or `x++`.
+    Unary {
+        op: &'static str,
+        expr: CExpr<'mx>,
+        /// Whether the operator is printed before the operand.
+        prefix: bool,
+    },
     /// A type cast expression, e.g. `(int) x`.
     Cast { ty: CTy<'mx>, expr: CExpr<'mx> },
+    /// A `sizeof` expression of a type, e.g. `sizeof(int32_t[4])`.
+    SizeOf(CTy<'mx>),
+    /// A `sizeof` expression of an expression, e.g. `sizeof(x)`.
+    ///
+    /// The operand is not evaluated.
+    SizeOfExpr(CExpr<'mx>),
+    /// An `offsetof` expression, e.g. `offsetof(struct foo, bar)`.
+    ///
+    /// The field is a member designator, so it may also name a nested field,
+    /// e.g. `bar.baz`.
+    OffsetOf { ty: CTy<'mx>, field: &'mx str },
     /// A function call expression, e.g. `foo(x, y)`.
     Call { callee: CExpr<'mx>, args: Vec<CExpr<'mx>> },
+    /// An array subscript expression, e.g. `a[i]`.
+    Index { base: CExpr<'mx>, index: CExpr<'mx> },
     /// A member access expression, e.g. `foo.bar` or `foo->bar`.
     Member {
         expr: CExpr<'mx>,
@@ -41,6 +74,104 @@ pub enum CExprKind<'mx> {
         arrow: bool,
         field: &'mx str,
     },
+    /// A generic selection expression, e.g. `_Generic(x, int: a, default: b)`.
+    ///
+    /// Requires C11 or later.
+    Generic {
+        controlling: CExpr<'mx>,
+        cases: Vec<(CTy<'mx>, CExpr<'mx>)>,
+        default: Option<CExpr<'mx>>,
+    },
+    /// A zero value of a type, as a compound literal, e.g. `(int32_t){0}`.
+    Zeroed(CTy<'mx>),
+    /// An initializer list, e.g. `{1, 2, 3}`.
+    ///
+    /// Only valid as the initializer of a declaration.
+    InitList(Vec<CExpr<'mx>>),
+    /// A designated initializer of a struct field, e.g. `.a = 1`.
+    ///
+    /// Only valid as an item of an initializer list.
+    Designated { field: &'mx str, expr: CExpr<'mx> },
+    /// A compound literal, e.g. `(struct foo){.a = 1, .c = 3}`.
+    CompoundLiteral { ty: CTy<'mx>, items: Vec<CExpr<'mx>> },
+    /// A comma expression, e.g. `(f(), g(), x)`, evaluating the expressions in
+    /// order and yielding the last one.
+    ///
+    /// It is always parenthesized, as the comma has the lowest precedence.
+    Comma(Vec<CExpr<'mx>>),
+    /// A conditional expression, e.g. `(a ? b : c)`, evaluating only one of
+    /// `then` and `els`.
+    ///
+    /// Like a binary operation, it is parenthesized, except when chained in
+    /// the else position of another one, e.g. `(a ? b : c ? d : e)`.
+    Ternary { cond: CExpr<'mx>, then: CExpr<'mx>, els: CExpr<'mx> },
+    /// An expression using a GNU extension, e.g. `__builtin_expect(x, 1)`.
+    ///
+    /// Without GNU extensions, the fallback is printed instead, or nothing at
+    /// all for a hint which is only used as a statement, e.g. a prefetch.
+    Gnu { expr: CExpr<'mx>, fallback: Option<CExpr<'mx>> },
+}
+
+impl CExprKind<'_> {
+    /// Whether the expression is known to evaluate to 0 or 1, e.g. a
+    /// comparison, so that it needs no normalization to be used as a `_Bool`.
+    pub fn is_bool(&self) -> bool {
+        match self {
+            CExprKind::Binary { op, .. } => {
+                matches!(*op, "==" | "!=" | "<" | ">" | "<=" | ">=" | "&&" | "||")
+            }
+            CExprKind::Unary { op, .. } => *op == "!",
+            CExprKind::Cast { ty, .. } => *ty == CTy::Bool,
+            CExprKind::Value(CValue::Scalar(value)) => matches!(value, 0 | 1),
+            CExprKind::Ternary { then, els, .. } => then.is_bool() && els.is_bool(),
+            _ => false,
+        }
+    }
+
+    /// Whether the expression is a constant expression, and thus may
+    /// initialize a variable with static storage duration.
+    ///
+    /// This is conservative, except for raw expressions which are trusted to
+    /// be constant.
+    pub fn is_constant(&self) -> bool {
+        match self {
+            CExprKind::Raw(_)
+            | CExprKind::Str(_)
+            | CExprKind::Value(
+                CValue::Scalar(_) | CValue::Float(..) | CValue::Func(_) | CValue::Const(_),
+            )
+            | CExprKind::SizeOf(_)
+            | CExprKind::SizeOfExpr(_)
+            | CExprKind::OffsetOf { .. } => true,
+            CExprKind::Binary { lhs, rhs, op } => {
+                // neither assignments nor the comma operator may appear in a
+                // constant expression
+                let assign = op.ends_with('=') && !matches!(*op, "==" | "!=" | "<=" | ">=");
+                !assign && *op != "," && lhs.is_constant() && rhs.is_constant()
+            }
+            CExprKind::Unary { op: "&", expr, .. } => {
+                matches!(expr, CExprKind::Value(CValue::Func(_) | CValue::Global(_)))
+            }
+            CExprKind::Unary { op, expr, .. } => !matches!(*op, "++" | "--") && expr.is_constant(),
+            CExprKind::Cast { expr, .. } => expr.is_constant(),
+            CExprKind::InitList(items) => items.iter().all(|item| item.is_constant()),
+            CExprKind::Designated { expr, .. } => expr.is_constant(),
+            CExprKind::Ternary { cond, then, els } => {
+                cond.is_constant() && then.is_constant() && els.is_constant()
+            }
+            CExprKind::Gnu { expr, fallback } => {
+                expr.is_constant() && fallback.is_some_and(|fallback| fallback.is_constant())
+            }
+            CExprKind::Value(CValue::Local(_) | CValue::Global(_))
+            | CExprKind::Call { .. }
+            | CExprKind::Index { .. }
+            | CExprKind::Member { .. }
+            | CExprKind::Generic { .. }
+            | CExprKind::Zeroed(_)
+            | CExprKind::CompoundLiteral { .. }
+            | CExprKind::Comma(_) => false,
+        }
+    }
 }
 
 impl<'mx> ModuleCtx<'mx> {
@@ -59,42 +190,342 @@ impl<'mx> ModuleCtx<'mx> {
         self.expr(CExprKind::Value(value))
     }
 
+    /// Create a new floating-point literal expression of type `ty`, e.g. `1.5f`
+    /// for `float` or `3.14` for `double`.
+    ///
+    /// Infinities and NaNs have no literal, so they are printed as the
+    /// `INFINITY` and `NAN` macros from `<math.h>`.
+    pub fn float(&self, value: f64, ty: CFloatTy) -> CExpr<'mx> {
+        let value = match ty {
+            CFloatTy::F32 => value as f32 as f64,
+            CFloatTy::F64 => value,
+        };
+        if !value.is_finite() {
+            self.module().require_include("math.h");
+        }
+        self.value(CValue::Float(value.to_bits(), ty))
+    }
+
+    /// Create a new string literal expression.
+    pub fn string(&self, s: impl AsRef<[u8]>) -> CExpr<'mx> {
+        let s = self.arena().alloc_slice(s.as_ref());
+        self.expr(CExprKind::Str(s))
+    }
+
     /// Create a new binary expression.
     pub fn binary(&self, lhs: CExpr<'mx>, rhs: CExpr<'mx>, op: &'static str) -> CExpr<'mx> {
         self.expr(CExprKind::Binary { lhs, rhs, op })
     }
 
+    /// Create a new wrapping arithmetic expression on values of integer type `ty`.
+    ///
+    /// C promotes operands narrower than `int` to `int` before the operation, so
+    /// the result may not fit `ty`, e.g. `200 + 100` for `uint8_t`. The result is
+    /// cast back to `ty`, which wraps it like Rust does. Products of promoted
+    /// 16-bit operands can overflow `int`, so they are multiplied as `uint32_t`.
+    pub fn wrapping_binary(
+        &self,
+        ty: CTy<'mx>,
+        lhs: CExpr<'mx>,
+        rhs: CExpr<'mx>,
+        op: &'static str,
+    ) -> CExpr<'mx> {
+        if !ty.is_promoted() {
+            return self.binary(lhs, rhs, op);
+        }
+
+        let (lhs, rhs) = if op == "*" {
+            let uint = CTy::UInt(CUintTy::U32);
+            (self.cast(uint, lhs), self.cast(uint, rhs))
+        } else {
+            (lhs, rhs)
+        };
+        self.cast(ty, self.binary(lhs, rhs, op))
+    }
+
+    /// Create a new prefix unary expression, e.g. `-x`, `!x`, `~x` or `++x`.
+    ///
+    /// The operand is parenthesized where needed, e.g. `-(x + y)` or `-(-x)`.
+    pub fn unary(&self, op: &'static str, expr: CExpr<'mx>) -> CExpr<'mx> {
+        self.expr(CExprKind::Unary { op, expr, prefix: true })
+    }
+
+    /// Create a new postfix unary expression, e.g. `x++`.
+    ///
+    /// Unlike the prefix form, the value of `x++` is the value of `x` before
+    /// the increment.
+    pub fn postfix(&self, op: &'static str, expr: CExpr<'mx>) -> CExpr<'mx> {
+        self.expr(CExprKind::Unary { op, expr, prefix: false })
+    }
+
+    /// Create a load of a `ty` from `ptr`, e.g. `*(int32_t *) _0`.
+    ///
+    /// The pointer is cast to `ty *` first, unless it is already a cast to
+    /// that type.
+    pub fn load(&self, ptr: CExpr<'mx>, ty: CTy<'mx>) -> CExpr<'mx> {
+        let ptr_ty = self.ptr(ty);
+        let ptr = match ptr {
+            CExprKind::Cast { ty, .. } if *ty == ptr_ty => ptr,
+            _ => self.cast(ptr_ty, ptr),
+        };
+        self.unary("*", ptr)
+    }
+
+    /// Create a new expression using a GNU extension, printed as `fallback`
+    /// without them, see [`CExprKind::Gnu`].
+    pub fn gnu(&self, expr: CExpr<'mx>, fallback: Option<CExpr<'mx>>) -> CExpr<'mx> {
+        self.expr(CExprKind::Gnu { expr, fallback })
+    }
+
+    /// Create a new `__builtin_expect(expr, expected)` expression, hinting that
+    /// `expr` is most likely `expected`, e.g. `0` for a condition guarding a
+    /// call to a cold function.
+    ///
+    /// The hint is a GNU extension, without which `expr` is printed as-is.
+    pub fn builtin_expect(&self, expr: CExpr<'mx>, expected: i128) -> CExpr<'mx> {
+        self.gnu(self.expect_call(expr, expected), Some(expr))
+    }
+
+    /// Create a new expression hinting that `cond` is most likely true, e.g.
+    /// `__builtin_expect(!!_0, 1)`.
+    ///
+    /// The hint is a GNU extension, without which `cond` is returned as-is.
+    pub fn likely(&self, cond: CExpr<'mx>) -> CExpr<'mx> {
+        self.expect_bool(cond, true)
+    }
+
+    /// Create a new expression hinting that `cond` is most likely false, e.g.
+    /// `__builtin_expect(!!_0, 0)`.
+    ///
+    /// The hint is a GNU extension, without which `cond` is returned as-is.
+    pub fn unlikely(&self, cond: CExpr<'mx>) -> CExpr<'mx> {
+        self.expect_bool(cond, false)
+    }
+
+    fn expect_bool(&self, cond: CExpr<'mx>, expected: bool) -> CExpr<'mx> {
+        // `!!` normalizes the condition to `0` or `1` to compare with the expected value
+        let normalized = self.unary("!", self.unary("!", cond));
+        self.gnu(self.expect_call(normalized, expected as i128), Some(cond))
+    }
+
+    fn expect_call(&self, expr: CExpr<'mx>, expected: i128) -> CExpr<'mx> {
+        let callee = self.value(CValue::Func("__builtin_expect"));
+        self.call(callee, vec![expr, self.value(CValue::Scalar(expected))])
+    }
+
+    /// Create a new null pointer check, e.g. `(p == NULL)`.
+    ///
+    /// `<stddef.h>` is included in the module for `NULL`.
+    pub fn is_null(&self, ptr: CExpr<'mx>) -> CExpr<'mx> {
+        self.module().require_include("stddef.h");
+        self.binary(ptr, self.raw("NULL"), "==")
+    }
+
+    /// Create a new logical and expression, e.g. `a && b`.
+    ///
+    /// `rhs` is kept inside the `&&` expression, so it is evaluated only if
+    /// `lhs` is true, matching Rust's `&&`. When lowering `a && b`, `b` must not
+    /// be evaluated into a temporary beforehand, otherwise its side effects
+    /// happen regardless of `a`.
+    pub fn logical_and_lazy(&self, lhs: CExpr<'mx>, rhs: CExpr<'mx>) -> CExpr<'mx> {
+        self.binary(lhs, rhs, "&&")
+    }
+
+    /// Create a new right shift expression on a value of integer type `ty`.
+    ///
+    /// In C, `>>` is an arithmetic shift if the left operand is signed, and a
+    /// logical shift if it is unsigned. When the signedness of `ty` does not
+    /// match the requested shift, the left operand is cast to the signed or
+    /// unsigned counterpart of `ty` first, and the result is cast back to `ty`.
+    pub fn shr(
+        &self,
+        ty: CTy<'mx>,
+        lhs: CExpr<'mx>,
+        rhs: CExpr<'mx>,
+        arithmetic: bool,
+    ) -> CExpr<'mx> {
+        if ty.is_signed() == arithmetic {
+            return self.binary(lhs, rhs, ">>");
+        }
+
+        let shift_ty = if arithmetic { ty.to_signed() } else { ty.to_unsigned() };
+        self.cast(ty, self.binary(self.cast(shift_ty, lhs), rhs, ">>"))
+    }
+
+    /// Create a new comparison expression, e.g. `lhs < rhs`, between values of
+    /// integer types `lhs_ty` and `rhs_ty`, performed as a signed comparison if
+    /// `signed` is true, and as an unsigned one otherwise.
+    ///
+    /// In C, comparing a signed and an unsigned operand converts the signed one
+    /// to unsigned, so e.g. `-1 < (size_t) 1` is false. Each operand whose
+    /// signedness does not match the comparison is cast to its signed or
+    /// unsigned counterpart first. Operands of other types, e.g. pointers, are
+    /// compared as-is.
+    pub fn compare(
+        &self,
+        lhs_ty: CTy<'mx>,
+        lhs: CExpr<'mx>,
+        rhs_ty: CTy<'mx>,
+        rhs: CExpr<'mx>,
+        op: &'static str,
+        signed: bool,
+    ) -> CExpr<'mx> {
+        let operand = |ty: CTy<'mx>, expr| match ty {
+            CTy::Int(_) if !signed => self.cast(ty.to_unsigned(), expr),
+            CTy::UInt(_) if signed => self.cast(ty.to_signed(), expr),
+            _ => expr,
+        };
+        self.binary(operand(lhs_ty, lhs), operand(rhs_ty, rhs), op)
+    }
+
     /// Create a new cast expression.
     pub fn cast(&self, ty: CTy<'mx>, expr: CExpr<'mx>) -> CExpr<'mx> {
         self.expr(CExprKind::Cast { ty, expr })
     }
 
+    /// Create a new `sizeof` expression of a type.
+    ///
+    /// The size of an array type is printed as e.g. `sizeof(int32_t[4])`,
+    /// rather than computed from its element type and length.
+    pub fn sizeof(&self, ty: CTy<'mx>) -> CExpr<'mx> {
+        self.expr(CExprKind::SizeOf(ty))
+    }
+
+    /// Create a new `sizeof` expression of an expression.
+    pub fn sizeof_expr(&self, expr: CExpr<'mx>) -> CExpr<'mx> {
+        self.expr(CExprKind::SizeOfExpr(expr))
+    }
+
+    /// Create a new `offsetof` expression.
+    ///
+    /// `<stddef.h>` is included in the module for `offsetof`.
+    pub fn offsetof(&self, ty: CTy<'mx>, field: &'mx str) -> CExpr<'mx> {
+        self.module().require_include("stddef.h");
+        self.expr(CExprKind::OffsetOf { ty, field })
+    }
+
     /// Create a new function call expression.
     pub fn call(&self, callee: CExpr<'mx>, args: Vec<CExpr<'mx>>) -> CExpr<'mx> {
         self.expr(CExprKind::Call { callee, args })
     }
 
+    /// Create a new array subscript expression, e.g. `a[i]`.
+    ///
+    /// The base is parenthesized where needed, e.g. `(*p)[i]`.
+    pub fn index(&self, base: CExpr<'mx>, index: CExpr<'mx>) -> CExpr<'mx> {
+        self.expr(CExprKind::Index { base, index })
+    }
+
     /// Create a new member access expression.
     pub fn member(&self, expr: CExpr<'mx>, field: &'mx str) -> CExpr<'mx> {
         self.expr(CExprKind::Member { expr, field, arrow: false })
     }
+
+    /// Create a new generic selection expression.
+    pub fn generic(
+        &self,
+        controlling: CExpr<'mx>,
+        cases: Vec<(CTy<'mx>, CExpr<'mx>)>,
+        default: Option<CExpr<'mx>>,
+    ) -> CExpr<'mx> {
+        self.expr(CExprKind::Generic { controlling, cases, default })
+    }
+
+    /// Create a new zero value of type `ty`, e.g. `(struct foo){0}`.
+    ///
+    /// This stands in for uninitialized values, so that the generated code is
+    /// deterministic instead of reading uninitialized memory.
+    pub fn zeroed(&self, ty: CTy<'mx>) -> CExpr<'mx> {
+        self.expr(CExprKind::Zeroed(ty))
+    }
+
+    /// Create a new initializer list.
+    pub fn init_list(&self, items: Vec<CExpr<'mx>>) -> CExpr<'mx> {
+        self.expr(CExprKind::InitList(items))
+    }
+
+    /// Create a designated initializer of a struct field, e.g. `.a = 1`, to be
+    /// used as an item of an initializer list or a compound literal.
+    pub fn designated(&self, field: &'mx str, expr: CExpr<'mx>) -> CExpr<'mx> {
+        self.expr(CExprKind::Designated { field, expr })
+    }
+
+    /// Create a struct value from its fields, as a compound literal of
+    /// designated initializers, e.g. `(struct foo){.a = 1, .c = 3}`.
+    ///
+    /// The fields not listed are zero-initialized, so they can be given in any
+    /// order and omitted where a zero value is wanted anyway.
+    pub fn struct_literal(&self, ty: CTy<'mx>, fields: Vec<(&'mx str, CExpr<'mx>)>) -> CExpr<'mx> {
+        if fields.is_empty() {
+            // an empty initializer list is only valid since C23
+            return self.zeroed(ty);
+        }
+        let items = fields.into_iter().map(|(field, expr)| self.designated(field, expr)).collect();
+        self.expr(CExprKind::CompoundLiteral { ty, items })
+    }
+
+    /// Create a new conditional expression, e.g. `(_0 ? 1 : 2)`.
+    pub fn ternary(&self, cond: CExpr<'mx>, then: CExpr<'mx>, els: CExpr<'mx>) -> CExpr<'mx> {
+        self.expr(CExprKind::Ternary { cond, then, els })
+    }
+
+    /// Create a new comma expression.
+    pub fn comma(&self, exprs: Vec<CExpr<'mx>>) -> CExpr<'mx> {
+        assert!(!exprs.is_empty(), "a comma expression needs at least one operand");
+        self.expr(CExprKind::Comma(exprs))
+    }
+}
+
+impl CValue<'_> {
+    /// Whether the value is printed as a literal starting with a `-`.
+    fn is_negative_literal(&self) -> bool {
+        match *self {
+            CValue::Scalar(value) => value < 0,
+            CValue::Float(bits, _) => {
+                let value = f64::from_bits(bits);
+                value.is_sign_negative() && !value.is_nan()
+            }
+            _ => false,
+        }
+    }
 }
 
 impl Print for CValue<'_> {
     fn print_to(&self, ctx: &mut PrinterCtx) {
         match self {
             CValue::Scalar(i) => ctx.word(i.to_string()),
+            CValue::Float(bits, ty) => ctx.word(float_literal(f64::from_bits(*bits), *ty)),
             CValue::Local(i) => ctx.word(format!("_{}", i)),
             CValue::Func(name) => ctx.word(name.to_string()),
+            CValue::Global(name) => ctx.word(name.to_string()),
+            CValue::Const(name) => ctx.word(name.to_string()),
         }
     }
 }
 
+/// Format a floating-point literal, keeping a decimal point or an exponent so
+/// that it is not read as an integer, e.g. `0.0` rather than `0`.
+fn float_literal(value: f64, ty: CFloatTy) -> String {
+    if value.is_nan() {
+        return "NAN".to_string();
+    }
+    if value.is_infinite() {
+        return if value < 0.0 { "-INFINITY" } else { "INFINITY" }.to_string();
+    }
+    // `Debug` prints the shortest representation that round-trips, always with
+    // a decimal point or an exponent
+    match ty {
+        CFloatTy::F32 => format!("{:?}f", value as f32),
+        CFloatTy::F64 => format!("{value:?}"),
+    }
+}
+
 impl Print for CExpr<'_> {
     fn print_to(&self, ctx: &mut PrinterCtx) {
         match self {
             CExprKind::Raw(raw) => ctx.word(*raw),
             CExprKind::Value(value) => value.print_to(ctx),
+            CExprKind::Str(s) => print_str(s, ctx),
             CExprKind::Binary { lhs, rhs, op } => ctx.ibox_delim(INDENT, ("(", ")"), 0, |ctx| {
                 ctx.ibox(-INDENT, |ctx| lhs.print_to(ctx));
 
@@ -104,20 +535,79 @@ impl Print for CExpr<'_> {
 
                 rhs.print_to(ctx);
             }),
+            CExprKind::Unary { op, expr, prefix } => {
+                // Operands binding looser than the operator are parenthesized,
+                // and so are nested prefix operators which would otherwise be
+                // lexed as another token, e.g. `- -x` as `--x`. A negative
+                // literal starts with a `-` too, e.g. `-(-1)` rather than `--1`.
+                let paren = match expr {
+                    CExprKind::Cast { .. } => !prefix,
+                    CExprKind::Unary { op: inner, prefix: true, .. } => {
+                        !prefix || (op.ends_with(['+', '-', '&']) && op.ends_with(&inner[..1]))
+                    }
+                    CExprKind::Value(value) if value.is_negative_literal() => {
+                        !prefix || op.ends_with('-')
+                    }
+                    _ => false,
+                };
+                if *prefix {
+                    ctx.word(*op);
+                }
+                if paren {
+                    ctx.word("(");
+                    expr.print_to(ctx);
+                    ctx.word(")");
+                } else {
+                    expr.print_to(ctx);
+                }
+                if !prefix {
+                    ctx.word(*op);
+                }
+            }
             CExprKind::Cast { ty, expr } => ctx.ibox(INDENT, |ctx| {
                 ctx.word("(");
                 print_declarator(*ty, None, ctx);
                 ctx.word(")");
 
+                // A cast binds like a prefix operator, and the operands binding
+                // looser, i.e. binary operations, print their own parentheses,
+                // so the operand never needs more, e.g. `(int32_t) -x`.
                 ctx.nbsp();
                 expr.print_to(ctx);
             }),
+            CExprKind::SizeOf(ty) => {
+                ctx.word("sizeof(");
+                print_declarator(*ty, None, ctx);
+                ctx.word(")");
+            }
+            CExprKind::SizeOfExpr(expr) => ctx.ibox_delim(INDENT, ("sizeof(", ")"), 0, |ctx| {
+                expr.print_to(ctx);
+            }),
+            CExprKind::OffsetOf { ty, field } => {
+                ctx.word("offsetof(");
+                print_declarator(*ty, None, ctx);
+                ctx.word(format!(", {field})"));
+            }
             CExprKind::Call { callee, args } => ctx.ibox(INDENT, |ctx| {
                 callee.print_to(ctx);
                 ctx.cbox_delim(INDENT, ("(", ")"), 0, |ctx| {
                     ctx.seperated(",", args, |ctx, arg| arg.print_to(ctx));
                 });
             }),
+            CExprKind::Index { base, index } => ctx.ibox(INDENT, |ctx| {
+                // The subscript binds tighter than casts and prefix operators,
+                // while binary operations print their own parentheses.
+                let paren =
+                    matches!(base, CExprKind::Cast { .. } | CExprKind::Unary { prefix: true, .. });
+                if paren {
+                    ctx.word("(");
+                    base.print_to(ctx);
+                    ctx.word(")");
+                } else {
+                    base.print_to(ctx);
+                }
+                ctx.ibox_delim(INDENT, ("[", "]"), 0, |ctx| index.print_to(ctx));
+            }),
             CExprKind::Member { expr, arrow, field } => ctx.cbox(INDENT, |ctx| {
                 expr.print_to(ctx);
                 ctx.zerobreak();
@@ -128,6 +618,116 @@ impl Print for CExpr<'_> {
                 }
                 ctx.word(field.to_string());
             }),
+            CExprKind::Generic { controlling, cases, default } => ctx.ibox(INDENT, |ctx| {
+                let standard = ctx.standard();
+                assert!(standard.has_c11(), "`_Generic` is not available in {}", standard.to_str());
+                ctx.word("_Generic");
+                ctx.cbox_delim(INDENT, ("(", ")"), 0, |ctx| {
+                    controlling.print_to(ctx);
+                    for (ty, expr) in cases {
+                        ctx.word(",");
+                        ctx.softbreak();
+                        ctx.ibox(INDENT, |ctx| {
+                            print_declarator(*ty, None, ctx);
+                            ctx.word(":");
+                            ctx.softbreak();
+                            expr.print_to(ctx);
+                        });
+                    }
+                    if let Some(default) = default {
+                        ctx.word(",");
+                        ctx.softbreak();
+                        ctx.ibox(INDENT, |ctx| {
+                            ctx.word("default:");
+                            ctx.softbreak();
+                            default.print_to(ctx);
+                        });
+                    }
+                });
+            }),
+            CExprKind::Zeroed(ty) => {
+                ctx.word("(");
+                print_declarator(*ty, None, ctx);
+                ctx.word("){0}");
+            }
+            CExprKind::InitList(items) => ctx.cbox_delim(INDENT, ("{", "}"), 0, |ctx| {
+                ctx.seperated(",", items, |ctx, item| item.print_to(ctx));
+            }),
+            CExprKind::Designated { field, expr } => {
+                ctx.word(format!(".{field} = "));
+                expr.print_to(ctx);
+            }
+            CExprKind::CompoundLiteral { ty, items } => {
+                ctx.word("(");
+                print_declarator(*ty, None, ctx);
+                ctx.word(")");
+                ctx.cbox_delim(INDENT, ("{", "}"), 0, |ctx| {
+                    ctx.seperated(",", items, |ctx, item| item.print_to(ctx));
+                });
+            }
+            CExprKind::Comma(exprs) => ctx.cbox_delim(INDENT, ("(", ")"), 0, |ctx| {
+                ctx.seperated(",", exprs, |ctx, expr| expr.print_to(ctx));
+            }),
+            CExprKind::Ternary { .. } => {
+                ctx.cbox_delim(INDENT, ("(", ")"), 0, |ctx| print_ternary(self, ctx))
+            }
+            CExprKind::Gnu { expr, fallback } => {
+                if ctx.standard().has_gnu() {
+                    expr.print_to(ctx);
+                } else if let Some(fallback) = fallback {
+                    fallback.print_to(ctx);
+                }
+            }
+        }
+    }
+}
+
+/// Print the operands of a conditional expression without the parentheses.
+///
+/// `?:` is right-associative, so a conditional expression in the else position
+/// is chained without parentheses, e.g. `a ? b : c ? d : e`, while one in the
+/// condition or then position keeps them.
+fn print_ternary(expr: CExpr, ctx: &mut PrinterCtx) {
+    let CExprKind::Ternary { cond, then, els } = expr else {
+        unreachable!("not a conditional expression: {expr:?}")
+    };
+    cond.print_to(ctx);
+    ctx.softbreak();
+    ctx.word("? ");
+    then.print_to(ctx);
+    ctx.softbreak();
+    ctx.word(": ");
+    match els {
+        CExprKind::Ternary { .. } => print_ternary(els, ctx),
+        _ => els.print_to(ctx),
+    }
+}
+
+/// Print a string literal, escaping the bytes that are not printable ASCII.
+pub(crate) fn print_str(bytes: &[u8], ctx: &mut PrinterCtx) {
+    let mut lit = String::from("\"");
+    let mut after_hex = false;
+    for &b in bytes {
+        if after_hex && b.is_ascii_hexdigit() {
+            // A hexadecimal escape sequence consumes all the following hex
+            // digits, so the literal is split to terminate it.
+            lit.push_str("\" \"");
+        }
+        after_hex = false;
+        match b {
+            b'"' => lit.push_str("\\\""),
+            b'\\' => lit.push_str("\\\\"),
+            b'?' => lit.push_str("\\?"), // avoid trigraphs
+            b'\n' => lit.push_str("\\n"),
+            b'\r' => lit.push_str("\\r"),
+            b'\t' => lit.push_str("\\t"),
+            b' '..=b'~' => lit.push(b as char),
+            _ => {
+                lit.push_str(&format!("\\x{:02x}", b));
+                after_hex = true;
+            }
         }
     }
+    lit.push('"');
+    ctx.word(lit);
 }
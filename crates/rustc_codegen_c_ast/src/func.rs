@@ -4,9 +4,10 @@ use std::cell::{Cell, RefCell};
 
 use rustc_data_structures::intern::Interned;
 
-use crate::expr::CValue;
+use crate::expr::{CExpr, CValue};
+use crate::module::{diff_field, diff_nodes};
 use crate::pretty::{Print, PrinterCtx};
-use crate::stmt::{print_compound, CStmt};
+use crate::stmt::{print_block, CStmt};
 use crate::ty::{print_declarator, CTy};
 use crate::ModuleCtx;
 
@@ -14,6 +15,13 @@ use crate::ModuleCtx;
 pub type CFunc<'mx> = Interned<'mx, CFuncKind<'mx>>;
 
 /// C function definition.
+///
+/// Drop glue and other cleanup shared by all the exits of a function can be
+/// emitted once in a cleanup block at the end of the body: the statements are
+/// registered with [`CFuncKind::push_cleanup`], every exit is lowered with
+/// [`ModuleCtx::ret_via_cleanup`] to store the return value and jump to the
+/// block, and the block is emitted by [`ModuleCtx::push_cleanup_block`] after
+/// the rest of the body.
 #[derive(Debug, Clone)]
 pub struct CFuncKind<'mx> {
     /// Function name.
@@ -24,11 +32,42 @@ pub struct CFuncKind<'mx> {
     pub params: Vec<(CTy<'mx>, CValue<'mx>)>,
     /// Function body.
     pub body: RefCell<Vec<CStmt<'mx>>>,
+    /// Indices of the parameters which are never used in the body.
+    unused_params: RefCell<Vec<usize>>,
+    /// Function attributes.
+    attrs: RefCell<Vec<CFuncAttr>>,
+    /// Whether the function has internal linkage, i.e. is `static`.
+    internal: Cell<bool>,
+    /// Statements of the cleanup block.
+    cleanup: RefCell<Vec<CStmt<'mx>>>,
+    /// The local holding the return value while the cleanup block runs.
+    cleanup_ret: Cell<Option<CValue<'mx>>>,
     /// A counter for local variables, for generating unique names.
     local_var_counter: Cell<usize>,
 }
 
 impl<'mx> CFuncKind<'mx> {
+    /// Compare the function with another one of the same name, pushing a line
+    /// to `diffs` for each difference, see [`Module::diff`](crate::module::Module::diff).
+    pub(crate) fn diff(&self, other: &Self, diffs: &mut Vec<String>) {
+        let name = self.name;
+        if self.ty != other.ty || self.params != other.params {
+            diffs.push(format!("{name}: signatures differ"));
+        }
+        let (attrs, other_attrs) = (self.attrs.borrow(), other.attrs.borrow());
+        diff_field(&format!("{name} attrs"), &*attrs, &*other_attrs, diffs);
+        let (unused, other_unused) = (self.unused_params.borrow(), other.unused_params.borrow());
+        diff_field(&format!("{name} unused params"), &*unused, &*other_unused, diffs);
+        let (internal, other_internal) = (self.internal.get(), other.internal.get());
+        diff_field(&format!("{name} internal"), &internal, &other_internal, diffs);
+        let (ret, other_ret) = (self.cleanup_ret.get(), other.cleanup_ret.get());
+        diff_field(&format!("{name} cleanup ret"), &ret, &other_ret, diffs);
+        let (body, other_body) = (self.body.borrow(), other.body.borrow());
+        diff_nodes(&format!("{name} body"), &body, &other_body, diffs);
+        let (cleanup, other_cleanup) = (self.cleanup.borrow(), other.cleanup.borrow());
+        diff_nodes(&format!("{name} cleanup"), &cleanup, &other_cleanup, diffs);
+    }
+
     /// Make a new function definition.
     pub fn new(name: &'mx str, ty: CTy<'mx>, params: impl IntoIterator<Item = CTy<'mx>>) -> Self {
         let params = params
@@ -38,7 +77,18 @@ impl<'mx> CFuncKind<'mx> {
             .collect::<Vec<_>>();
         let local_var_counter = Cell::new(params.len());
 
-        Self { name, ty, params, body: RefCell::new(Vec::new()), local_var_counter }
+        Self {
+            name,
+            ty,
+            params,
+            body: RefCell::new(Vec::new()),
+            unused_params: RefCell::new(Vec::new()),
+            attrs: RefCell::new(Vec::new()),
+            internal: Cell::new(false),
+            cleanup: RefCell::new(Vec::new()),
+            cleanup_ret: Cell::new(None),
+            local_var_counter,
+        }
     }
 
     /// Push a statement to the end of the function body.
@@ -46,7 +96,63 @@ impl<'mx> CFuncKind<'mx> {
         self.body.borrow_mut().push(stmt);
     }
 
+    /// Push a statement to the end of the cleanup block, which runs before the
+    /// function returns through [`ModuleCtx::ret_via_cleanup`].
+    pub fn push_cleanup(&self, stmt: CStmt<'mx>) {
+        self.cleanup.borrow_mut().push(stmt);
+    }
+
+    /// Mark the parameter at `index` as unused.
+    ///
+    /// A `(void) _N;` discard is printed at the top of the body for each unused
+    /// parameter, which keeps `-Wunused-parameter` quiet.
+    pub fn mark_param_unused(&self, index: usize) {
+        assert!(index < self.params.len(), "parameter index {index} out of range");
+        self.unused_params.borrow_mut().push(index);
+    }
+
+    /// Add an attribute to the function, unless it is already present.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if the attribute conflicts with one already present, e.g.
+    /// `noinline` and `always_inline`.
+    pub fn add_attr(&self, attr: CFuncAttr) {
+        let mut attrs = self.attrs.borrow_mut();
+        if let Some(other) = attrs.iter().find(|other| other.conflicts_with(attr)) {
+            panic!("`{}` conflicts with `{}` on `{}`", attr.to_str(), other.to_str(), self.name);
+        }
+        if !attrs.contains(&attr) {
+            attrs.push(attr);
+        }
+    }
+
+    /// Mark the function as `#[must_use]`, so that the C compiler warns about
+    /// callers ignoring its result.
+    ///
+    /// This has no effect on functions returning `void`.
+    pub fn set_must_use(&self) {
+        if self.ty != CTy::Void {
+            self.add_attr(CFuncAttr::WarnUnusedResult);
+        }
+    }
+
+    /// Give the function internal linkage, printing it as `static`, e.g. for a
+    /// helper defined in each C file which needs it.
+    pub fn mark_internal(&self) {
+        self.internal.set(true);
+    }
+
+    /// Whether the function has internal linkage.
+    pub fn is_internal(&self) -> bool {
+        self.internal.get()
+    }
+
     /// Get a new unique local variable.
+    ///
+    /// Locals are numbered in the order they are requested, so callers must
+    /// request them in a stable order, e.g. following the MIR, and never while
+    /// iterating a hash map, to keep the output deterministic.
     pub fn next_local_var(&self) -> CValue {
         let val = CValue::Local(self.local_var_counter.get());
         self.local_var_counter.set(self.local_var_counter.get() + 1);
@@ -54,11 +160,153 @@ impl<'mx> CFuncKind<'mx> {
     }
 }
 
+/// C function attributes, printed as `__attribute__((...))` before the function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CFuncAttr {
+    /// `warn_unused_result`, warns when a caller ignores the return value.
+    WarnUnusedResult,
+    /// `always_inline`, from `#[inline(always)]`.
+    AlwaysInline,
+    /// `noinline`, from `#[inline(never)]`.
+    NoInline,
+    /// `cold`, from `#[cold]`, moves the function and the paths leading to it
+    /// out of the hot code.
+    Cold,
+    /// `aligned(N)`, aligns the function entry to `N` bytes, from
+    /// `#[repr(align(N))]` on the function.
+    Aligned(u64),
+    /// `format(printf, N, M)`, checks the arguments of the calls to a
+    /// `printf`-like function with `-Wformat`, where `N` is the 1-based index
+    /// of the format string parameter and `M` the index of the first variadic
+    /// argument.
+    Format { fmt_index: usize, first_arg: usize },
+    /// `visibility("hidden")`, keeps a symbol which is not exported from the
+    /// crate out of the dynamic symbol table of a shared object.
+    Hidden,
+    /// `nonnull(N)`, declares that the `N`th parameter, 1-based, is never a
+    /// null pointer, e.g. one lowered from a Rust reference.
+    NonNull(usize),
+}
+
+impl CFuncAttr {
+    /// Get the attribute name.
+    pub fn to_str(self) -> &'static str {
+        match self {
+            CFuncAttr::WarnUnusedResult => "warn_unused_result",
+            CFuncAttr::AlwaysInline => "always_inline",
+            CFuncAttr::NoInline => "noinline",
+            CFuncAttr::Cold => "cold",
+            CFuncAttr::Aligned(_) => "aligned",
+            CFuncAttr::Format { .. } => "format",
+            CFuncAttr::Hidden => "visibility",
+            CFuncAttr::NonNull(_) => "nonnull",
+        }
+    }
+
+    /// Get the attribute as printed, with its arguments if any, e.g. `aligned(16)`.
+    pub fn spelling(self) -> String {
+        match self {
+            CFuncAttr::Aligned(align) => format!("aligned({align})"),
+            CFuncAttr::Format { fmt_index, first_arg } => {
+                format!("format(printf, {fmt_index}, {first_arg})")
+            }
+            CFuncAttr::Hidden => "visibility(\"hidden\")".to_string(),
+            CFuncAttr::NonNull(index) => format!("nonnull({index})"),
+            _ => self.to_str().to_string(),
+        }
+    }
+
+    /// Whether the two attributes cannot be applied to the same function.
+    pub fn conflicts_with(self, other: CFuncAttr) -> bool {
+        matches!(
+            (self, other),
+            (CFuncAttr::AlwaysInline, CFuncAttr::NoInline)
+                | (CFuncAttr::NoInline, CFuncAttr::AlwaysInline)
+        ) || matches!((self, other), (CFuncAttr::Aligned(a), CFuncAttr::Aligned(b)) if a != b)
+    }
+}
+
 impl<'mx> ModuleCtx<'mx> {
     /// Create a new function definition.
     pub fn func(&self, func: CFuncKind<'mx>) -> &'mx CFuncKind<'mx> {
         self.arena().alloc(func)
     }
+
+    /// Declare the local variable of a phi node of type `ty` in `func`.
+    ///
+    /// A phi node merges the values flowing in from several predecessor blocks.
+    /// It is lowered to a local declared once, which each predecessor assigns
+    /// right before jumping to the merging block, see [`ModuleCtx::phi_jump`]:
+    ///
+    /// ```c
+    /// int32_t _1;
+    /// ...
+    /// bb1:
+    /// (_1 = 1);
+    /// goto bb3;
+    /// bb2:
+    /// (_1 = 2);
+    /// goto bb3;
+    /// bb3:
+    /// return _1;
+    /// ```
+    pub fn phi_local(self, func: &'mx CFuncKind<'mx>, ty: CTy<'mx>) -> CValue<'mx> {
+        let local = func.next_local_var();
+        func.push_stmt(self.decl_stmt(self.var(local, ty, None)));
+        local
+    }
+
+    /// Create the end of a predecessor block of phi nodes, assigning each phi
+    /// local its incoming value and then jumping to `target`.
+    ///
+    /// The assignments are sequential, so an incoming value must not read a phi
+    /// local assigned before it.
+    pub fn phi_jump(self, phis: &[(CValue<'mx>, CExpr<'mx>)], target: &'mx str) -> Vec<CStmt<'mx>> {
+        phis.iter()
+            .map(|&(local, value)| self.expr_stmt(self.binary(self.value(local), value, "=")))
+            .chain([self.goto(target)])
+            .collect()
+    }
+}
+
+/// The label of the cleanup block, see [`CFuncKind`].
+const CLEANUP_LABEL: &str = "__rust_cleanup";
+
+impl<'mx> ModuleCtx<'mx> {
+    /// Create a return from `func` through its cleanup block, storing the
+    /// return value and jumping to the block, e.g.
+    /// `(_1 = 1); goto __rust_cleanup;`.
+    ///
+    /// The local holding the return value is declared at the top of the body
+    /// the first time.
+    pub fn ret_via_cleanup(
+        self,
+        func: &'mx CFuncKind<'mx>,
+        expr: Option<CExpr<'mx>>,
+    ) -> Vec<CStmt<'mx>> {
+        let mut stmts = vec![];
+        if let Some(expr) = expr {
+            let local = func.cleanup_ret.get().unwrap_or_else(|| {
+                let local = func.next_local_var();
+                func.body.borrow_mut().insert(0, self.decl_stmt(self.var(local, func.ty, None)));
+                func.cleanup_ret.set(Some(local));
+                local
+            });
+            stmts.push(self.expr_stmt(self.binary(self.value(local), expr, "=")));
+        }
+        stmts.push(self.goto(CLEANUP_LABEL));
+        stmts
+    }
+
+    /// Push the cleanup block of `func` to the end of its body, followed by the
+    /// actual return.
+    pub fn push_cleanup_block(self, func: &'mx CFuncKind<'mx>) {
+        func.push_stmt(self.label(CLEANUP_LABEL));
+        for &stmt in func.cleanup.borrow().iter() {
+            func.push_stmt(stmt);
+        }
+        func.push_stmt(self.ret(func.cleanup_ret.get().map(|local| self.value(local))));
+    }
 }
 
 impl Print for CFunc<'_> {
@@ -66,24 +314,64 @@ impl Print for CFunc<'_> {
         ctx.ibox(0, |ctx| {
             print_signature(*self, ctx);
             ctx.softbreak(); // I don't know how to avoid a newline here
-            print_compound(&self.0.body.borrow(), ctx);
+
+            let unused_params = self.0.unused_params.borrow();
+            let discards = self
+                .0
+                .params
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| unused_params.contains(i))
+                .map(|(_, &(_, val))| Discard(val))
+                .collect::<Vec<_>>();
+            let body = self.0.body.borrow();
+            let items = discards
+                .iter()
+                .map(|discard| discard as &dyn Print)
+                .chain(body.iter().map(|stmt| stmt as &dyn Print));
+            print_block(items, ctx);
         })
     }
 }
 
+/// A discard of an unused parameter, e.g. `(void) _0;`.
+struct Discard<'mx>(CValue<'mx>);
+
+impl Print for Discard<'_> {
+    fn print_to(&self, ctx: &mut PrinterCtx) {
+        ctx.word("(void) ");
+        self.0.print_to(ctx);
+        ctx.word(";");
+    }
+}
+
 pub(crate) fn print_func_decl(func: CFunc, ctx: &mut PrinterCtx) {
     print_signature(func, ctx);
     ctx.word(";");
 }
 
+/// Print the attributes as `__attribute__((...)) `, if any.
+pub(crate) fn print_attrs(attrs: &[CFuncAttr], ctx: &mut PrinterCtx) {
+    if !attrs.is_empty() {
+        ctx.word("__attribute__((");
+        ctx.word(attrs.iter().map(|attr| attr.spelling()).collect::<Vec<_>>().join(", "));
+        ctx.word("))");
+        ctx.nbsp();
+    }
+}
+
 fn print_signature(func: CFunc, ctx: &mut PrinterCtx) {
     ctx.ibox(0, |ctx| {
-        print_declarator(func.0.ty, Some(CValue::Func(func.0.name)), ctx);
+        print_attrs(&func.0.attrs.borrow(), ctx);
+        if func.0.is_internal() {
+            ctx.word("static ");
+        }
+        print_declarator(func.0.ty, Some(&CValue::Func(func.0.name)), ctx);
 
         ctx.valign_delim(("(", ")"), |ctx| {
             ctx.seperated(",", &func.0.params, |ctx, (ty, name)| {
                 ctx.ibox(0, |ctx| {
-                    print_declarator(*ty, Some(*name), ctx);
+                    print_declarator(*ty, Some(name), ctx);
                 })
             })
         });
@@ -7,7 +7,12 @@
 //! with the `rustc_codegen_ssa` backend.
 #![feature(rustc_private)]
 
+use std::cell::RefCell;
 use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use rustc_data_structures::fx::FxHashSet;
 
 use crate::pretty::Print;
 
@@ -18,11 +23,14 @@ extern crate rustc_driver;
 extern crate rustc_type_ir;
 
 pub mod arena;
+pub mod builtin;
 pub mod decl;
 pub mod expr;
 pub mod func;
+pub mod lint;
 pub mod module;
 pub mod pretty;
+pub mod standard;
 pub mod stmt;
 pub mod ty;
 
@@ -45,11 +53,86 @@ impl<'mx> ModuleCtx<'mx> {
     pub fn alloc_str(&self, s: &str) -> &'mx str {
         self.arena().alloc_str(s)
     }
+
+    /// Print the module and pass it through `clang-format` for a canonical
+    /// layout.
+    ///
+    /// The binary is the one named by `RUSTC_CODEGEN_C_CLANG_FORMAT`, or else the
+    /// newest `clang-format` found in `PATH`. If none can be run, the output of
+    /// the internal pretty printer is returned unchanged.
+    pub fn to_formatted_string(&self) -> String {
+        match std::env::var("RUSTC_CODEGEN_C_CLANG_FORMAT") {
+            Ok(clang_format) => self.to_formatted_string_with(&[&clang_format]),
+            Err(_) => self.to_formatted_string_with(&[
+                "clang-format-18",
+                "clang-format-17",
+                "clang-format-16",
+                "clang-format",
+            ]),
+        }
+    }
+
+    /// Like [`Self::to_formatted_string`], but with the first of `candidates`
+    /// that can be run as the `clang-format` binary.
+    pub fn to_formatted_string_with(&self, candidates: &[&str]) -> String {
+        let source = self.to_string();
+        candidates
+            .iter()
+            .find_map(|clang_format| clang_format_with(clang_format, &source))
+            .unwrap_or(source)
+    }
+}
+
+/// Run `clang_format` on `source`, or `None` if it is missing or fails.
+fn clang_format_with(clang_format: &str, source: &str) -> Option<String> {
+    let mut child = Command::new(clang_format)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    // `clang-format` reads all of its input before writing, so this cannot deadlock
+    child.stdin.take()?.write_all(source.as_bytes()).ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+impl<'mx> ModuleCtx<'mx> {
+    /// Print the header declaring the functions of the module, see
+    /// [`module::Module::print_header`].
+    pub fn to_header_string(&self, guard: &str) -> String {
+        let mut printer = self.printer();
+        self.module().print_header(guard, &mut printer);
+        printer.finish()
+    }
+
+    /// Print the module to `out`, writing it chunk by chunk, e.g. function by
+    /// function, instead of building the whole output in memory first.
+    ///
+    /// The output is the same as that of the [`Display`] implementation.
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        self.module().print_chunks(|newlines, chunk| {
+            out.write_all("\n".repeat(newlines).as_bytes())?;
+            let mut printer = self.printer();
+            printer.cbox(0, chunk);
+            printer.finish_to(out)
+        })
+    }
+
+    /// A printer configured as set on the module.
+    fn printer(&self) -> pretty::PrinterCtx {
+        pretty::PrinterCtx::with_standard(self.module().standard())
+            .with_int_names(self.module().int_names.get())
+            .with_indent_style(self.module().indent_style.get())
+    }
 }
 
 impl<'mx> Display for ModuleCtx<'mx> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut printer = pretty::PrinterCtx::new();
+        let mut printer = self.printer();
         self.module().print_to(&mut printer);
         write!(f, "{}", printer.finish())
     }
@@ -60,10 +143,16 @@ pub struct ModuleArena<'mx> {
     pub arena: arena::Arena<'mx>,
     /// The module's AST node.
     pub module: module::Module<'mx>,
+    /// The interned complex types, see [`ModuleCtx::ty`].
+    types: RefCell<FxHashSet<&'mx ty::CTyKind<'mx>>>,
 }
 
 impl<'mx> ModuleArena<'mx> {
     pub fn new(helper: &'static str) -> Self {
-        Self { arena: arena::Arena::default(), module: module::Module::new(helper) }
+        Self {
+            arena: arena::Arena::default(),
+            module: module::Module::new(helper),
+            types: RefCell::new(FxHashSet::default()),
+        }
     }
 }
@@ -0,0 +1,197 @@
+//! This module defines lints over the built AST, catching lowering bugs which
+//! the C compiler would accept silently.
+
+use rustc_data_structures::fx::FxHashMap;
+
+use crate::decl::CDeclKind;
+use crate::expr::{CExpr, CExprKind, CValue};
+use crate::module::Module;
+use crate::pretty::{Print, PrinterCtx};
+use crate::stmt::{CStmt, CStmtKind};
+use crate::ty::{CIntTy, CTy, CUintTy};
+
+impl<'mx> Module<'mx> {
+    /// Find the assignments and initializers implicitly converting an integer
+    /// to a narrower type, returning a line for each, e.g.
+    /// ``foo: `(_1 = _0)` narrows uint32_t to uint8_t``.
+    ///
+    /// The lowering is expected to cast explicitly wherever a value may be
+    /// truncated, so such a conversion is likely a bug. The type of an
+    /// expression is only known for parameters, locals and casts, and other
+    /// expressions are not checked.
+    pub fn narrowing_conversions(&self) -> Vec<String> {
+        let mut lints = vec![];
+        for func in self.funcs.borrow().iter() {
+            let mut lint = NarrowingLint {
+                func: func.0.name,
+                locals: func.0.params.iter().map(|&(ty, name)| (name, ty)).collect(),
+                lints: &mut lints,
+            };
+            for stmt in func.0.body.borrow().iter() {
+                lint.visit_stmt(stmt);
+            }
+        }
+        lints
+    }
+}
+
+/// The state of [`Module::narrowing_conversions`] within a function.
+struct NarrowingLint<'a, 'mx> {
+    func: &'mx str,
+    /// The types of the parameters and of the locals declared so far.
+    locals: FxHashMap<CValue<'mx>, CTy<'mx>>,
+    lints: &'a mut Vec<String>,
+}
+
+impl<'mx> NarrowingLint<'_, 'mx> {
+    fn visit_stmt(&mut self, stmt: CStmt<'mx>) {
+        match stmt {
+            CStmtKind::Compound(stmts) => {
+                for stmt in stmts {
+                    self.visit_stmt(stmt);
+                }
+            }
+            CStmtKind::If { cond, then_br, else_br } => {
+                self.visit_expr(cond);
+                self.visit_stmt(then_br);
+                if let Some(else_br) = else_br {
+                    self.visit_stmt(else_br);
+                }
+            }
+            CStmtKind::While { cond, body } => {
+                self.visit_expr(cond);
+                self.visit_stmt(body);
+            }
+            CStmtKind::For { init, cond, step, body } => {
+                if let Some(init) = init {
+                    self.visit_stmt(init);
+                }
+                for expr in [cond, step].into_iter().flatten() {
+                    self.visit_expr(expr);
+                }
+                self.visit_stmt(body);
+            }
+            CStmtKind::Switch { scrutinee, cases, default } => {
+                self.visit_expr(scrutinee);
+                for (_, body) in cases {
+                    self.visit_stmt(body);
+                }
+                if let Some(default) = default {
+                    self.visit_stmt(default);
+                }
+            }
+            CStmtKind::Decl(CDeclKind::Var { name, ty, init, .. }) => {
+                self.locals.insert(*name, *ty);
+                if let Some(init) = init {
+                    self.check(*ty, init, &stmt);
+                    self.visit_expr(init);
+                }
+            }
+            CStmtKind::Return(Some(expr)) | CStmtKind::Expr(expr) => self.visit_expr(expr),
+            CStmtKind::Return(None)
+            | CStmtKind::Decl(_)
+            | CStmtKind::Label(_)
+            | CStmtKind::Goto(_)
+            | CStmtKind::Break
+            | CStmtKind::Continue
+            | CStmtKind::Comment(_)
+            | CStmtKind::Empty => {}
+        }
+    }
+
+    fn visit_expr(&mut self, expr: CExpr<'mx>) {
+        match expr {
+            CExprKind::Binary { lhs, rhs, op } => {
+                if *op == "=" {
+                    if let Some(ty) = self.type_of(lhs) {
+                        self.check(ty, rhs, &expr);
+                    }
+                }
+                self.visit_expr(lhs);
+                self.visit_expr(rhs);
+            }
+            CExprKind::Unary { expr, .. }
+            | CExprKind::Cast { expr, .. }
+            | CExprKind::Member { expr, .. }
+            | CExprKind::Designated { expr, .. } => self.visit_expr(expr),
+            CExprKind::Call { callee, args } => {
+                self.visit_expr(callee);
+                for arg in args {
+                    self.visit_expr(arg);
+                }
+            }
+            CExprKind::Index { base, index } => {
+                self.visit_expr(base);
+                self.visit_expr(index);
+            }
+            CExprKind::Ternary { cond, then, els } => {
+                for expr in [cond, then, els] {
+                    self.visit_expr(expr);
+                }
+            }
+            CExprKind::Gnu { expr, fallback } => {
+                self.visit_expr(expr);
+                if let Some(fallback) = fallback {
+                    self.visit_expr(fallback);
+                }
+            }
+            CExprKind::InitList(exprs)
+            | CExprKind::CompoundLiteral { items: exprs, .. }
+            | CExprKind::Comma(exprs) => {
+                for expr in exprs {
+                    self.visit_expr(expr);
+                }
+            }
+            // the operand of `sizeof` is not evaluated, and a generic selection
+            // only evaluates the chosen case, whose conversions are not lowered
+            CExprKind::Raw(_)
+            | CExprKind::Value(_)
+            | CExprKind::Str(_)
+            | CExprKind::SizeOf(_)
+            | CExprKind::SizeOfExpr(_)
+            | CExprKind::OffsetOf { .. }
+            | CExprKind::Generic { .. }
+            | CExprKind::Zeroed(_) => {}
+        }
+    }
+
+    /// The type of `expr`, if known.
+    fn type_of(&self, expr: CExpr<'mx>) -> Option<CTy<'mx>> {
+        match expr {
+            CExprKind::Value(value) => self.locals.get(value).copied(),
+            CExprKind::Cast { ty, .. } => Some(*ty),
+            _ => None,
+        }
+    }
+
+    /// Report `node` if storing `value` to a `target` implicitly narrows it.
+    fn check(&mut self, target: CTy<'mx>, value: CExpr<'mx>, node: &dyn Print) {
+        let Some(ty) = self.type_of(value) else { return };
+        let (Some(from), Some(to)) = (int_width(ty), int_width(target)) else { return };
+        if from > to {
+            let mut ctx = PrinterCtx::new();
+            node.print_to(&mut ctx);
+            self.lints.push(format!(
+                "{}: `{}` narrows {} to {}",
+                self.func,
+                ctx.finish(),
+                ty.to_str(),
+                target.to_str()
+            ));
+        }
+    }
+}
+
+/// The width in bits of a fixed width integer type.
+///
+/// Pointer-sized types have no fixed width, so they are never considered
+/// narrower or wider than another type.
+fn int_width(ty: CTy) -> Option<u32> {
+    match ty {
+        CTy::Int(CIntTy::I8) | CTy::UInt(CUintTy::U8) => Some(8),
+        CTy::Int(CIntTy::I16) | CTy::UInt(CUintTy::U16) => Some(16),
+        CTy::Int(CIntTy::I32) | CTy::UInt(CUintTy::U32) => Some(32),
+        CTy::Int(CIntTy::I64) | CTy::UInt(CUintTy::U64) => Some(64),
+        _ => None,
+    }
+}
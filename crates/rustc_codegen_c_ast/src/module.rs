@@ -1,22 +1,63 @@
 //! This module defines AST nodes for C modules.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::convert::Infallible;
+use std::hash::{Hash, Hasher};
 
-use crate::decl::CDecl;
-use crate::func::{print_func_decl, CFunc};
-use crate::pretty::{Print, PrinterCtx};
+use rustc_data_structures::fx::{FxHashMap, FxHashSet, FxHasher};
+
+use crate::decl::{CConstStyle, CDecl, CDeclKind, CStorageClass};
+use crate::expr::{print_str, CExpr, CExprKind, CValue};
+use crate::func::{print_func_decl, CFunc, CFuncAttr};
+use crate::pretty::{IndentStyle, Print, PrinterCtx};
+use crate::standard::CStandard;
+use crate::stmt::{CStmt, CStmtKind};
+use crate::ty::{print_declarator, CIntNames, CQualifier, CTy, CUintTy};
+use crate::ModuleCtx;
 
 /// C module definition.
 #[derive(Debug, Clone)]
 pub struct Module<'mx> {
     /// Includes. Only the file name is recorded, without the angle brackets.
     pub includes: RefCell<Vec<&'static str>>,
+    /// Includes of files next to the generated C, printed as `#include "..."`
+    /// after the other includes.
+    pub local_includes: RefCell<Vec<&'mx str>>,
+    /// Pragmas, printed after the includes. Only the text after `#pragma` is recorded.
+    pub pragmas: RefCell<Vec<&'static str>>,
+    /// Constructs the backend could not lower, printed as `#error` directives
+    /// after the pragmas, see [`Module::push_error`].
+    pub errors: RefCell<Vec<&'mx str>>,
     /// A piece of helper code to be included at the beginning of the file.
     pub helper: &'static str,
     /// Declarations.
     pub decls: RefCell<Vec<CDecl<'mx>>>,
     /// Function definitions.
     pub funcs: RefCell<Vec<CFunc<'mx>>>,
+    /// The C standard the module is generated for.
+    pub standard: Cell<CStandard>,
+    /// String literals promoted to globals, see [`ModuleCtx::interned_string`].
+    pub strings: RefCell<FxHashMap<&'mx [u8], CValue<'mx>>>,
+    /// Only string literals longer than this many bytes are promoted to globals.
+    pub string_dedup_threshold: Cell<usize>,
+    /// Constant lookup tables by element type and contents, see [`ModuleCtx::const_table`].
+    pub tables: RefCell<FxHashMap<(CTy<'mx>, Vec<CExpr<'mx>>), CValue<'mx>>>,
+    /// Struct wrappers of fixed length arrays by element type and length,
+    /// see [`ModuleCtx::array_wrapper`].
+    pub array_wrappers: RefCell<FxHashMap<(CTy<'mx>, usize), CTy<'mx>>>,
+    /// Structs for anonymous aggregates by field types, see [`ModuleCtx::tuple_struct`].
+    pub tuple_structs: RefCell<FxHashMap<Vec<CTy<'mx>>, CTy<'mx>>>,
+    /// How fixed width integer types are spelled.
+    pub int_names: Cell<CIntNames>,
+    /// How the indentation of the printed module is spelled.
+    pub indent_style: Cell<IndentStyle>,
+    /// Names declared with [`ModuleCtx::declare_extern`].
+    pub externs: RefCell<FxHashSet<&'mx str>>,
+    /// Whether to print functions so that callees come before their callers
+    /// where possible, instead of in insertion order.
+    pub topological_order: Cell<bool>,
 }
 
 impl<'mx> Module<'mx> {
@@ -24,17 +65,98 @@ impl<'mx> Module<'mx> {
     pub fn new(helper: &'static str) -> Self {
         Self {
             includes: RefCell::new(Vec::new()),
+            local_includes: RefCell::new(Vec::new()),
+            pragmas: RefCell::new(Vec::new()),
+            errors: RefCell::new(Vec::new()),
             helper,
             decls: RefCell::new(Vec::new()),
             funcs: RefCell::new(Vec::new()),
+            standard: Cell::new(CStandard::default()),
+            strings: RefCell::new(FxHashMap::default()),
+            string_dedup_threshold: Cell::new(0),
+            tables: RefCell::new(FxHashMap::default()),
+            array_wrappers: RefCell::new(FxHashMap::default()),
+            tuple_structs: RefCell::new(FxHashMap::default()),
+            int_names: Cell::new(CIntNames::default()),
+            indent_style: Cell::new(IndentStyle::default()),
+            externs: RefCell::new(FxHashSet::default()),
+            topological_order: Cell::new(false),
         }
     }
 
+    /// Get the C standard the module is generated for.
+    pub fn standard(&self) -> CStandard {
+        self.standard.get()
+    }
+
+    /// Set the C standard the module is generated for.
+    pub fn set_standard(&self, standard: CStandard) {
+        self.standard.set(standard);
+    }
+
     /// Push an include directive to the end of the includes list.
     pub fn push_include(&self, include: &'static str) {
         self.includes.borrow_mut().push(include);
     }
 
+    /// Push an include directive to the end of the includes list, unless it is
+    /// already included.
+    pub fn require_include(&self, include: &'static str) {
+        let mut includes = self.includes.borrow_mut();
+        if !includes.contains(&include) {
+            includes.push(include);
+        }
+    }
+
+    /// Push an include directive of a file next to the generated C, e.g. a
+    /// header shared with the other files of the crate.
+    pub fn push_local_include(&self, include: &'mx str) {
+        self.local_includes.borrow_mut().push(include);
+    }
+
+    /// Push a pragma directive to the end of the pragmas list.
+    ///
+    /// Example: `module.push_pragma("GCC diagnostic ignored \"-Wunused-parameter\"")`
+    pub fn push_pragma(&self, pragma: &'static str) {
+        self.pragmas.borrow_mut().push(pragma);
+    }
+
+    /// Record a construct which cannot be lowered, printed as
+    /// `#error "unsupported: ..."`, so that compiling the C fails with a
+    /// message pointing at it, rather than the backend panicking.
+    ///
+    /// Example: `module.push_error("inline assembly at src/main.rs:3:5")`
+    pub fn push_error(&self, message: &'mx str) {
+        self.errors.borrow_mut().push(message);
+    }
+
+    /// Set the length in bytes a string literal must exceed to be promoted to a
+    /// global, see [`ModuleCtx::interned_string`].
+    pub fn set_string_dedup_threshold(&self, threshold: usize) {
+        self.string_dedup_threshold.set(threshold);
+    }
+
+    /// Set how fixed width integer types are spelled.
+    ///
+    /// The Rust names are declared at the top of the module, where the alias
+    /// of `usize` needs `size_t`, so `<stddef.h>` is included for them.
+    pub fn set_int_names(&self, int_names: CIntNames) {
+        if int_names == CIntNames::Rust {
+            self.require_include("stddef.h");
+        }
+        self.int_names.set(int_names);
+    }
+
+    /// Set how the indentation of the printed module is spelled.
+    pub fn set_indent_style(&self, indent_style: IndentStyle) {
+        self.indent_style.set(indent_style);
+    }
+
+    /// Set whether to print functions so that callees come before their callers.
+    pub fn set_topological_order(&self, topological_order: bool) {
+        self.topological_order.set(topological_order);
+    }
+
     /// Push a declaration to the end of the declarations list.
     pub fn push_decl(&self, decl: CDecl<'mx>) {
         self.decls.borrow_mut().push(decl);
@@ -46,9 +168,552 @@ impl<'mx> Module<'mx> {
     }
 }
 
-impl Print for Module<'_> {
-    fn print_to(&self, ctx: &mut PrinterCtx) {
+impl<'mx> ModuleCtx<'mx> {
+    /// Create a string literal expression which is shared with identical
+    /// literals in the module.
+    ///
+    /// Literals longer than [`Module::string_dedup_threshold`] are emitted once
+    /// as a `static const char[]` global, e.g.
+    /// `static const char __rust_str_0[4] = "foo";`, and the returned expression
+    /// refers to that global. Shorter literals are cheaper to repeat and are
+    /// returned as-is.
+    pub fn interned_string(&self, s: impl AsRef<[u8]>) -> CExpr<'mx> {
+        let s = s.as_ref();
+        let module = self.module();
+        if s.len() <= module.string_dedup_threshold.get() {
+            return self.string(s);
+        }
+
+        if let Some(&global) = module.strings.borrow().get(s) {
+            return self.value(global);
+        }
+
+        let s: &'mx [u8] = self.arena().alloc_slice(s);
+        let name = self.alloc_str(&format!("__rust_str_{}", module.strings.borrow().len()));
+        let global = CValue::Global(name);
+        let ty = self.arr(self.qualified(CTy::Char, CQualifier::Const), s.len() + 1);
+        module.push_decl(self.var_with_storage(
+            CStorageClass::Static,
+            global,
+            ty,
+            Some(self.string(s)),
+        ));
+        module.strings.borrow_mut().insert(s, global);
+        self.value(global)
+    }
+
+    /// Get a constant lookup table with elements of type `ty`, e.g.
+    /// `static const int32_t __rust_table_0[3] = {1, 2, 3};`.
+    ///
+    /// The table is emitted once per module for the same element type and
+    /// contents, and the returned global refers to it.
+    pub fn const_table(&self, ty: CTy<'mx>, items: Vec<CExpr<'mx>>) -> CValue<'mx> {
+        let module = self.module();
+        let key = (ty, items);
+        if let Some(&global) = module.tables.borrow().get(&key) {
+            return global;
+        }
+
+        let (ty, items) = key;
+        let name = self.alloc_str(&format!("__rust_table_{}", module.tables.borrow().len()));
+        let global = CValue::Global(name);
+        let table_ty = self.arr(self.qualified(ty, CQualifier::Const), items.len());
+        module.push_decl(self.var_with_storage(
+            CStorageClass::Static,
+            global,
+            table_ty,
+            Some(self.init_list(items.clone())),
+        ));
+        module.tables.borrow_mut().insert((ty, items), global);
+        global
+    }
+
+    /// Get a struct wrapping a fixed length array, e.g.
+    /// `struct __rust_array_0 { int32_t data[4]; };`.
+    ///
+    /// C arrays cannot be passed, returned or assigned by value, while a struct
+    /// containing one can, so Rust arrays are lowered to such wrappers. The
+    /// struct is defined once per module for the same element type and length,
+    /// and the array is its `data` field.
+    pub fn array_wrapper(&self, ty: CTy<'mx>, len: usize) -> CTy<'mx> {
+        let module = self.module();
+        if let Some(&wrapper) = module.array_wrappers.borrow().get(&(ty, len)) {
+            return wrapper;
+        }
+
+        let tag = self.alloc_str(&format!("__rust_array_{}", module.array_wrappers.borrow().len()));
+        module.push_decl(self.struct_decl(tag, vec![(self.arr(ty, len), "data")]));
+        let wrapper = self.struct_ty(tag);
+        module.array_wrappers.borrow_mut().insert((ty, len), wrapper);
+        wrapper
+    }
+
+    /// Get a struct for an anonymous aggregate, e.g. a Rust tuple or closure,
+    /// with the given field types, named `_0`, `_1` and so on.
+    ///
+    /// Unlike [`ModuleCtx::array_wrapper`], the tag is derived from the spelling
+    /// of the field types rather than from the order in which the structs are
+    /// requested, so that the same aggregate gets the same name in every
+    /// compilation, e.g. for incremental builds.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if there are no fields, as an empty struct is not valid C and a
+    /// zero-sized aggregate must not be materialized, or if the tag collides
+    /// with the one of other field types.
+    pub fn tuple_struct(&self, fields: &[CTy<'mx>]) -> CTy<'mx> {
+        assert!(!fields.is_empty(), "a zero-sized aggregate has no C struct");
+        let module = self.module();
+        if let Some(&tuple) = module.tuple_structs.borrow().get(fields) {
+            return tuple;
+        }
+
+        let mut hasher = FxHasher::default();
+        for &ty in fields {
+            let mut printer = PrinterCtx::new();
+            print_declarator(ty, None, &mut printer);
+            printer.finish().hash(&mut hasher);
+        }
+        let tag = self.alloc_str(&format!("__rust_tuple_{:016x}", hasher.finish()));
+        let tuple = self.struct_ty(tag);
+        if let Some((other, _)) = module.tuple_structs.borrow().iter().find(|&(_, &ty)| ty == tuple)
+        {
+            panic!("the tag {tag} of the tuple struct of {fields:?} collides with {other:?}");
+        }
+        let named =
+            fields.iter().enumerate().map(|(i, &ty)| (ty, self.alloc_str(&format!("_{i}"))));
+        module.push_decl(self.struct_decl(tag, named.collect()));
+        module.tuple_structs.borrow_mut().insert(fields.to_vec(), tuple);
+        tuple
+    }
+}
+
+/// The allocation error handler provided by the runtime.
+const ALLOC_ERROR_HANDLER: &str = "__rust_alloc_error_handler";
+
+impl<'mx> ModuleCtx<'mx> {
+    /// Declare an external variable or function of type `ty`, e.g.
+    /// `extern void foo(size_t);`, unless it is already declared.
+    pub fn declare_extern(&self, name: &'mx str, ty: CTy<'mx>) -> CValue<'mx> {
+        self.declare_extern_with_attrs(name, ty, vec![])
+    }
+
+    /// Declare an external function of type `ty` with attributes, e.g.
+    /// `__attribute__((format(printf, 1, 2))) extern int32_t foo(const char *, ...);`,
+    /// unless it is already declared.
+    pub fn declare_extern_with_attrs(
+        &self,
+        name: &'mx str,
+        ty: CTy<'mx>,
+        attrs: Vec<CFuncAttr>,
+    ) -> CValue<'mx> {
+        let module = self.module();
+        let global = CValue::Global(name);
+        if module.externs.borrow_mut().insert(name) {
+            let storage = Some(CStorageClass::Extern);
+            let thread_local = false;
+            let decl =
+                CDeclKind::Var { name: global, ty, init: None, storage, thread_local, attrs };
+            module.push_decl(self.decl(decl));
+        }
+        global
+    }
+
+    /// Define a global variable for a Rust static.
+    ///
+    /// An exported static, e.g. a `#[no_mangle] static FOO`, keeps external
+    /// linkage, while any other is `static`-qualified and only visible within
+    /// the module. An exported static may still be `hidden`, so that it is not
+    /// visible outside of the linked object, like a hidden function.
+    pub fn define_static(
+        &self,
+        name: &'mx str,
+        ty: CTy<'mx>,
+        init: Option<CExpr<'mx>>,
+        exported: bool,
+        hidden: bool,
+    ) -> CValue<'mx> {
+        let global = CValue::Global(name);
+        let decl = if exported {
+            self.var(global, ty, init)
+        } else {
+            self.var_with_storage(CStorageClass::Static, global, ty, init)
+        };
+        let decl = if exported && hidden { self.with_attr(decl, CFuncAttr::Hidden) } else { decl };
+        self.module().push_decl(decl);
+        global
+    }
+
+    /// Define a named integer constant for a Rust `const`, e.g.
+    /// `enum { FOO = 42 };` or `#define FOO 42`, and get its name to refer to.
+    ///
+    /// Unlike a global variable, the constant needs no storage, and may be used
+    /// wherever a constant expression is required, e.g. as an array length.
+    pub fn define_const(&self, name: &'mx str, value: i128, style: CConstStyle) -> CValue<'mx> {
+        self.module().push_decl(self.const_decl(name, value, style));
+        CValue::Const(name)
+    }
+
+    /// Push an enum definition with a fixed underlying integer type to the
+    /// module, followed by a `_Static_assert` for each variant that its value
+    /// fits the type.
+    ///
+    /// Without C23 the enum falls back to a typedef and macros, which would
+    /// silently truncate an out-of-range discriminant.
+    pub fn push_enum(&self, tag: &'mx str, underlying: CTy<'mx>, variants: Vec<(&'mx str, i128)>) {
+        let module = self.module();
+        module.require_include("stdint.h");
+        let mut asserts = vec![];
+        for &(name, value) in &variants {
+            let (min, max) = match underlying {
+                CTy::Int(int) => (self.raw(int.min_value()), self.raw(int.max_value())),
+                CTy::UInt(uint) => (self.value(CValue::Scalar(0)), self.raw(uint.max_value())),
+                _ => panic!("the underlying type of enum {tag} is not an integer type"),
+            };
+            let cond = if value < 0 {
+                self.binary(self.value(CValue::Scalar(value)), min, ">=")
+            } else {
+                self.binary(self.value(CValue::Scalar(value)), max, "<=")
+            };
+            let msg = format!("{tag}::{name} does not fit {}", underlying.to_str());
+            asserts.push(self.static_assert(cond, self.alloc_str(&msg)));
+        }
+        module.push_decl(self.enum_decl(tag, Some(underlying), variants));
+        for assert in asserts {
+            module.push_decl(assert);
+        }
+    }
+
+    /// Get the type of the allocation error handler, `void (size_t, size_t)`,
+    /// taking the size and alignment of the failed allocation.
+    pub fn alloc_error_handler_ty(&self) -> CTy<'mx> {
+        let usize = CTy::UInt(CUintTy::Usize);
+        self.fn_ty(CTy::Void, vec![usize, usize])
+    }
+
+    /// Get a reference to the allocation error handler, declaring it in the
+    /// module the first time.
+    ///
+    /// The reference decays to a function pointer `void (*)(size_t, size_t)`.
+    pub fn alloc_error_handler(&self) -> CExpr<'mx> {
+        self.module().require_include("stddef.h");
+        self.value(self.declare_extern(ALLOC_ERROR_HANDLER, self.alloc_error_handler_ty()))
+    }
+}
+
+impl<'mx> Module<'mx> {
+    /// Get the functions in the order they are printed.
+    ///
+    /// With [`Module::topological_order`], a function is printed only after all
+    /// the functions it calls, and functions are otherwise kept in insertion
+    /// order. Functions on a call cycle cannot be ordered that way, so the first
+    /// inserted one of them is printed when no function is ready.
+    fn ordered_funcs(&self) -> Vec<CFunc<'mx>> {
+        let funcs = self.funcs.borrow();
+        if !self.topological_order.get() {
+            return funcs.clone();
+        }
+
+        let index: FxHashMap<&str, usize> =
+            funcs.iter().enumerate().map(|(i, func)| (func.0.name, i)).collect();
+        let mut callers = vec![Vec::new(); funcs.len()];
+        let mut pending = vec![0; funcs.len()];
+        for (caller, func) in funcs.iter().enumerate() {
+            let mut callees = FxHashSet::default();
+            for stmt in func.0.body.borrow().iter() {
+                collect_callees_stmt(stmt, &mut callees);
+            }
+            for callee in callees {
+                match index.get(callee) {
+                    Some(&callee) if callee != caller => {
+                        callers[callee].push(caller);
+                        pending[caller] += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut ready: BinaryHeap<_> =
+            (0..funcs.len()).filter(|&i| pending[i] == 0).map(Reverse).collect();
+        let mut printed = vec![false; funcs.len()];
+        let mut next_unprinted = 0;
+        let mut order = Vec::with_capacity(funcs.len());
+        while order.len() < funcs.len() {
+            let i = match ready.pop() {
+                Some(Reverse(i)) => i,
+                None => {
+                    // only cycles are left, fall back to insertion order
+                    while printed[next_unprinted] {
+                        next_unprinted += 1;
+                    }
+                    next_unprinted
+                }
+            };
+            if printed[i] {
+                continue;
+            }
+            printed[i] = true;
+            order.push(funcs[i]);
+            for &caller in &callers[i] {
+                pending[caller] -= 1;
+                if pending[caller] == 0 {
+                    ready.push(Reverse(caller));
+                }
+            }
+        }
+        order
+    }
+}
+
+/// Collect the names of the functions called directly in a statement.
+fn collect_callees_stmt<'mx>(stmt: CStmt<'mx>, callees: &mut FxHashSet<&'mx str>) {
+    match stmt {
+        CStmtKind::Compound(stmts) => {
+            for stmt in stmts {
+                collect_callees_stmt(stmt, callees);
+            }
+        }
+        CStmtKind::Return(expr) => {
+            if let Some(expr) = expr {
+                collect_callees_expr(expr, callees);
+            }
+        }
+        CStmtKind::Decl(decl) => match decl {
+            CDeclKind::Var { init, .. } => {
+                if let Some(init) = init {
+                    collect_callees_expr(init, callees);
+                }
+            }
+            CDeclKind::StaticAssert { .. }
+            | CDeclKind::Struct { .. }
+            | CDeclKind::Enum { .. }
+            | CDeclKind::Typedef { .. }
+            | CDeclKind::Const { .. } => {}
+        },
+        CStmtKind::Expr(expr) => collect_callees_expr(expr, callees),
+        CStmtKind::If { cond, then_br, else_br } => {
+            collect_callees_expr(cond, callees);
+            collect_callees_stmt(then_br, callees);
+            if let Some(else_br) = else_br {
+                collect_callees_stmt(else_br, callees);
+            }
+        }
+        CStmtKind::While { cond, body } => {
+            collect_callees_expr(cond, callees);
+            collect_callees_stmt(body, callees);
+        }
+        CStmtKind::For { init, cond, step, body } => {
+            if let Some(init) = init {
+                collect_callees_stmt(init, callees);
+            }
+            for expr in [cond, step].into_iter().flatten() {
+                collect_callees_expr(expr, callees);
+            }
+            collect_callees_stmt(body, callees);
+        }
+        CStmtKind::Switch { scrutinee, cases, default } => {
+            collect_callees_expr(scrutinee, callees);
+            for (_, body) in cases {
+                collect_callees_stmt(body, callees);
+            }
+            if let Some(default) = default {
+                collect_callees_stmt(default, callees);
+            }
+        }
+        CStmtKind::Label(_)
+        | CStmtKind::Goto(_)
+        | CStmtKind::Break
+        | CStmtKind::Continue
+        | CStmtKind::Comment(_)
+        | CStmtKind::Empty => {}
+    }
+}
+
+/// Collect the names of the functions called directly in an expression.
+fn collect_callees_expr<'mx>(expr: CExpr<'mx>, callees: &mut FxHashSet<&'mx str>) {
+    match expr {
+        CExprKind::Raw(_)
+        | CExprKind::Value(_)
+        | CExprKind::Str(_)
+        | CExprKind::SizeOf(_)
+        | CExprKind::OffsetOf { .. }
+        | CExprKind::Zeroed(_) => {}
+        CExprKind::Binary { lhs, rhs, .. } => {
+            collect_callees_expr(lhs, callees);
+            collect_callees_expr(rhs, callees);
+        }
+        CExprKind::Ternary { cond, then, els } => {
+            for expr in [cond, then, els] {
+                collect_callees_expr(expr, callees);
+            }
+        }
+        CExprKind::Gnu { expr, fallback } => {
+            collect_callees_expr(expr, callees);
+            if let Some(fallback) = fallback {
+                collect_callees_expr(fallback, callees);
+            }
+        }
+        CExprKind::Index { base, index } => {
+            collect_callees_expr(base, callees);
+            collect_callees_expr(index, callees);
+        }
+        CExprKind::Unary { expr, .. }
+        | CExprKind::SizeOfExpr(expr)
+        | CExprKind::Cast { expr, .. }
+        | CExprKind::Member { expr, .. }
+        | CExprKind::Designated { expr, .. } => collect_callees_expr(expr, callees),
+        CExprKind::Call { callee, args } => {
+            match callee {
+                CExprKind::Value(CValue::Func(name)) => {
+                    callees.insert(*name);
+                }
+                _ => collect_callees_expr(callee, callees),
+            }
+            for arg in args {
+                collect_callees_expr(arg, callees);
+            }
+        }
+        CExprKind::InitList(items)
+        | CExprKind::CompoundLiteral { items, .. }
+        | CExprKind::Comma(items) => {
+            for item in items {
+                collect_callees_expr(item, callees);
+            }
+        }
+        CExprKind::Generic { controlling, cases, default } => {
+            collect_callees_expr(controlling, callees);
+            for (_, expr) in cases {
+                collect_callees_expr(expr, callees);
+            }
+            if let Some(default) = default {
+                collect_callees_expr(default, callees);
+            }
+        }
+    }
+}
+
+impl<'mx> Module<'mx> {
+    /// Compare the module structurally with another one, returning a line for
+    /// each differing node, e.g. ``foo body[1]: `return 1;` != `return 2;` ``.
+    ///
+    /// Every field affecting the printed module is compared, including the
+    /// options and the interned strings and tables, which are only printed
+    /// through the declarations. Declarations and statements are compared
+    /// index by index, so this is meant for tests comparing modules which are
+    /// expected to be equal.
+    pub fn diff(&self, other: &Module<'mx>) -> Vec<String> {
+        let mut diffs = vec![];
+        let diffs_ref = &mut diffs;
+        diff_field("includes", &*self.includes.borrow(), &*other.includes.borrow(), diffs_ref);
+        let (local, other_local) = (self.local_includes.borrow(), other.local_includes.borrow());
+        diff_field("local includes", &*local, &*other_local, diffs_ref);
+        diff_field("pragmas", &*self.pragmas.borrow(), &*other.pragmas.borrow(), diffs_ref);
+        diff_field("errors", &*self.errors.borrow(), &*other.errors.borrow(), diffs_ref);
+        diff_field("helper", &self.helper, &other.helper, diffs_ref);
+        diff_field("standard", &self.standard.get(), &other.standard.get(), diffs_ref);
+        diff_field("int names", &self.int_names.get(), &other.int_names.get(), diffs_ref);
+        diff_field("indent style", &self.indent_style.get(), &other.indent_style.get(), diffs_ref);
+        let (order, other_order) = (self.topological_order.get(), other.topological_order.get());
+        diff_field("topological order", &order, &other_order, diffs_ref);
+        diff_field("externs", &*self.externs.borrow(), &*other.externs.borrow(), diffs_ref);
+        diff_field("strings", &*self.strings.borrow(), &*other.strings.borrow(), diffs_ref);
+        let (threshold, other_threshold) =
+            (self.string_dedup_threshold.get(), other.string_dedup_threshold.get());
+        diff_field("string dedup threshold", &threshold, &other_threshold, diffs_ref);
+        diff_field("tables", &*self.tables.borrow(), &*other.tables.borrow(), diffs_ref);
+        let (wrappers, other_wrappers) =
+            (self.array_wrappers.borrow(), other.array_wrappers.borrow());
+        diff_field("array wrappers", &*wrappers, &*other_wrappers, diffs_ref);
+        let (tuples, other_tuples) = (self.tuple_structs.borrow(), other.tuple_structs.borrow());
+        diff_field("tuple structs", &*tuples, &*other_tuples, diffs_ref);
+        diff_nodes("decls", &self.decls.borrow(), &other.decls.borrow(), &mut diffs);
+
+        let (funcs, other_funcs) = (self.funcs.borrow(), other.funcs.borrow());
+        for i in 0..funcs.len().max(other_funcs.len()) {
+            match (funcs.get(i), other_funcs.get(i)) {
+                (Some(func), Some(other)) if func.0.name == other.0.name => {
+                    func.0.diff(other.0, &mut diffs);
+                }
+                (func, other) => {
+                    let name =
+                        |func: Option<&CFunc<'mx>>| func.map_or("nothing", |func| func.0.name);
+                    diffs.push(format!("funcs[{i}]: {} != {}", name(func), name(other)));
+                }
+            }
+        }
+        diffs
+    }
+}
+
+/// Push a line to `diffs` if the two values of a field differ.
+pub(crate) fn diff_field<T: PartialEq + std::fmt::Debug>(
+    what: &str,
+    value: &T,
+    other: &T,
+    diffs: &mut Vec<String>,
+) {
+    if value != other {
+        diffs.push(format!("{what}: {value:?} != {other:?}"));
+    }
+}
+
+/// Push a line to `diffs` for each index where the two lists of nodes differ.
+pub(crate) fn diff_nodes<T: Print + PartialEq>(
+    what: &str,
+    nodes: &[T],
+    others: &[T],
+    diffs: &mut Vec<String>,
+) {
+    let describe = |node: Option<&T>| match node {
+        Some(node) => {
+            let mut ctx = PrinterCtx::new();
+            node.print_to(&mut ctx);
+            format!("`{}`", ctx.finish())
+        }
+        None => "nothing".to_string(),
+    };
+    for i in 0..nodes.len().max(others.len()) {
+        let (node, other) = (nodes.get(i), others.get(i));
+        if node != other {
+            diffs.push(format!("{what}[{i}]: {} != {}", describe(node), describe(other)));
+        }
+    }
+}
+
+impl<'mx> PartialEq for Module<'mx> {
+    fn eq(&self, other: &Self) -> bool {
+        self.diff(other).is_empty()
+    }
+}
+
+impl Module<'_> {
+    /// Print a header declaring the functions of the module, wrapped in an
+    /// include guard named `guard`, for the other files of a crate split
+    /// into multiple C files.
+    ///
+    /// Internal functions are omitted, as they are defined in each file using
+    /// them. Only the includes are printed besides the prototypes, the aliases
+    /// of the integer types are declared by the including file before its
+    /// local includes.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if the module has declarations, e.g. a struct used by a
+    /// signature, as they would be defined again by the files including the
+    /// header.
+    pub fn print_header(&self, guard: &str, ctx: &mut PrinterCtx) {
+        assert!(
+            self.decls.borrow().is_empty(),
+            "the declarations of a module cannot be shared through a header"
+        );
         ctx.cbox(0, |ctx| {
+            ctx.word(format!("#ifndef {guard}"));
+            ctx.hardbreak();
+            ctx.word(format!("#define {guard}"));
+            ctx.hardbreak();
+            ctx.hardbreak();
+
             for &include in self.includes.borrow().iter() {
                 ctx.word("#include <");
                 ctx.word(include);
@@ -56,28 +721,109 @@ impl Print for Module<'_> {
                 ctx.hardbreak();
             }
 
+            for &func in self.funcs.borrow().iter().filter(|func| !func.0.is_internal()) {
+                ctx.hardbreak();
+                print_func_decl(func, ctx);
+            }
+
+            ctx.hardbreak();
             ctx.hardbreak();
+            ctx.word(format!("#endif /* {guard} */"));
+            ctx.hardbreak();
+        });
+    }
+}
 
-            ctx.word(self.helper);
+impl Module<'_> {
+    /// Print the module as a sequence of top-level chunks: the includes and
+    /// the helper code, then each declaration, prototype and definition.
+    ///
+    /// `emit` is called for each chunk with the number of newlines before it
+    /// and a closure printing it. Each chunk starts on a line of its own and is
+    /// laid out independently of the others, so that they can also be printed
+    /// by separate printers, see [`ModuleCtx::write_to`].
+    pub(crate) fn print_chunks<E>(
+        &self,
+        mut emit: impl FnMut(usize, &dyn Fn(&mut PrinterCtx)) -> Result<(), E>,
+    ) -> Result<(), E> {
+        emit(0, &|ctx| self.print_preamble(ctx))?;
 
-            for &decl in self.decls.borrow().iter() {
-                ctx.hardbreak();
-                ctx.hardbreak();
-                decl.print_to(ctx);
-            }
+        for &decl in self.decls.borrow().iter() {
+            emit(2, &|ctx| decl.print_to(ctx))?;
+        }
 
-            for &func in self.funcs.borrow().iter() {
-                ctx.hardbreak();
-                print_func_decl(func, ctx);
-            }
+        let funcs = self.ordered_funcs();
+        for &func in &funcs {
+            emit(1, &|ctx| print_func_decl(func, ctx))?;
+        }
 
-            for &func in self.funcs.borrow().iter() {
-                ctx.hardbreak();
-                ctx.hardbreak();
-                func.print_to(ctx);
-            }
+        for &func in &funcs {
+            emit(2, &|ctx| func.print_to(ctx))?;
+        }
+
+        emit(1, &|_| {})
+    }
 
+    /// Print the directives, the integer type aliases and the helper code.
+    fn print_preamble(&self, ctx: &mut PrinterCtx) {
+        for &include in self.includes.borrow().iter() {
+            ctx.word("#include <");
+            ctx.word(include);
+            ctx.word(">");
             ctx.hardbreak();
+        }
+
+        // the local headers may spell the integer types with their aliases
+        print_int_names(ctx);
+
+        for &include in self.local_includes.borrow().iter() {
+            ctx.word("#include \"");
+            ctx.word(include.to_string());
+            ctx.word("\"");
+            ctx.hardbreak();
+        }
+
+        for &pragma in self.pragmas.borrow().iter() {
+            ctx.word("#pragma ");
+            ctx.word(pragma);
+            ctx.hardbreak();
+        }
+
+        for &error in self.errors.borrow().iter() {
+            ctx.word("#error ");
+            print_str(format!("unsupported: {error}").as_bytes(), ctx);
+            ctx.hardbreak();
+        }
+
+        ctx.hardbreak();
+
+        ctx.word(self.helper);
+    }
+}
+
+/// Print the aliases of the integer types, if they are spelled with the Rust
+/// names, see [`CIntNames::Rust`].
+fn print_int_names(ctx: &mut PrinterCtx) {
+    if ctx.int_names() == CIntNames::Rust {
+        for ty in CIntNames::INT_TYS {
+            let (c, rust) = (ty.to_str(), ty.to_str_with(CIntNames::Rust));
+            ctx.word(format!("typedef {c} {rust};"));
+            ctx.hardbreak();
+        }
+    }
+}
+
+impl Print for Module<'_> {
+    fn print_to(&self, ctx: &mut PrinterCtx) {
+        ctx.cbox(0, |ctx| {
+            let printed = self.print_chunks(|newlines, chunk| {
+                for _ in 0..newlines {
+                    ctx.hardbreak();
+                }
+                chunk(ctx);
+                Ok::<_, Infallible>(())
+            });
+            printed.unwrap_or_else(|never| match never {});
         });
     }
 }
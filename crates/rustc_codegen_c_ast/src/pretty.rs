@@ -1,17 +1,45 @@
 //! Pretty printing support for C AST nodes.
 
 use std::borrow::Cow;
+use std::io::{self, Write};
 
 use rustc_ast_pretty::pp;
 
+use crate::standard::CStandard;
+use crate::stmt::CStmtKind;
+use crate::ty::CIntNames;
+
 /// Default indentation size.
 pub const INDENT: isize = 2;
 
+/// The blank space of a break which never fits, as for [`pp::Printer::hardbreak`].
+const SIZE_INFINITY: usize = 0xffff;
+
+/// How the indentation of the output is spelled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IndentStyle {
+    /// [`INDENT`] spaces per level.
+    #[default]
+    Spaces,
+    /// A tab per level of braces, while the visual alignment beyond it, e.g.
+    /// of the wrapped parameters of a function, is still spelled with spaces,
+    /// so that the output looks the same at any tab width.
+    Tabs,
+}
+
 /// Pretty printer, see [`rustc_ast_pretty::pp::Printer`] for details.
 pub struct PrinterCtx {
     pp: pp::Printer,
+    standard: CStandard,
+    int_names: CIntNames,
+    indent_style: IndentStyle,
+    stmt_hook: Option<Box<StmtHook>>,
 }
 
+/// A hook called before each statement is printed, returning the text to
+/// insert on a line of its own before it, if any.
+pub type StmtHook = dyn Fn(&CStmtKind) -> Option<String>;
+
 impl Default for PrinterCtx {
     fn default() -> Self {
         Self::new()
@@ -20,11 +48,73 @@ impl Default for PrinterCtx {
 
 impl PrinterCtx {
     pub fn new() -> Self {
-        Self { pp: pp::Printer::new() }
+        Self::with_standard(CStandard::default())
+    }
+
+    /// Make a new printer targeting the given C standard.
+    pub fn with_standard(standard: CStandard) -> Self {
+        Self {
+            pp: pp::Printer::new(),
+            standard,
+            int_names: CIntNames::default(),
+            indent_style: IndentStyle::default(),
+            stmt_hook: None,
+        }
+    }
+
+    /// Call `hook` before each statement is printed, e.g. to instrument the
+    /// generated code with a counter increment before each statement.
+    pub fn with_stmt_hook(mut self, hook: impl Fn(&CStmtKind) -> Option<String> + 'static) -> Self {
+        self.stmt_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Spell fixed width integer types according to `int_names`.
+    pub fn with_int_names(mut self, int_names: CIntNames) -> Self {
+        self.int_names = int_names;
+        self
+    }
+
+    /// Spell the indentation according to `indent_style`.
+    pub fn with_indent_style(mut self, indent_style: IndentStyle) -> Self {
+        self.indent_style = indent_style;
+        self
+    }
+
+    /// The C standard the output should conform to.
+    pub fn standard(&self) -> CStandard {
+        self.standard
+    }
+
+    /// How fixed width integer types are spelled.
+    pub fn int_names(&self) -> CIntNames {
+        self.int_names
+    }
+
+    /// Print the text inserted by the statement hook before `stmt`, if any.
+    pub(crate) fn stmt_prologue(&mut self, stmt: &CStmtKind) {
+        if let Some(text) = self.stmt_hook.as_ref().and_then(|hook| hook(stmt)) {
+            self.word(text);
+            // the break before a label is dedented, and so is the one after its text
+            self.hardbreak_offset(if stmt.is_dedented() { -INDENT } else { 0 });
+        }
+    }
+
+    /// Finish printing and write the output to `out`, see [`PrinterCtx::finish`].
+    ///
+    /// The underlying printer lays out a whole document before writing any of
+    /// it, so a large output is streamed by printing it in chunks, each with a
+    /// printer of its own, see [`ModuleCtx::write_to`](crate::ModuleCtx::write_to).
+    pub fn finish_to(self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(self.finish().as_bytes())
     }
 
     pub fn finish(self) -> String {
-        self.pp.eof()
+        let output = self.pp.eof();
+        match self.indent_style {
+            IndentStyle::Spaces => output,
+            IndentStyle::Tabs => smart_tabs(&output),
+        }
     }
 
     pub(crate) fn seperated<T>(
@@ -105,13 +195,15 @@ impl PrinterCtx {
         self.pp.end();
     }
 
+    /// Visually aligned box enclosed in delimiters, the continuation lines
+    /// being aligned after the opening delimiter
     pub(crate) fn valign_delim(
         &mut self,
         delim: (&'static str, &'static str),
         op: impl FnOnce(&mut Self),
     ) {
+        self.word(delim.0);
         self.valign(|this| {
-            this.word(delim.0);
             op(this);
             this.word(delim.1);
         });
@@ -127,6 +219,17 @@ impl PrinterCtx {
         self.pp.hardbreak();
     }
 
+    /// Hard break, with the next line indented by `offset` relative to the box
+    pub(crate) fn hardbreak_offset(&mut self, offset: isize) {
+        self.pp.break_offset(SIZE_INFINITY, offset);
+    }
+
+    /// Break of `n` spaces if fits, otherwise newline indented by `offset`
+    /// relative to the box
+    pub(crate) fn break_offset(&mut self, n: usize, offset: isize) {
+        self.pp.break_offset(n, offset);
+    }
+
     /// Zero break: nothing if fits, otherwise newline
     pub(crate) fn zerobreak(&mut self) {
         self.pp.zerobreak();
@@ -143,7 +246,65 @@ impl PrinterCtx {
     }
 }
 
+/// Replace the structural indentation of `text` with tabs.
+///
+/// The underlying printer only indents with spaces, whether for a nested block
+/// or for visual alignment, so the structural part is recovered from the
+/// nesting of braces: each level of braces open at the start of a line accounts
+/// for [`INDENT`] of its leading spaces, and the rest is alignment.
+fn smart_tabs(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut depth = 0usize;
+    for line in text.split_inclusive('\n') {
+        let code = line.trim_start_matches(' ');
+        let line_depth = if code.starts_with('}') { depth.saturating_sub(1) } else { depth };
+        let spaces = line.len() - code.len();
+        let tabs = line_depth.min(spaces / INDENT as usize);
+        output.extend(std::iter::repeat('\t').take(tabs));
+        output.extend(std::iter::repeat(' ').take(spaces - tabs * INDENT as usize));
+        output.push_str(code);
+
+        // braces in string and character literals and in comments do not nest
+        let mut chars = code.chars();
+        let mut quote = None;
+        while let Some(c) = chars.next() {
+            match (quote, c) {
+                (Some(_), '\\') => {
+                    chars.next();
+                }
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '/') if chars.clone().next() == Some('/') => break,
+                (None, '{') => depth += 1,
+                (None, '}') => depth = depth.saturating_sub(1),
+                (None, _) => {}
+            }
+        }
+    }
+    output
+}
+
 /// Trait for a type that can be pretty printed.
 pub trait Print {
     fn print_to(&self, ctx: &mut PrinterCtx);
+
+    /// Whether the item is printed dedented by [`INDENT`] within a block, as
+    /// a label is.
+    fn is_dedented(&self) -> bool {
+        false
+    }
+
+    /// Whether the item extends to the end of its line, as a line comment
+    /// does, so that whatever follows it must start on a new line.
+    fn ends_line(&self) -> bool {
+        false
+    }
+}
+
+/// Identifiers, e.g. struct field names, are printed as-is.
+impl Print for &str {
+    fn print_to(&self, ctx: &mut PrinterCtx) {
+        ctx.word(self.to_string());
+    }
 }
@@ -0,0 +1,53 @@
+//! This module defines the C language standards that the generated code can target.
+
+/// C language standard.
+///
+/// Some constructs are only available in newer standards or as GNU extensions.
+/// Nodes consult the standard when printing, so that they can pick a portable
+/// spelling, or fail loudly instead of emitting code the C compiler rejects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CStandard {
+    /// ISO C99.
+    C99,
+    /// ISO C11.
+    #[default]
+    C11,
+    /// C11 with GNU extensions.
+    Gnu11,
+    /// ISO C23.
+    C23,
+}
+
+impl CStandard {
+    /// Whether C11 features, e.g. `_Generic` and `_Static_assert`, are available.
+    pub fn has_c11(self) -> bool {
+        self >= CStandard::C11
+    }
+
+    /// Whether variable length arrays are available.
+    ///
+    /// They are mandatory in C99 but only an optional feature since C11.
+    pub fn has_vla(self) -> bool {
+        matches!(self, CStandard::C99 | CStandard::Gnu11)
+    }
+
+    /// Whether C23 features, e.g. enums with a fixed underlying type, are available.
+    pub fn has_c23(self) -> bool {
+        matches!(self, CStandard::C23)
+    }
+
+    /// Whether GNU extensions, e.g. statement expressions and `__int128`, are available.
+    pub fn has_gnu(self) -> bool {
+        matches!(self, CStandard::Gnu11)
+    }
+
+    /// Get the name of the standard, as accepted by `-std=`.
+    pub fn to_str(self) -> &'static str {
+        match self {
+            CStandard::C99 => "c99",
+            CStandard::C11 => "c11",
+            CStandard::Gnu11 => "gnu11",
+            CStandard::C23 => "c23",
+        }
+    }
+}
@@ -1,15 +1,16 @@
 //! This module defines the AST nodes for C statements.
 
-use crate::decl::CDecl;
-use crate::expr::CExpr;
+use crate::decl::{CDecl, CStorageClass};
+use crate::expr::{CExpr, CValue};
 use crate::pretty::{Print, PrinterCtx, INDENT};
+use crate::ty::CTy;
 use crate::ModuleCtx;
 
 /// C statement.
 pub type CStmt<'mx> = &'mx CStmtKind<'mx>;
 
 /// C statement.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CStmtKind<'mx> {
     /// Compound statement, which is a sequence of statements enclosed in braces.
     Compound(Vec<CStmt<'mx>>),
@@ -19,6 +20,60 @@ pub enum CStmtKind<'mx> {
     Decl(CDecl<'mx>),
     /// Expression statement, e.g. `foo(x + 1);`.
     Expr(CExpr<'mx>),
+    /// If statement, e.g. `if (x) { foo(); } else { bar(); }`.
+    ///
+    /// The branches are always printed in braces, except for an else branch
+    /// which is itself an if statement, printed as `else if`.
+    If { cond: CExpr<'mx>, then_br: CStmt<'mx>, else_br: Option<CStmt<'mx>> },
+    /// While loop, e.g. `while (x) { foo(); }`.
+    ///
+    /// A body which is not a compound statement is printed on its own
+    /// indented line, and an empty compound body as `{}`.
+    While { cond: CExpr<'mx>, body: CStmt<'mx> },
+    /// For loop, e.g. `for (int32_t _1 = 0; (_1 < _0); (_1 = (_1 + 1))) { foo(); }`.
+    ///
+    /// The init clause is a declaration or an expression statement, and the
+    /// body is printed as the body of a [`CStmtKind::While`].
+    For {
+        init: Option<CStmt<'mx>>,
+        cond: Option<CExpr<'mx>>,
+        step: Option<CExpr<'mx>>,
+        body: CStmt<'mx>,
+    },
+    /// Switch statement, e.g. `switch (x) { case 1: foo(); break; default: bar(); break; }`.
+    ///
+    /// The labels are printed at the indentation of the switch, and the body of
+    /// each case on the following lines, indented, without the braces of a
+    /// compound body. Fallthrough is not expressible: each body is assumed to
+    /// end with a jump of its own, e.g. a `break`.
+    Switch { scrutinee: CExpr<'mx>, cases: Vec<(i128, CStmt<'mx>)>, default: Option<CStmt<'mx>> },
+    /// Labeled statement, e.g. `foo:`, labeling the statement that follows.
+    Label(&'mx str),
+    /// Goto statement, e.g. `goto foo;`.
+    Goto(&'mx str),
+    /// Break statement `break;`, exiting the innermost loop. A labeled
+    /// `break` is lowered to a goto, see [`CLoopLabel`].
+    Break,
+    /// Continue statement `continue;`, starting the next iteration of the
+    /// innermost loop. A labeled `continue` is lowered to a goto, see [`CLoopLabel`].
+    Continue,
+    /// Line comment, e.g. `// bb2[3]`, annotating the statement that follows.
+    Comment(&'mx str),
+    /// Empty statement `;`, e.g. for a label at the end of a block, which must
+    /// be followed by a statement.
+    Empty,
+}
+
+/// The label of a Rust loop, e.g. `'outer`.
+///
+/// C has no labeled `break` and `continue`, so they are lowered to `goto`s: a
+/// labeled `break` jumps to a label right after the loop, see
+/// [`ModuleCtx::break_target`], and a labeled `continue` jumps to a label at the
+/// end of the loop body, see [`ModuleCtx::continue_target`].
+#[derive(Clone, Copy, Debug)]
+pub struct CLoopLabel<'mx> {
+    break_label: &'mx str,
+    continue_label: &'mx str,
 }
 
 impl<'mx> ModuleCtx<'mx> {
@@ -32,24 +87,194 @@ impl<'mx> ModuleCtx<'mx> {
         self.stmt(CStmtKind::Compound(stmts))
     }
 
+    /// Create a compound statement from the statements of an iterator.
+    pub fn seq(self, stmts: impl IntoIterator<Item = CStmt<'mx>>) -> CStmt<'mx> {
+        self.compound(stmts.into_iter().collect())
+    }
+
+    /// Create an if statement, with an optional else branch.
+    pub fn if_stmt(
+        self,
+        cond: CExpr<'mx>,
+        then_br: CStmt<'mx>,
+        else_br: Option<CStmt<'mx>>,
+    ) -> CStmt<'mx> {
+        self.stmt(CStmtKind::If { cond, then_br, else_br })
+    }
+
+    /// Create an if statement whose branches are built by closures pushing
+    /// statements to a fresh compound statement each.
+    ///
+    /// The else branch is omitted if `else_fn` pushes no statement.
+    pub fn cond(
+        self,
+        cond: CExpr<'mx>,
+        then_fn: impl FnOnce(&mut Vec<CStmt<'mx>>),
+        else_fn: impl FnOnce(&mut Vec<CStmt<'mx>>),
+    ) -> CStmt<'mx> {
+        let mut then_stmts = vec![];
+        then_fn(&mut then_stmts);
+        let mut else_stmts = vec![];
+        else_fn(&mut else_stmts);
+        let else_br = (!else_stmts.is_empty()).then(|| self.compound(else_stmts));
+        self.if_stmt(cond, self.compound(then_stmts), else_br)
+    }
+
+    /// Create a while loop.
+    pub fn while_stmt(self, cond: CExpr<'mx>, body: CStmt<'mx>) -> CStmt<'mx> {
+        self.stmt(CStmtKind::While { cond, body })
+    }
+
+    /// Create a for loop, which is `for (;;)` if all the clauses are `None`.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if `init` is neither a declaration nor an expression statement.
+    pub fn for_stmt(
+        self,
+        init: Option<CStmt<'mx>>,
+        cond: Option<CExpr<'mx>>,
+        step: Option<CExpr<'mx>>,
+        body: CStmt<'mx>,
+    ) -> CStmt<'mx> {
+        assert!(
+            matches!(init, None | Some(CStmtKind::Decl(_) | CStmtKind::Expr(_))),
+            "the init clause of a for loop must be a declaration or an expression"
+        );
+        self.stmt(CStmtKind::For { init, cond, step, body })
+    }
+
+    /// Create a switch statement, where each case body is expected to end with
+    /// a jump, e.g. [`ModuleCtx::break_stmt`].
+    pub fn switch(
+        self,
+        scrutinee: CExpr<'mx>,
+        cases: Vec<(i128, CStmt<'mx>)>,
+        default: Option<CStmt<'mx>>,
+    ) -> CStmt<'mx> {
+        self.stmt(CStmtKind::Switch { scrutinee, cases, default })
+    }
+
     /// Create a return statement.
     pub fn ret(self, expr: Option<CExpr<'mx>>) -> CStmt<'mx> {
         self.stmt(CStmtKind::Return(expr))
     }
 
+    /// Create a return statement of a function returning `ret_ty`, where `expr`
+    /// is of type `ty`.
+    ///
+    /// `expr` is cast to `ret_ty` explicitly if the types differ, instead of
+    /// relying on the implicit conversion.
+    pub fn ret_as(self, ret_ty: CTy<'mx>, ty: CTy<'mx>, expr: CExpr<'mx>) -> CStmt<'mx> {
+        let expr = if ty == ret_ty { expr } else { self.cast(ret_ty, expr) };
+        self.ret(Some(expr))
+    }
+
+    /// Create a line comment.
+    ///
+    /// The text must not contain newlines.
+    pub fn comment(self, text: &'mx str) -> CStmt<'mx> {
+        debug_assert!(!text.contains('\n'), "comment `{text}` spans multiple lines");
+        self.stmt(CStmtKind::Comment(text))
+    }
+
+    /// Create an empty statement.
+    pub fn empty(self) -> CStmt<'mx> {
+        self.stmt(CStmtKind::Empty)
+    }
+
     /// Create a declaration statement.
     pub fn decl_stmt(self, decl: CDecl<'mx>) -> CStmt<'mx> {
         self.stmt(CStmtKind::Decl(decl))
     }
 
+    /// Create a declaration statement of a function-local `static` variable,
+    /// e.g. `static int32_t _1 = 0;`, which is initialized only once.
+    pub fn local_static(self, name: CValue<'mx>, ty: CTy<'mx>, init: CExpr<'mx>) -> CStmt<'mx> {
+        assert!(
+            init.is_constant(),
+            "the initializer of a local static must be a constant expression"
+        );
+        self.decl_stmt(self.var_with_storage(CStorageClass::Static, name, ty, Some(init)))
+    }
+
     /// Create an expression statement.
     pub fn expr_stmt(self, expr: CExpr<'mx>) -> CStmt<'mx> {
         self.stmt(CStmtKind::Expr(expr))
     }
+
+    /// Create a store of `value` as a `ty` to `ptr`, e.g. `(*(int32_t *) _0 = _1);`,
+    /// see [`ModuleCtx::load`].
+    pub fn store(self, ptr: CExpr<'mx>, value: CExpr<'mx>, ty: CTy<'mx>) -> CStmt<'mx> {
+        self.expr_stmt(self.binary(self.load(ptr, ty), value, "="))
+    }
+
+    /// Create a labeled statement.
+    pub fn label(self, name: &'mx str) -> CStmt<'mx> {
+        self.stmt(CStmtKind::Label(name))
+    }
+
+    /// Create a goto statement.
+    pub fn goto(self, name: &'mx str) -> CStmt<'mx> {
+        self.stmt(CStmtKind::Goto(name))
+    }
+
+    /// Create a break statement, exiting the innermost loop.
+    pub fn break_stmt(self) -> CStmt<'mx> {
+        self.stmt(CStmtKind::Break)
+    }
+
+    /// Create a continue statement, starting the next iteration of the innermost loop.
+    pub fn continue_stmt(self) -> CStmt<'mx> {
+        self.stmt(CStmtKind::Continue)
+    }
+
+    /// Create the label of a loop.
+    ///
+    /// Labels are scoped to the whole function in C, so `name` should be unique
+    /// within the function.
+    pub fn loop_label(self, name: &str) -> CLoopLabel<'mx> {
+        CLoopLabel {
+            break_label: self.alloc_str(&format!("{name}_break")),
+            continue_label: self.alloc_str(&format!("{name}_continue")),
+        }
+    }
+
+    /// Create a labeled `break`, jumping to the [`ModuleCtx::break_target`] of the loop.
+    pub fn break_to(self, label: CLoopLabel<'mx>) -> CStmt<'mx> {
+        self.goto(label.break_label)
+    }
+
+    /// Create a labeled `continue`, jumping to the [`ModuleCtx::continue_target`] of the loop.
+    pub fn continue_to(self, label: CLoopLabel<'mx>) -> CStmt<'mx> {
+        self.goto(label.continue_label)
+    }
+
+    /// Create the label to put right after the loop, targeted by labeled `break`s.
+    pub fn break_target(self, label: CLoopLabel<'mx>) -> CStmt<'mx> {
+        self.label(label.break_label)
+    }
+
+    /// Create the label to put at the end of the loop body, targeted by labeled
+    /// `continue`s.
+    pub fn continue_target(self, label: CLoopLabel<'mx>) -> CStmt<'mx> {
+        self.label(label.continue_label)
+    }
 }
 
 impl Print for CStmt<'_> {
+    /// Labels are dedented by convention, so that they stand out from the
+    /// statements of their block.
+    fn is_dedented(&self) -> bool {
+        matches!(self, CStmtKind::Label(_))
+    }
+
+    fn ends_line(&self) -> bool {
+        matches!(self, CStmtKind::Comment(_))
+    }
+
     fn print_to(&self, ctx: &mut PrinterCtx) {
+        ctx.stmt_prologue(self);
         match self {
             CStmtKind::Compound(stmts) => print_compound(stmts, ctx),
             CStmtKind::Return(ret) => {
@@ -63,23 +288,178 @@ impl Print for CStmt<'_> {
                 });
             }
             CStmtKind::Decl(decl) => decl.print_to(ctx),
+            CStmtKind::If { cond, then_br, else_br } => print_if(cond, then_br, *else_br, ctx),
+            CStmtKind::While { cond, body } => {
+                ctx.word("while (");
+                cond.print_to(ctx);
+                ctx.word(")");
+                print_loop_body(body, ctx);
+            }
+            CStmtKind::For { init, cond, step, body } => {
+                ctx.word("for (");
+                // the init clause is printed without the statement hook, and
+                // brings its own semicolon
+                match init {
+                    Some(CStmtKind::Decl(decl)) => decl.print_to(ctx),
+                    Some(CStmtKind::Expr(expr)) => {
+                        expr.print_to(ctx);
+                        ctx.word(";");
+                    }
+                    Some(init) => unreachable!("invalid init clause `{init:?}`"),
+                    None => ctx.word(";"),
+                }
+                if let Some(cond) = cond {
+                    ctx.nbsp();
+                    cond.print_to(ctx);
+                }
+                ctx.word(";");
+                if let Some(step) = step {
+                    ctx.nbsp();
+                    step.print_to(ctx);
+                }
+                ctx.word(")");
+                print_loop_body(body, ctx);
+            }
             CStmtKind::Expr(expr) => {
                 expr.print_to(ctx);
                 ctx.word(";");
             }
+            CStmtKind::Switch { scrutinee, cases, default } => {
+                print_switch(scrutinee, cases, *default, ctx)
+            }
+            CStmtKind::Label(name) => {
+                ctx.word(name.to_string());
+                ctx.word(":");
+            }
+            CStmtKind::Goto(name) => {
+                ctx.word("goto ");
+                ctx.word(name.to_string());
+                ctx.word(";");
+            }
+            CStmtKind::Break => ctx.word("break;"),
+            CStmtKind::Continue => ctx.word("continue;"),
+            CStmtKind::Comment(text) => ctx.word(format!("// {text}")),
+            CStmtKind::Empty => ctx.word(";"),
         }
     }
 }
 
-/// Print a compound statement.
+/// Print a compound statement, or `{}` if it is empty.
 pub(crate) fn print_compound(stmts: &[CStmt], ctx: &mut PrinterCtx) {
-    ctx.cbox_delim(INDENT, ("{", "}"), 1, |ctx| {
-        if let Some((first, rest)) = stmts.split_first() {
-            first.print_to(ctx);
-            for stmt in rest {
-                ctx.hardbreak();
-                stmt.print_to(ctx);
+    if stmts.is_empty() {
+        ctx.word("{}");
+        return;
+    }
+    print_block(stmts.iter().map(|stmt| stmt as &dyn Print), ctx);
+}
+
+/// Print an if statement, chaining `else if` instead of nesting an if statement
+/// in the braces of the else branch.
+fn print_if(cond: CExpr, then_br: CStmt, else_br: Option<CStmt>, ctx: &mut PrinterCtx) {
+    ctx.word("if (");
+    cond.print_to(ctx);
+    ctx.word(") ");
+    print_branch(then_br, ctx);
+    match else_br {
+        // the statement hook is not called for the chained if, as its text
+        // cannot go between the `else` and the `if`
+        Some(CStmtKind::If { cond, then_br, else_br }) => {
+            ctx.word(" else ");
+            print_if(cond, then_br, *else_br, ctx);
+        }
+        Some(else_br) => {
+            ctx.word(" else ");
+            print_branch(else_br, ctx);
+        }
+        None => {}
+    }
+}
+
+/// Print the body of a loop after its header, on the same line if it is a
+/// compound statement, and on its own indented line otherwise.
+fn print_loop_body(body: CStmt, ctx: &mut PrinterCtx) {
+    match body {
+        CStmtKind::Compound(stmts) => {
+            ctx.nbsp();
+            print_compound(stmts, ctx);
+        }
+        _ => ctx.cbox(INDENT, |ctx| {
+            ctx.hardbreak();
+            body.print_to(ctx);
+        }),
+    }
+}
+
+/// Print a switch statement, with the labels at the indentation of the switch.
+fn print_switch(
+    scrutinee: CExpr,
+    cases: &[(i128, CStmt)],
+    default: Option<CStmt>,
+    ctx: &mut PrinterCtx,
+) {
+    ctx.word("switch (");
+    scrutinee.print_to(ctx);
+    ctx.word(")");
+    ctx.nbsp();
+    if cases.is_empty() && default.is_none() {
+        ctx.word("{}");
+        return;
+    }
+    ctx.word("{");
+    ctx.cbox(0, |ctx| {
+        let labels = cases.iter().map(|(value, body)| (format!("case {value}:"), *body));
+        for (label, body) in labels.chain(default.map(|body| ("default:".to_string(), body))) {
+            ctx.hardbreak();
+            ctx.word(label);
+            let stmts = match body {
+                CStmtKind::Compound(stmts) => stmts.as_slice(),
+                _ => std::slice::from_ref(&body),
+            };
+            ctx.cbox(INDENT, |ctx| {
+                for stmt in stmts {
+                    ctx.hardbreak_offset(if stmt.is_dedented() { -INDENT } else { 0 });
+                    stmt.print_to(ctx);
+                }
+            });
+        }
+        ctx.hardbreak();
+    });
+    ctx.word("}");
+}
+
+/// Print a branch of an if statement, always enclosed in braces.
+fn print_branch(stmt: CStmt, ctx: &mut PrinterCtx) {
+    match stmt {
+        CStmtKind::Compound(stmts) => print_compound(stmts, ctx),
+        _ => print_compound(&[stmt], ctx),
+    }
+}
+
+/// Print a sequence of statement-like items enclosed in braces.
+pub(crate) fn print_block<'a>(
+    items: impl IntoIterator<Item = &'a dyn Print>,
+    ctx: &mut PrinterCtx,
+) {
+    ctx.cbox(INDENT, |ctx| {
+        ctx.word("{");
+        let mut ends_line = false;
+        for (i, item) in items.into_iter().enumerate() {
+            let offset = if item.is_dedented() { -INDENT } else { 0 };
+            if i == 0 {
+                ctx.break_offset(1, offset);
+            } else {
+                ctx.hardbreak_offset(offset);
             }
+            item.print_to(ctx);
+            ends_line = item.ends_line();
+        }
+        // the closing brace of e.g. `{ // comment }` would be commented out,
+        // and the hardbreak breaks the whole block
+        if ends_line {
+            ctx.hardbreak_offset(-INDENT);
+        } else {
+            ctx.break_offset(1, -INDENT);
         }
+        ctx.word("}");
     });
 }
@@ -1,9 +1,9 @@
 //! This module defines the AST nodes for C types.
 
 use rustc_data_structures::intern::Interned;
-use rustc_type_ir::{IntTy, UintTy};
+use rustc_type_ir::{FloatTy, IntTy, UintTy};
 
-use crate::expr::CValue;
+use crate::expr::CExpr;
 use crate::pretty::{Print, PrinterCtx};
 use crate::ModuleCtx;
 
@@ -27,6 +27,8 @@ pub enum CTy<'mx> {
     Int(CIntTy),
     /// An unsigned integer type.
     UInt(CUintTy),
+    /// A floating-point type.
+    Float(CFloatTy),
     /// A non-primitive C type, e.g. a pointer type.
     ///
     /// This is an interned reference to a complex type.
@@ -39,6 +41,18 @@ impl<'mx> CTy<'mx> {
         matches!(self, CTy::Int(_))
     }
 
+    /// Whether the type is narrower than `int`, and thus promoted to `int` in
+    /// arithmetic, assuming `int` is 32 bits wide.
+    pub fn is_promoted(self) -> bool {
+        matches!(
+            self,
+            CTy::Bool
+                | CTy::Char
+                | CTy::Int(CIntTy::I8 | CIntTy::I16)
+                | CTy::UInt(CUintTy::U8 | CUintTy::U16)
+        )
+    }
+
     /// The unsigned version of this type.
     ///
     /// ## Panic
@@ -51,6 +65,18 @@ impl<'mx> CTy<'mx> {
         }
     }
 
+    /// The signed version of this type.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if the type is not an unsigned integer.
+    pub fn to_signed(self) -> Self {
+        match self {
+            CTy::UInt(ty) => CTy::Int(ty.to_signed()),
+            _ => unreachable!(),
+        }
+    }
+
     /// Get the corresponding C type name.
     ///
     /// This function should be only used for primitive types.
@@ -65,10 +91,28 @@ impl<'mx> CTy<'mx> {
             CTy::Char => "char",
             CTy::Int(ty) => ty.to_str(),
             CTy::UInt(ty) => ty.to_str(),
+            CTy::Float(ty) => ty.to_str(),
             CTy::Ref(_) => unreachable!(),
         }
     }
 
+    /// Get the C type name spelled according to `names`.
+    ///
+    /// This function should be only used for primitive types.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if the type is not a primitive type.
+    pub fn to_str_with(self, names: CIntNames) -> &'static str {
+        match (self, names) {
+            (CTy::Int(ty), CIntNames::Native) => ty.to_native_str().unwrap_or(ty.to_str()),
+            (CTy::UInt(ty), CIntNames::Native) => ty.to_native_str().unwrap_or(ty.to_str()),
+            (CTy::Int(ty), CIntNames::Rust) => ty.to_rust_str(),
+            (CTy::UInt(ty), CIntNames::Rust) => ty.to_rust_str(),
+            _ => self.to_str(),
+        }
+    }
+
     /// The maximum value of this type. From `<stdint.h>`.
     ///
     /// This function should be only used for integer types (signed or unsigned).
@@ -85,6 +129,38 @@ impl<'mx> CTy<'mx> {
     }
 }
 
+/// How fixed width integer types are spelled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CIntNames {
+    /// The `<stdint.h>` names, e.g. `uint32_t`.
+    #[default]
+    Stdint,
+    /// The native names, e.g. `unsigned int`, where the width is the same on
+    /// all common data models (ILP32, LP64 and LLP64), i.e. 8-bit `char`,
+    /// 16-bit `short`, 32-bit `int` and 64-bit `long long`. Pointer-sized types
+    /// fall back to the `<stdint.h>` names.
+    Native,
+    /// The Rust names, e.g. `u32`, declared as `typedef`s of the `<stdint.h>`
+    /// names at the top of the module, so that the C reads like the Rust.
+    Rust,
+}
+
+impl CIntNames {
+    /// All the integer types, for declaring their aliases.
+    pub(crate) const INT_TYS: [CTy<'static>; 10] = [
+        CTy::Int(CIntTy::Isize),
+        CTy::Int(CIntTy::I8),
+        CTy::Int(CIntTy::I16),
+        CTy::Int(CIntTy::I32),
+        CTy::Int(CIntTy::I64),
+        CTy::UInt(CUintTy::Usize),
+        CTy::UInt(CUintTy::U8),
+        CTy::UInt(CUintTy::U16),
+        CTy::UInt(CUintTy::U32),
+        CTy::UInt(CUintTy::U64),
+    ];
+}
+
 /// C primitive types.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum CIntTy {
@@ -110,7 +186,7 @@ impl CIntTy {
     /// Get the corresponding C type name.
     pub fn to_str(self) -> &'static str {
         match self {
-            CIntTy::Isize => "size_t",
+            CIntTy::Isize => "intptr_t",
             CIntTy::I8 => "int8_t",
             CIntTy::I16 => "int16_t",
             CIntTy::I32 => "int32_t",
@@ -118,16 +194,49 @@ impl CIntTy {
         }
     }
 
+    /// Get the Rust type name.
+    pub fn to_rust_str(self) -> &'static str {
+        match self {
+            CIntTy::Isize => "isize",
+            CIntTy::I8 => "i8",
+            CIntTy::I16 => "i16",
+            CIntTy::I32 => "i32",
+            CIntTy::I64 => "i64",
+        }
+    }
+
+    /// Get the native C type name, if the type has a fixed width.
+    pub fn to_native_str(self) -> Option<&'static str> {
+        match self {
+            CIntTy::Isize => None,
+            CIntTy::I8 => Some("signed char"),
+            CIntTy::I16 => Some("short"),
+            CIntTy::I32 => Some("int"),
+            CIntTy::I64 => Some("long long"),
+        }
+    }
+
     /// The maximum value of this type. From `<stdint.h>`.
     pub fn max_value(self) -> &'static str {
         match self {
-            CIntTy::Isize => "SIZE_MAX",
+            CIntTy::Isize => "INTPTR_MAX",
             CIntTy::I8 => "INT8_MAX",
             CIntTy::I16 => "INT16_MAX",
             CIntTy::I32 => "INT32_MAX",
             CIntTy::I64 => "INT64_MAX",
         }
     }
+
+    /// The minimum value of this type. From `<stdint.h>`.
+    pub fn min_value(self) -> &'static str {
+        match self {
+            CIntTy::Isize => "INTPTR_MIN",
+            CIntTy::I8 => "INT8_MIN",
+            CIntTy::I16 => "INT16_MIN",
+            CIntTy::I32 => "INT32_MIN",
+            CIntTy::I64 => "INT64_MIN",
+        }
+    }
 }
 
 /// C primitive types.
@@ -141,6 +250,17 @@ pub enum CUintTy {
 }
 
 impl CUintTy {
+    /// Get the signed version of this type.
+    pub fn to_signed(self) -> CIntTy {
+        match self {
+            CUintTy::Usize => CIntTy::Isize,
+            CUintTy::U8 => CIntTy::I8,
+            CUintTy::U16 => CIntTy::I16,
+            CUintTy::U32 => CIntTy::I32,
+            CUintTy::U64 => CIntTy::I64,
+        }
+    }
+
     /// Get the corresponding C type name.
     pub fn to_str(self) -> &'static str {
         match self {
@@ -152,6 +272,28 @@ impl CUintTy {
         }
     }
 
+    /// Get the Rust type name.
+    pub fn to_rust_str(self) -> &'static str {
+        match self {
+            CUintTy::Usize => "usize",
+            CUintTy::U8 => "u8",
+            CUintTy::U16 => "u16",
+            CUintTy::U32 => "u32",
+            CUintTy::U64 => "u64",
+        }
+    }
+
+    /// Get the native C type name, if the type has a fixed width.
+    pub fn to_native_str(self) -> Option<&'static str> {
+        match self {
+            CUintTy::Usize => None,
+            CUintTy::U8 => Some("unsigned char"),
+            CUintTy::U16 => Some("unsigned short"),
+            CUintTy::U32 => Some("unsigned int"),
+            CUintTy::U64 => Some("unsigned long long"),
+        }
+    }
+
     /// The maximum value of this type. From `<stdint.h>`.
     pub fn max_value(self) -> &'static str {
         match self {
@@ -164,16 +306,168 @@ impl CUintTy {
     }
 }
 
+/// C floating-point types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CFloatTy {
+    F32,
+    F64,
+}
+
+impl CFloatTy {
+    /// Get the corresponding C type name.
+    pub fn to_str(self) -> &'static str {
+        match self {
+            CFloatTy::F32 => "float",
+            CFloatTy::F64 => "double",
+        }
+    }
+}
+
 /// Complex C types, e.g. pointers and arrays.
 ///
 /// This type is interned, and thus should be unique in a specific context.
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Always create it with [`ModuleCtx::ty`], so that structurally equal types
+/// are also equal as [`CTy`]s.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum CTyKind<'mx> {
     /// A pointer type.
     Pointer(CTy<'mx>),
+    /// An array type with a fixed length, e.g. `int32_t[4]`.
+    Array(CTy<'mx>, usize),
+    /// A variable length array type, e.g. `int32_t[n]`.
+    ///
+    /// Only valid for block scope declarations, and only available in C99 or
+    /// with GNU extensions, see [`crate::standard::CStandard::has_vla`].
+    Vla(CTy<'mx>, CExpr<'mx>),
+    /// An array type whose length is a constant expression, e.g.
+    /// `int32_t[SIZE]` for an array whose length is a const generic.
+    ///
+    /// Unlike a [`CTyKind::Vla`], it is valid anywhere a fixed length array is.
+    ConstArray(CTy<'mx>, CExpr<'mx>),
+    /// A qualified type, e.g. `const char` or `int32_t *const`.
+    Qualified(CTy<'mx>, CQualifier),
+    /// A struct type referred to by its tag, e.g. `struct foo`.
+    ///
+    /// The struct is defined by a [`crate::decl::CDeclKind::Struct`].
+    Struct(&'mx str),
+    /// A function type, e.g. `void (size_t, size_t)`, mostly used as the
+    /// pointee of a function pointer, e.g. `void (*)(size_t, size_t)`.
+    ///
+    /// A variadic function type takes extra arguments after its parameters,
+    /// e.g. `int32_t (const char *, ...)`.
+    Function { ret: CTy<'mx>, params: Vec<CTy<'mx>>, variadic: bool },
+}
+
+/// C type qualifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CQualifier {
+    /// `const`
+    Const,
+    /// `volatile`
+    Volatile,
+    /// `restrict`, only valid for pointer types.
+    Restrict,
+}
+
+impl CQualifier {
+    /// Get the corresponding C keyword.
+    pub fn to_str(self) -> &'static str {
+        match self {
+            CQualifier::Const => "const",
+            CQualifier::Volatile => "volatile",
+            CQualifier::Restrict => "restrict",
+        }
+    }
 }
 
 impl<'mx> ModuleCtx<'mx> {
+    /// Create a new complex type, or get the existing one if it was created before.
+    pub fn ty(&self, ty: CTyKind<'mx>) -> CTy<'mx> {
+        let mut types = self.0.types.borrow_mut();
+        let ty = match types.get(&ty) {
+            Some(&ty) => ty,
+            None => {
+                let ty: &'mx CTyKind<'mx> = self.arena().alloc(ty);
+                types.insert(ty);
+                ty
+            }
+        };
+        CTy::Ref(Interned::new_unchecked(ty))
+    }
+
+    /// Create a pointer type.
+    pub fn ptr(&self, ty: CTy<'mx>) -> CTy<'mx> {
+        self.ty(CTyKind::Pointer(ty))
+    }
+
+    /// Create a fixed length array type.
+    pub fn arr(&self, ty: CTy<'mx>, len: usize) -> CTy<'mx> {
+        self.ty(CTyKind::Array(ty, len))
+    }
+
+    /// Create a variable length array type.
+    pub fn vla(&self, ty: CTy<'mx>, len: CExpr<'mx>) -> CTy<'mx> {
+        self.ty(CTyKind::Vla(ty, len))
+    }
+
+    /// Create an array type whose length is a constant expression.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if `len` is not a constant expression, see
+    /// [`crate::expr::CExprKind::is_constant`].
+    pub fn const_arr(&self, ty: CTy<'mx>, len: CExpr<'mx>) -> CTy<'mx> {
+        assert!(len.is_constant(), "the length of an array must be a constant expression");
+        self.ty(CTyKind::ConstArray(ty, len))
+    }
+
+    /// Create a struct type from its tag.
+    pub fn struct_ty(&self, tag: &'mx str) -> CTy<'mx> {
+        self.ty(CTyKind::Struct(tag))
+    }
+
+    /// Create a function type.
+    pub fn fn_ty(&self, ret: CTy<'mx>, params: Vec<CTy<'mx>>) -> CTy<'mx> {
+        self.ty(CTyKind::Function { ret, params, variadic: false })
+    }
+
+    /// Create a variadic function type.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if there are no parameters, which C requires before the `...`.
+    pub fn variadic_fn_ty(&self, ret: CTy<'mx>, params: Vec<CTy<'mx>>) -> CTy<'mx> {
+        assert!(!params.is_empty(), "a variadic function needs at least one parameter");
+        self.ty(CTyKind::Function { ret, params, variadic: true })
+    }
+
+    /// Create a qualified type.
+    pub fn qualified(&self, ty: CTy<'mx>, qualifier: CQualifier) -> CTy<'mx> {
+        self.ty(CTyKind::Qualified(ty, qualifier))
+    }
+
+    /// Get the type of a pointer lowered from a Rust reference to `pointee`,
+    /// e.g. `int32_t *restrict` for a `&mut i32`.
+    ///
+    /// A unique reference, i.e. `&mut T`, cannot alias any other pointer, so it
+    /// is `restrict`-qualified, while a shared one points to `const`.
+    pub fn ref_ptr(&self, pointee: CTy<'mx>, unique: bool) -> CTy<'mx> {
+        if unique {
+            self.qualified(self.ptr(pointee), CQualifier::Restrict)
+        } else {
+            self.ptr(self.qualified(pointee, CQualifier::Const))
+        }
+    }
+
+    /// Get the types of the data pointer and the length of a Rust slice of
+    /// `elem`, e.g. `int32_t *restrict` and `size_t` for a `&mut [i32]`.
+    ///
+    /// The data pointer is qualified like a reference, see [`ModuleCtx::ref_ptr`],
+    /// and the length is a `usize`, see [`ModuleCtx::get_uint_type`].
+    pub fn slice_parts(&self, elem: CTy<'mx>, unique: bool) -> (CTy<'mx>, CTy<'mx>) {
+        (self.ref_ptr(elem, unique), self.get_uint_type(UintTy::Usize))
+    }
+
     /// Get the type of an signed integer
     pub fn get_int_type(&self, int: IntTy) -> CTy<'mx> {
         match int {
@@ -186,10 +480,25 @@ impl<'mx> ModuleCtx<'mx> {
         }
     }
 
+    /// Get the type of a floating-point number
+    pub fn get_float_type(&self, float: FloatTy) -> CTy<'mx> {
+        match float {
+            FloatTy::F32 => CTy::Float(CFloatTy::F32),
+            FloatTy::F64 => CTy::Float(CFloatTy::F64),
+            FloatTy::F16 => unimplemented!("f16 not supported yet"),
+            FloatTy::F128 => unimplemented!("f128 not supported yet"),
+        }
+    }
+
     /// Get the type of an unsigned integer
+    ///
+    /// `<stddef.h>` is included in the module for the `size_t` of `usize`.
     pub fn get_uint_type(&self, uint: UintTy) -> CTy<'mx> {
         match uint {
-            UintTy::Usize => CTy::UInt(CUintTy::Usize),
+            UintTy::Usize => {
+                self.module().require_include("stddef.h");
+                CTy::UInt(CUintTy::Usize)
+            }
             UintTy::U8 => CTy::UInt(CUintTy::U8),
             UintTy::U16 => CTy::UInt(CUintTy::U16),
             UintTy::U32 => CTy::UInt(CUintTy::U32),
@@ -207,45 +516,161 @@ impl<'mx> ModuleCtx<'mx> {
 /// This function is necessary because the C declarator syntax is quite complex
 /// when the type becomes more complex, e.g. `int (*x)[10]`.
 ///
-/// When `val` is `None`, this prints an abstract declarator, or in other words,
+/// When `name` is `None`, this prints an abstract declarator, or in other words,
 /// a standalone type without an identifier.
-pub(crate) fn print_declarator(mut ty: CTy, val: Option<CValue>, ctx: &mut PrinterCtx) {
-    enum DeclaratorPart<'mx> {
-        Ident(Option<CValue<'mx>>),
+pub(crate) fn print_declarator(mut ty: CTy, name: Option<&dyn Print>, ctx: &mut PrinterCtx) {
+    enum DeclaratorPart<'a, 'mx> {
+        Ident(Option<&'a dyn Print>),
         Ptr,
+        Qualifier(CQualifier),
+        Array(usize),
+        Vla(CExpr<'mx>),
+        ConstArray(CExpr<'mx>),
+        Params(&'mx [CTy<'mx>], bool),
+        Paren(&'static str),
     }
 
-    impl Print for DeclaratorPart<'_> {
+    impl Print for DeclaratorPart<'_, '_> {
         fn print_to(&self, ctx: &mut PrinterCtx) {
             match self {
-                DeclaratorPart::Ident(val) => {
-                    if let &Some(val) = val {
-                        val.print_to(ctx);
+                DeclaratorPart::Ident(name) => {
+                    if let Some(name) = name {
+                        name.print_to(ctx);
                     }
                 }
                 DeclaratorPart::Ptr => {
                     ctx.word("*");
                 }
+                DeclaratorPart::Qualifier(qualifier) => {
+                    ctx.word(qualifier.to_str());
+                }
+                DeclaratorPart::Array(len) => {
+                    ctx.word(format!("[{}]", len));
+                }
+                DeclaratorPart::Vla(len) => {
+                    let standard = ctx.standard();
+                    assert!(
+                        standard.has_vla(),
+                        "variable length arrays are not available in {}",
+                        standard.to_str()
+                    );
+                    ctx.word("[");
+                    len.print_to(ctx);
+                    ctx.word("]");
+                }
+                DeclaratorPart::ConstArray(len) => {
+                    ctx.word("[");
+                    len.print_to(ctx);
+                    ctx.word("]");
+                }
+                DeclaratorPart::Params(params, variadic) => {
+                    if params.is_empty() {
+                        ctx.word("(void)");
+                    } else {
+                        ctx.word("(");
+                        for (i, param) in params.iter().enumerate() {
+                            if i != 0 {
+                                ctx.word(", ");
+                            }
+                            print_declarator(*param, None, ctx);
+                        }
+                        if *variadic {
+                            ctx.word(", ...");
+                        }
+                        ctx.word(")");
+                    }
+                }
+                DeclaratorPart::Paren(paren) => {
+                    ctx.word(*paren);
+                }
             }
         }
     }
 
+    // Pointers are prefix operators while arrays are suffix operators binding
+    // tighter, so a pointer followed by an array needs parentheses, e.g.
+    // `int (*x)[10]` is a pointer to an array, while `int *x[10]` is an array
+    // of pointers.
+    //
+    // Qualifiers of a pointer follow the `*`, e.g. `int *const x`, while other
+    // qualifiers are printed before the base type, e.g. `const int x[10]`, as
+    // qualifying an array type qualifies its elements anyway.
     let mut decl_parts = std::collections::VecDeque::new();
-    decl_parts.push_front(DeclaratorPart::Ident(val));
+    decl_parts.push_front(DeclaratorPart::Ident(name));
+    let mut base_qualifiers = Vec::new();
+    let mut after_ptr = false;
     while let CTy::Ref(kind) = ty {
-        match kind.0 {
-            CTyKind::Pointer(_) => decl_parts.push_front(DeclaratorPart::Ptr),
-        }
-        ty = match kind.0 {
-            CTyKind::Pointer(ty) => *ty,
+        let suffix = match kind.0 {
+            CTyKind::Pointer(inner) => {
+                decl_parts.push_front(DeclaratorPart::Ptr);
+                after_ptr = true;
+                ty = *inner;
+                continue;
+            }
+            CTyKind::Qualified(inner, qualifier) => {
+                match inner {
+                    CTy::Ref(inner) if matches!(inner.0, CTyKind::Pointer(_)) => {
+                        decl_parts.push_front(DeclaratorPart::Qualifier(*qualifier))
+                    }
+                    _ => base_qualifiers.push(*qualifier),
+                }
+                ty = *inner;
+                continue;
+            }
+            CTyKind::Array(inner, len) => {
+                ty = *inner;
+                DeclaratorPart::Array(*len)
+            }
+            CTyKind::Vla(inner, len) => {
+                ty = *inner;
+                DeclaratorPart::Vla(len)
+            }
+            CTyKind::ConstArray(inner, len) => {
+                ty = *inner;
+                DeclaratorPart::ConstArray(len)
+            }
+            CTyKind::Function { ret, params, variadic } => {
+                ty = *ret;
+                DeclaratorPart::Params(params, *variadic)
+            }
+            CTyKind::Struct(_) => break,
         };
+        if after_ptr {
+            decl_parts.push_front(DeclaratorPart::Paren("("));
+            decl_parts.push_back(DeclaratorPart::Paren(")"));
+            after_ptr = false;
+        }
+        decl_parts.push_back(suffix);
     }
 
-    ctx.word(ty.to_str()); // `ty` should be a primitive type here
-    if val.is_some() {
+    for qualifier in base_qualifiers {
+        ctx.word(qualifier.to_str());
+        ctx.nbsp();
+    }
+    match ty {
+        CTy::Ref(kind) => match kind.0 {
+            CTyKind::Struct(tag) => {
+                ctx.word("struct ");
+                ctx.word(tag.to_string());
+            }
+            _ => unreachable!(),
+        },
+        _ => ctx.word(ty.to_str_with(ctx.int_names())),
+    }
+    // Abstract declarators are spaced like named ones, e.g. `const void *`,
+    // except for arrays and functions, e.g. `int32_t[4]`
+    if name.is_some()
+        || matches!(decl_parts.front(), Some(DeclaratorPart::Ptr | DeclaratorPart::Paren(_)))
+    {
         ctx.nbsp();
     }
-    for part in decl_parts {
+    let mut decl_parts = decl_parts.into_iter().peekable();
+    while let Some(part) = decl_parts.next() {
         part.print_to(ctx);
+        if let DeclaratorPart::Qualifier(_) = part {
+            if let Some(DeclaratorPart::Ptr | DeclaratorPart::Ident(Some(_))) = decl_parts.peek() {
+                ctx.nbsp();
+            }
+        }
     }
 }
@@ -0,0 +1,220 @@
+#![feature(rustc_private)]
+
+use blessed_test::*;
+use run_c::run_module;
+use std::os::unix::process::ExitStatusExt;
+
+use rustc_codegen_c_ast::expr::CValue;
+use rustc_codegen_c_ast::func::{CFunc, CFuncKind};
+use rustc_codegen_c_ast::pretty::{Print, PrinterCtx};
+use rustc_codegen_c_ast::standard::CStandard;
+use rustc_codegen_c_ast::{ModuleArena, ModuleCtx};
+use rustc_type_ir::{IntTy, UintTy};
+
+extern crate rustc_driver;
+extern crate rustc_type_ir;
+mod blessed_test;
+mod run_c;
+
+#[test]
+fn test_builtin_copy_disjoint() {
+    printer_test("test_builtin_copy_disjoint", |ctx| {
+        let dst = ctx.value(CValue::Local(0));
+        let src = ctx.value(CValue::Local(1));
+        let size = ctx.value(CValue::Scalar(16));
+        Box::new(ctx.copy_bytes(dst, src, size, true))
+    });
+}
+
+#[test]
+fn test_builtin_copy_overlapping() {
+    printer_test("test_builtin_copy_overlapping", |ctx| {
+        let dst = ctx.value(CValue::Local(0));
+        let src = ctx.value(CValue::Local(1));
+        let size = ctx.value(CValue::Scalar(16));
+        Box::new(ctx.copy_bytes(dst, src, size, false))
+    });
+}
+
+#[test]
+fn test_builtin_copy_nonoverlapping() {
+    printer_test("test_builtin_copy_nonoverlapping", |ctx| {
+        let dst = ctx.value(CValue::Local(0));
+        let src = ctx.value(CValue::Local(1));
+        let count = ctx.value(CValue::Local(2));
+        Box::new(ctx.copy_nonoverlapping(dst, src, count, ctx.get_int_type(IntTy::I32)))
+    });
+}
+
+#[test]
+fn test_builtin_copy() {
+    printer_test("test_builtin_copy", |ctx| {
+        let dst = ctx.value(CValue::Local(0));
+        let src = ctx.value(CValue::Local(1));
+        let count = ctx.value(CValue::Local(2));
+        Box::new(ctx.copy(dst, src, count, ctx.get_int_type(IntTy::I32)))
+    });
+}
+
+#[test]
+fn test_builtin_prefetch() {
+    blessed_test("test_builtin_prefetch", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        let mut pp = PrinterCtx::with_standard(CStandard::Gnu11);
+        ctx.prefetch(ctx.value(CValue::Local(0)), false, 3).print_to(&mut pp);
+        pp.finish()
+    });
+}
+
+#[test]
+fn test_builtin_prefetch_c11() {
+    printer_test("test_builtin_prefetch_c11", |ctx| {
+        Box::new(ctx.prefetch(ctx.value(CValue::Local(0)), true, 0))
+    });
+}
+
+#[test]
+fn test_builtin_trap() {
+    blessed_test("test_builtin_trap", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        let mut pp = PrinterCtx::with_standard(CStandard::Gnu11);
+        ctx.trap().print_to(&mut pp);
+        pp.finish()
+    });
+}
+
+#[test]
+fn test_builtin_trap_c11() {
+    printer_test("test_builtin_trap_c11", |ctx| Box::new(ctx.trap()));
+}
+
+#[test]
+fn test_builtin_unreachable() {
+    blessed_test("test_builtin_unreachable", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        let mut pp = PrinterCtx::with_standard(CStandard::Gnu11);
+        ctx.unreachable().print_to(&mut pp);
+        pp.finish()
+    });
+}
+
+#[test]
+fn test_builtin_unreachable_c11() {
+    printer_test("test_builtin_unreachable_c11", |ctx| Box::new(ctx.unreachable()));
+}
+
+#[test]
+fn test_builtin_trap_run() {
+    // `SIGILL` or `SIGTRAP` for `__builtin_trap`, depending on the target, and `SIGABRT` for `abort`
+    for (standard, signals) in [(CStandard::Gnu11, [4, 5]), (CStandard::C11, [6, 6])] {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+        ctx.module().set_standard(standard);
+        ctx.module().push_include("stdint.h");
+
+        let main = ctx.func(CFuncKind::new("main", ctx.get_int_type(IntTy::I32), vec![]));
+        main.push_stmt(ctx.trap());
+        main.push_stmt(ctx.ret(Some(ctx.value(CValue::Scalar(0)))));
+        ctx.module().push_func(CFunc::new_unchecked(main));
+
+        let std = format!("-std={}", standard.to_str());
+        let status = run_module(&format!("trap_{}", standard.to_str()), ctx, &[&std]);
+        assert!(signals.contains(&status.signal().unwrap()), "{standard:?} exited with {status}");
+    }
+}
+
+#[test]
+fn test_builtin_include() {
+    let module = ModuleArena::new("// blessed test");
+    let ctx = ModuleCtx(&module);
+
+    let size = ctx.value(CValue::Scalar(16));
+    ctx.memcpy(ctx.value(CValue::Local(0)), ctx.value(CValue::Local(1)), size);
+    ctx.memmove(ctx.value(CValue::Local(0)), ctx.value(CValue::Local(1)), size);
+    assert_eq!(*ctx.module().includes.borrow(), vec!["string.h"]);
+}
+
+#[test]
+fn test_builtin_ctlz() {
+    printer_test("test_builtin_ctlz", |ctx| {
+        ctx.module().set_standard(CStandard::Gnu11);
+        Box::new(ctx.ctlz(ctx.value(CValue::Local(0)), ctx.get_uint_type(UintTy::U32)))
+    });
+}
+
+#[test]
+fn test_builtin_ctlz_u8() {
+    printer_test("test_builtin_ctlz_u8", |ctx| {
+        ctx.module().set_standard(CStandard::Gnu11);
+        Box::new(ctx.ctlz(ctx.value(CValue::Local(0)), ctx.get_uint_type(UintTy::U8)))
+    });
+}
+
+#[test]
+fn test_builtin_ctlz_usize() {
+    printer_test("test_builtin_ctlz_usize", |ctx| {
+        ctx.module().set_standard(CStandard::Gnu11);
+        Box::new(ctx.ctlz(ctx.value(CValue::Local(0)), ctx.get_uint_type(UintTy::Usize)))
+    });
+}
+
+#[test]
+fn test_builtin_cttz() {
+    printer_test("test_builtin_cttz", |ctx| {
+        ctx.module().set_standard(CStandard::Gnu11);
+        Box::new(ctx.cttz(ctx.value(CValue::Local(0)), ctx.get_uint_type(UintTy::U64)))
+    });
+}
+
+#[test]
+fn test_builtin_ctpop_signed() {
+    printer_test("test_builtin_ctpop_signed", |ctx| {
+        ctx.module().set_standard(CStandard::Gnu11);
+        Box::new(ctx.ctpop(ctx.value(CValue::Local(0)), ctx.get_int_type(IntTy::I8)))
+    });
+}
+
+#[test]
+fn test_builtin_ctlz_c11() {
+    printer_test("test_builtin_ctlz_c11", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+        let ty = ctx.get_uint_type(UintTy::U32);
+        let func = ctx.func(CFuncKind::new("foo", ty, vec![ty]));
+        func.push_stmt(ctx.ret(Some(ctx.ctlz(ctx.value(CValue::Local(0)), ty))));
+        // the helper is only defined once
+        ctx.ctlz(ctx.value(CValue::Local(0)), ty);
+        module.push_func(CFunc::new_unchecked(func));
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_builtin_ctlz_run() {
+    // `u32::leading_zeros`, which is the width of the type for zero
+    for standard in [CStandard::Gnu11, CStandard::C11] {
+        for (value, expected) in [(0, 32), (1, 31), (0x00ff_0000, 8)] {
+            let module = ModuleArena::new("// blessed test");
+            let ctx = ModuleCtx(&module);
+            ctx.module().set_standard(standard);
+            ctx.module().push_include("stdint.h");
+
+            let ty = ctx.get_uint_type(UintTy::U32);
+            let main = ctx.func(CFuncKind::new("main", ctx.get_int_type(IntTy::I32), vec![]));
+            let x = main.next_local_var();
+            main.push_stmt(ctx.decl_stmt(ctx.var(x, ty, Some(ctx.value(CValue::Scalar(value))))));
+            main.push_stmt(ctx.ret(Some(ctx.ctlz(ctx.value(x), ty))));
+            ctx.module().push_func(CFunc::new_unchecked(main));
+
+            let std = format!("-std={}", standard.to_str());
+            let status = run_module(&format!("ctlz_{}_{value}", standard.to_str()), ctx, &[&std]);
+            assert_eq!(status.code(), Some(expected), "{standard:?} of {value}");
+        }
+    }
+}
@@ -1,8 +1,13 @@
 #![feature(rustc_private)]
 
 use blessed_test::*;
+use rustc_codegen_c_ast::decl::CStorageClass;
 use rustc_codegen_c_ast::expr::CValue;
-use rustc_type_ir::IntTy;
+use rustc_codegen_c_ast::pretty::{Print, PrinterCtx};
+use rustc_codegen_c_ast::standard::CStandard;
+use rustc_codegen_c_ast::ty::{CIntNames, CQualifier, CTy};
+use rustc_codegen_c_ast::{ModuleArena, ModuleCtx};
+use rustc_type_ir::{IntTy, UintTy};
 
 extern crate rustc_driver;
 extern crate rustc_type_ir;
@@ -17,3 +22,175 @@ fn test_decl_var() {
         Box::new(ctx.var(name, ty, Some(value)))
     });
 }
+
+#[test]
+fn test_decl_array() {
+    printer_test("test_decl_array", |ctx| {
+        let ty = ctx.arr(ctx.get_int_type(IntTy::I32), 4);
+        Box::new(ctx.var(CValue::Local(42), ty, None))
+    });
+}
+
+#[test]
+fn test_decl_array_of_ptr_to_array() {
+    printer_test("test_decl_array_of_ptr_to_array", |ctx| {
+        let ty = ctx.arr(ctx.ptr(ctx.arr(ctx.get_int_type(IntTy::I32), 5)), 3);
+        Box::new(ctx.var(CValue::Local(4), ty, None))
+    });
+}
+
+#[test]
+fn test_decl_qualified_ptr() {
+    printer_test("test_decl_qualified_ptr", |ctx| {
+        let ty =
+            ctx.qualified(ctx.ptr(ctx.qualified(CTy::Char, CQualifier::Const)), CQualifier::Const);
+        Box::new(ctx.var(CValue::Local(1), ctx.ptr(ty), None))
+    });
+}
+
+#[test]
+fn test_decl_thread_local() {
+    printer_test("test_decl_thread_local", |ctx| {
+        let ty = ctx.get_int_type(IntTy::I32);
+        let init = ctx.value(CValue::Scalar(0));
+        Box::new(ctx.thread_local_var(
+            Some(CStorageClass::Static),
+            CValue::Global("foo"),
+            ty,
+            Some(init),
+        ))
+    });
+}
+
+#[test]
+fn test_decl_thread_local_c99() {
+    blessed_test("test_decl_thread_local_c99", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        let ty = ctx.get_int_type(IntTy::I32);
+        let mut pp = PrinterCtx::with_standard(CStandard::C99);
+        ctx.thread_local_var(Some(CStorageClass::Static), CValue::Global("foo"), ty, None)
+            .print_to(&mut pp);
+        pp.finish()
+    });
+}
+
+#[test]
+fn test_decl_static_assert() {
+    printer_test("test_decl_static_assert", |ctx| {
+        let cond = ctx.binary(ctx.raw("sizeof(int32_t)"), ctx.value(CValue::Scalar(4)), "==");
+        Box::new(ctx.static_assert(cond, "int32_t must be 4 bytes"))
+    });
+}
+
+#[test]
+fn test_decl_static_assert_c99() {
+    blessed_test("test_decl_static_assert_c99", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        let cond = ctx.binary(ctx.raw("sizeof(int32_t)"), ctx.value(CValue::Scalar(4)), "==");
+        let mut pp = PrinterCtx::with_standard(CStandard::C99);
+        ctx.static_assert(cond, "int32_t must be 4 bytes").print_to(&mut pp);
+        pp.finish()
+    });
+}
+
+#[test]
+fn test_decl_u32_stdint() {
+    printer_test("test_decl_u32_stdint", |ctx| {
+        Box::new(ctx.var(CValue::Local(42), ctx.get_uint_type(UintTy::U32), None))
+    });
+}
+
+#[test]
+fn test_decl_u32_native() {
+    blessed_test("test_decl_u32_native", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        let mut pp = PrinterCtx::new().with_int_names(CIntNames::Native);
+        ctx.var(CValue::Local(42), ctx.get_uint_type(UintTy::U32), None).print_to(&mut pp);
+        pp.finish()
+    });
+}
+
+#[test]
+fn test_decl_zeroed_struct() {
+    printer_test("test_decl_zeroed_struct", |ctx| {
+        let ty = ctx.array_wrapper(ctx.get_int_type(IntTy::I32), 4);
+        Box::new(ctx.zeroed_var(CValue::Local(1), ty))
+    });
+}
+
+#[test]
+fn test_decl_typedef_qsort_cmp() {
+    printer_test("test_decl_typedef_qsort_cmp", |ctx| {
+        let param = ctx.ptr(ctx.qualified(CTy::Void, CQualifier::Const));
+        let cmp = ctx.fn_ty(ctx.get_int_type(IntTy::I32), vec![param, param]);
+        Box::new(ctx.typedef("cmp", ctx.ptr(cmp)))
+    });
+}
+
+#[test]
+fn test_decl_bool_normalized() {
+    printer_test("test_decl_bool_normalized", |ctx| {
+        Box::new(ctx.var(CValue::Local(1), CTy::Bool, Some(ctx.value(CValue::Local(0)))))
+    });
+}
+
+#[test]
+fn test_decl_bool_comparison() {
+    printer_test("test_decl_bool_comparison", |ctx| {
+        let cond = ctx.binary(ctx.value(CValue::Local(0)), ctx.value(CValue::Scalar(1)), "<");
+        Box::new(ctx.var(CValue::Local(1), CTy::Bool, Some(cond)))
+    });
+}
+
+#[test]
+fn test_decl_enum_c23() {
+    blessed_test("test_decl_enum_c23", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        let ty = ctx.get_uint_type(UintTy::U8);
+        let mut pp = PrinterCtx::with_standard(CStandard::C23);
+        ctx.enum_decl("foo", Some(ty), vec![("A", 0), ("B", 255)]).print_to(&mut pp);
+        pp.finish()
+    });
+}
+
+#[test]
+fn test_decl_enum_c11() {
+    printer_test("test_decl_enum_c11", |ctx| {
+        let ty = ctx.get_uint_type(UintTy::U8);
+        Box::new(ctx.enum_decl("foo", Some(ty), vec![("A", 0), ("B", 255)]))
+    });
+}
+
+#[test]
+fn test_decl_struct_volatile_fields() {
+    printer_test("test_decl_struct_volatile_fields", |ctx| {
+        let reg = ctx.qualified(ctx.get_uint_type(UintTy::U32), CQualifier::Volatile);
+        // qualifying an array qualifies its elements
+        let data = ctx.qualified(ctx.arr(ctx.get_uint_type(UintTy::U32), 4), CQualifier::Volatile);
+        Box::new(ctx.struct_decl("regs", vec![(reg, "STATUS"), (reg, "CTRL"), (data, "DATA")]))
+    });
+}
+
+#[test]
+fn test_decl_const_array_len() {
+    printer_test("test_decl_const_array_len", |ctx| {
+        let ty = ctx.const_arr(ctx.get_int_type(IntTy::I32), ctx.raw("SIZE"));
+        Box::new(ctx.var(CValue::Global("a"), ty, None))
+    });
+}
+
+#[test]
+#[should_panic(expected = "the length of an array must be a constant expression")]
+fn test_decl_const_array_len_local() {
+    let module = ModuleArena::new("// blessed test");
+    let ctx = ModuleCtx(&module);
+    ctx.const_arr(ctx.get_int_type(IntTy::I32), ctx.value(CValue::Local(0)));
+}
@@ -1,12 +1,19 @@
 #![feature(rustc_private)]
 
 use blessed_test::*;
+use run_c::run_module;
 use rustc_codegen_c_ast::expr::CValue;
-use rustc_type_ir::IntTy;
+use rustc_codegen_c_ast::func::{CFunc, CFuncKind};
+use rustc_codegen_c_ast::pretty::{Print, PrinterCtx};
+use rustc_codegen_c_ast::standard::CStandard;
+use rustc_codegen_c_ast::ty::{CFloatTy, CTy};
+use rustc_codegen_c_ast::{ModuleArena, ModuleCtx};
+use rustc_type_ir::{IntTy, UintTy};
 
 extern crate rustc_driver;
 extern crate rustc_type_ir;
 mod blessed_test;
+mod run_c;
 
 #[test]
 fn test_value_scalar() {
@@ -23,11 +30,51 @@ fn test_value_func() {
     printer_test("test_value_func", |_| Box::new(CValue::Func("foo")));
 }
 
+#[test]
+fn test_value_float() {
+    printer_test("test_value_float", |ctx| Box::new(ctx.float(1.5, CFloatTy::F32)));
+}
+
+#[test]
+#[allow(clippy::approx_constant)]
+fn test_value_double() {
+    printer_test("test_value_double", |ctx| Box::new(ctx.float(3.14, CFloatTy::F64)));
+}
+
+#[test]
+fn test_value_double_zero() {
+    printer_test("test_value_double_zero", |ctx| Box::new(ctx.float(0.0, CFloatTy::F64)));
+}
+
+#[test]
+fn test_value_double_neg_zero() {
+    printer_test("test_value_double_neg_zero", |ctx| Box::new(ctx.float(-0.0, CFloatTy::F64)));
+}
+
+#[test]
+fn test_value_float_special() {
+    printer_test("test_value_float_special", |ctx| {
+        let nan = ctx.float(f64::NAN, CFloatTy::F32);
+        let inf = ctx.float(f64::NEG_INFINITY, CFloatTy::F64);
+        Box::new(ctx.comma(vec![nan, inf]))
+    });
+}
+
 #[test]
 fn test_expr_raw() {
     printer_test("test_expr_raw", |ctx| Box::new(ctx.raw("42")));
 }
 
+#[test]
+fn test_expr_string() {
+    printer_test("test_expr_string", |ctx| Box::new(ctx.string("\"été\"\n")));
+}
+
+#[test]
+fn test_expr_string_hex_digit() {
+    printer_test("test_expr_string_hex_digit", |ctx| Box::new(ctx.string("Ébène")));
+}
+
 #[test]
 fn test_expr_binary() {
     printer_test("test_expr_binary", |ctx| {
@@ -37,6 +84,118 @@ fn test_expr_binary() {
     });
 }
 
+#[test]
+fn test_expr_prefix_inc() {
+    printer_test("test_expr_prefix_inc", |ctx| {
+        Box::new(ctx.unary("++", ctx.value(CValue::Local(0))))
+    });
+}
+
+#[test]
+fn test_expr_postfix_inc() {
+    printer_test("test_expr_postfix_inc", |ctx| {
+        Box::new(ctx.postfix("++", ctx.value(CValue::Local(0))))
+    });
+}
+
+#[test]
+fn test_expr_postfix_dec_deref() {
+    printer_test("test_expr_postfix_dec_deref", |ctx| {
+        Box::new(ctx.postfix("--", ctx.unary("*", ctx.value(CValue::Local(0)))))
+    });
+}
+
+#[test]
+fn test_expr_neg() {
+    printer_test("test_expr_neg", |ctx| Box::new(ctx.unary("-", ctx.value(CValue::Local(0)))));
+}
+
+#[test]
+fn test_expr_neg_literal() {
+    printer_test("test_expr_neg_literal", |ctx| {
+        // `-` on a negative literal must not be printed as the decrement `--1`
+        Box::new(ctx.comma(vec![
+            ctx.unary("-", ctx.value(CValue::Scalar(-1))),
+            ctx.unary("-", ctx.float(-1.5, CFloatTy::F64)),
+            ctx.unary("-", ctx.float(-0.0, CFloatTy::F32)),
+            ctx.unary("~", ctx.value(CValue::Scalar(-1))),
+            ctx.unary("-", ctx.value(CValue::Scalar(1))),
+        ]))
+    });
+}
+
+#[test]
+fn test_expr_not() {
+    printer_test("test_expr_not", |ctx| Box::new(ctx.unary("!", ctx.value(CValue::Local(0)))));
+}
+
+#[test]
+fn test_expr_bit_not() {
+    printer_test("test_expr_bit_not", |ctx| Box::new(ctx.unary("~", ctx.value(CValue::Local(0)))));
+}
+
+#[test]
+fn test_expr_neg_nested() {
+    printer_test("test_expr_neg_nested", |ctx| {
+        // `- -x` must not be printed as the decrement `--x`, while `-~x` is unambiguous
+        let x = ctx.value(CValue::Local(0));
+        let sum = ctx.binary(x, ctx.value(CValue::Local(1)), "+");
+        Box::new(ctx.comma(vec![
+            ctx.unary("-", ctx.unary("-", x)),
+            ctx.unary("-", ctx.unary("~", x)),
+            ctx.unary("-", sum),
+        ]))
+    });
+}
+
+#[test]
+fn test_expr_likely() {
+    blessed_test("test_expr_likely", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        let mut pp = PrinterCtx::with_standard(CStandard::Gnu11);
+        ctx.likely(ctx.value(CValue::Local(0))).print_to(&mut pp);
+        pp.finish()
+    });
+}
+
+#[test]
+fn test_expr_unlikely() {
+    blessed_test("test_expr_unlikely", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        let cond = ctx.binary(ctx.value(CValue::Local(0)), ctx.value(CValue::Scalar(1)), "<");
+        let mut pp = PrinterCtx::with_standard(CStandard::Gnu11);
+        ctx.unlikely(cond).print_to(&mut pp);
+        pp.finish()
+    });
+}
+
+#[test]
+fn test_expr_unlikely_c11() {
+    printer_test("test_expr_unlikely_c11", |ctx| {
+        Box::new(ctx.unlikely(ctx.value(CValue::Local(0))))
+    });
+}
+
+#[test]
+fn test_expr_is_null() {
+    printer_test("test_expr_is_null", |ctx| {
+        let expr = ctx.is_null(ctx.value(CValue::Local(0)));
+        assert!(ctx.module().includes.borrow().contains(&"stddef.h"));
+        Box::new(expr)
+    });
+}
+
+#[test]
+fn test_expr_zeroed() {
+    printer_test("test_expr_zeroed", |ctx| {
+        Box::new(ctx.zeroed(ctx.array_wrapper(ctx.get_int_type(IntTy::I32), 4)))
+    });
+}
+
 #[test]
 fn test_expr_cast() {
     printer_test("test_expr_cast", |ctx| {
@@ -46,6 +205,46 @@ fn test_expr_cast() {
     });
 }
 
+#[test]
+fn test_expr_cast_neg() {
+    printer_test("test_expr_cast_neg", |ctx| {
+        let ty = ctx.get_int_type(IntTy::I32);
+        Box::new(ctx.cast(ty, ctx.unary("-", ctx.value(CValue::Local(0)))))
+    });
+}
+
+#[test]
+fn test_expr_cast_binary() {
+    printer_test("test_expr_cast_binary", |ctx| {
+        let ty = ctx.get_int_type(IntTy::I32);
+        let sum = ctx.binary(ctx.value(CValue::Local(0)), ctx.value(CValue::Local(1)), "+");
+        Box::new(ctx.cast(ty, sum))
+    });
+}
+
+#[test]
+fn test_expr_sizeof_array() {
+    printer_test("test_expr_sizeof_array", |ctx| {
+        Box::new(ctx.sizeof(ctx.arr(ctx.get_int_type(IntTy::I32), 4)))
+    });
+}
+
+#[test]
+fn test_expr_sizeof_expr() {
+    printer_test("test_expr_sizeof_expr", |ctx| {
+        Box::new(ctx.sizeof_expr(ctx.value(CValue::Local(0))))
+    });
+}
+
+#[test]
+fn test_expr_offsetof_nested() {
+    printer_test("test_expr_offsetof_nested", |ctx| {
+        let expr = ctx.offsetof(ctx.struct_ty("foo"), "bar.baz");
+        assert!(ctx.module().includes.borrow().contains(&"stddef.h"));
+        Box::new(expr)
+    });
+}
+
 #[test]
 fn test_expr_call() {
     printer_test("test_expr_call", |ctx| {
@@ -55,6 +254,59 @@ fn test_expr_call() {
     });
 }
 
+#[test]
+fn test_expr_comma() {
+    printer_test("test_expr_comma", |ctx| {
+        let f = ctx.call(ctx.value(CValue::Func("f")), vec![]);
+        let g = ctx.call(ctx.value(CValue::Func("g")), vec![]);
+        Box::new(ctx.comma(vec![f, g, ctx.value(CValue::Local(0))]))
+    });
+}
+
+#[test]
+fn test_expr_ternary() {
+    printer_test("test_expr_ternary", |ctx| {
+        let cond = ctx.binary(ctx.value(CValue::Local(0)), ctx.value(CValue::Scalar(0)), "==");
+        let then = ctx.value(CValue::Scalar(1));
+        let els = ctx.binary(ctx.value(CValue::Local(1)), ctx.value(CValue::Scalar(2)), "+");
+        Box::new(ctx.ternary(cond, then, els))
+    });
+}
+
+#[test]
+fn test_expr_ternary_chain() {
+    printer_test("test_expr_ternary_chain", |ctx| {
+        let [a, b, c] = [0, 1, 2].map(|i| ctx.value(CValue::Local(i)));
+        let inner = ctx.ternary(b, ctx.value(CValue::Scalar(2)), ctx.value(CValue::Scalar(3)));
+        let chain = ctx.ternary(a, ctx.value(CValue::Scalar(1)), inner);
+        // a conditional expression as the condition keeps its parentheses
+        Box::new(ctx.ternary(chain, c, ctx.value(CValue::Scalar(4))))
+    });
+}
+
+#[test]
+fn test_expr_index() {
+    printer_test("test_expr_index", |ctx| {
+        Box::new(ctx.index(ctx.value(CValue::Global("a")), ctx.value(CValue::Scalar(0))))
+    });
+}
+
+#[test]
+fn test_expr_index_nested() {
+    printer_test("test_expr_index_nested", |ctx| {
+        let row = ctx.index(ctx.value(CValue::Global("a")), ctx.value(CValue::Local(0)));
+        Box::new(ctx.index(row, ctx.value(CValue::Local(1))))
+    });
+}
+
+#[test]
+fn test_expr_index_deref() {
+    printer_test("test_expr_index_deref", |ctx| {
+        let array = ctx.unary("*", ctx.value(CValue::Global("p")));
+        Box::new(ctx.index(array, ctx.value(CValue::Local(0))))
+    });
+}
+
 #[test]
 fn test_expr_member() {
     printer_test("test_expr_member", |ctx| {
@@ -82,3 +334,193 @@ fn test_expr_complex() {
         Box::new(member)
     });
 }
+
+#[test]
+fn test_expr_generic() {
+    printer_test("test_expr_generic", |ctx| {
+        let controlling = ctx.value(CValue::Local(0));
+        let cases = vec![
+            (ctx.get_int_type(IntTy::I32), ctx.value(CValue::Func("foo_i32"))),
+            (ctx.get_uint_type(UintTy::U32), ctx.value(CValue::Func("foo_u32"))),
+        ];
+        let default = ctx.value(CValue::Func("foo"));
+        Box::new(ctx.generic(controlling, cases, Some(default)))
+    });
+}
+
+#[test]
+#[should_panic(expected = "`_Generic` is not available in c99")]
+fn test_expr_generic_c99() {
+    let module = ModuleArena::new("// blessed test");
+    let ctx = ModuleCtx(&module);
+
+    let controlling = ctx.value(CValue::Local(0));
+    let cases = vec![(ctx.get_int_type(IntTy::I32), ctx.value(CValue::Func("foo_i32")))];
+    let mut pp = PrinterCtx::with_standard(CStandard::C99);
+    ctx.generic(controlling, cases, None).print_to(&mut pp);
+}
+
+#[test]
+fn test_expr_shr_u32() {
+    printer_test("test_expr_shr_u32", |ctx| {
+        let ty = ctx.get_uint_type(UintTy::U32);
+        Box::new(ctx.shr(ty, ctx.value(CValue::Local(0)), ctx.value(CValue::Scalar(1)), false))
+    });
+}
+
+#[test]
+fn test_expr_shr_i32() {
+    printer_test("test_expr_shr_i32", |ctx| {
+        let ty = ctx.get_int_type(IntTy::I32);
+        Box::new(ctx.shr(ty, ctx.value(CValue::Local(0)), ctx.value(CValue::Scalar(1)), true))
+    });
+}
+
+#[test]
+fn test_expr_shr_logical_on_signed() {
+    printer_test("test_expr_shr_logical_on_signed", |ctx| {
+        let ty = ctx.get_int_type(IntTy::I32);
+        Box::new(ctx.shr(ty, ctx.value(CValue::Local(0)), ctx.value(CValue::Scalar(1)), false))
+    });
+}
+
+#[test]
+fn test_expr_shr_arithmetic_on_unsigned() {
+    printer_test("test_expr_shr_arithmetic_on_unsigned", |ctx| {
+        let ty = ctx.get_uint_type(UintTy::U32);
+        Box::new(ctx.shr(ty, ctx.value(CValue::Local(0)), ctx.value(CValue::Scalar(1)), true))
+    });
+}
+
+#[test]
+fn test_expr_compare_same_sign() {
+    printer_test("test_expr_compare_same_sign", |ctx| {
+        let ty = ctx.get_int_type(IntTy::I32);
+        let (lhs, rhs) = (ctx.value(CValue::Local(0)), ctx.value(CValue::Local(1)));
+        Box::new(ctx.compare(ty, lhs, ty, rhs, "<", true))
+    });
+}
+
+#[test]
+fn test_expr_compare_index_len_signed() {
+    printer_test("test_expr_compare_index_len_signed", |ctx| {
+        let index = ctx.get_int_type(IntTy::Isize);
+        let len = ctx.get_uint_type(UintTy::Usize);
+        let (lhs, rhs) = (ctx.value(CValue::Local(0)), ctx.value(CValue::Local(1)));
+        Box::new(ctx.compare(index, lhs, len, rhs, "<", true))
+    });
+}
+
+#[test]
+fn test_expr_compare_index_len_unsigned() {
+    printer_test("test_expr_compare_index_len_unsigned", |ctx| {
+        let index = ctx.get_int_type(IntTy::Isize);
+        let len = ctx.get_uint_type(UintTy::Usize);
+        let (lhs, rhs) = (ctx.value(CValue::Local(0)), ctx.value(CValue::Local(1)));
+        Box::new(ctx.compare(index, lhs, len, rhs, "<", false))
+    });
+}
+
+#[test]
+fn test_expr_logical_and_lazy() {
+    printer_test("test_expr_logical_and_lazy", |ctx| {
+        let rhs = ctx.call(ctx.value(CValue::Func("foo")), vec![]);
+        Box::new(ctx.logical_and_lazy(ctx.value(CValue::Local(0)), rhs))
+    });
+}
+
+#[test]
+fn test_expr_logical_and_lazy_run() {
+    let module = ModuleArena::new(
+        "static int32_t calls = 0;\nstatic _Bool side_effect(void) { calls++; return 1; }",
+    );
+    let ctx = ModuleCtx(&module);
+    ctx.module().push_include("stdint.h");
+
+    let main = ctx.func(CFuncKind::new("main", ctx.get_int_type(IntTy::I32), vec![]));
+    for lhs in [0, 1] {
+        let rhs = ctx.call(ctx.value(CValue::Func("side_effect")), vec![]);
+        let and = ctx.logical_and_lazy(ctx.value(CValue::Scalar(lhs)), rhs);
+        main.push_stmt(ctx.expr_stmt(ctx.cast(CTy::Void, and)));
+    }
+    main.push_stmt(ctx.ret(Some(ctx.raw("calls"))));
+    ctx.module().push_func(CFunc::new_unchecked(main));
+
+    // the side effect only happens for the true lhs
+    assert_eq!(run_module("logical_and_lazy", ctx, &[]).code(), Some(1));
+}
+
+#[test]
+fn test_expr_wrapping_u8() {
+    printer_test("test_expr_wrapping_u8", |ctx| {
+        let ty = ctx.get_uint_type(UintTy::U8);
+        Box::new(ctx.wrapping_binary(
+            ty,
+            ctx.value(CValue::Local(0)),
+            ctx.value(CValue::Local(1)),
+            "+",
+        ))
+    });
+}
+
+#[test]
+fn test_expr_wrapping_u16_mul() {
+    printer_test("test_expr_wrapping_u16_mul", |ctx| {
+        let ty = ctx.get_uint_type(UintTy::U16);
+        Box::new(ctx.wrapping_binary(
+            ty,
+            ctx.value(CValue::Local(0)),
+            ctx.value(CValue::Local(1)),
+            "*",
+        ))
+    });
+}
+
+#[test]
+fn test_expr_struct_literal() {
+    printer_test("test_expr_struct_literal", |ctx| {
+        let one = ctx.value(CValue::Scalar(1));
+        let three = ctx.value(CValue::Scalar(3));
+        Box::new(ctx.struct_literal(ctx.struct_ty("foo"), vec![("a", one), ("c", three)]))
+    });
+}
+
+#[test]
+fn test_expr_struct_literal_run() {
+    let module = ModuleArena::new("// struct literal test");
+    let ctx = ModuleCtx(&module);
+    ctx.module().push_include("stdint.h");
+
+    let ty = ctx.get_int_type(IntTy::I32);
+    ctx.module().push_decl(ctx.struct_decl("foo", vec![(ty, "a"), (ty, "b"), (ty, "c")]));
+    let main = ctx.func(CFuncKind::new("main", ty, vec![]));
+    let x = main.next_local_var();
+    let fields = vec![("c", ctx.value(CValue::Scalar(3))), ("a", ctx.value(CValue::Scalar(1)))];
+    let init = ctx.struct_literal(ctx.struct_ty("foo"), fields);
+    main.push_stmt(ctx.decl_stmt(ctx.var(x, ctx.struct_ty("foo"), Some(init))));
+    // `b` is not designated, so it is zero-filled
+    let field = |field, value| {
+        ctx.binary(ctx.member(ctx.value(x), field), ctx.value(CValue::Scalar(value)), "==")
+    };
+    let all = ctx.binary(ctx.binary(field("a", 1), field("b", 0), "&&"), field("c", 3), "&&");
+    main.push_stmt(ctx.ret(Some(all)));
+    ctx.module().push_func(CFunc::new_unchecked(main));
+
+    assert_eq!(run_module("struct_literal", ctx, &[]).code(), Some(1));
+}
+
+#[test]
+fn test_expr_load() {
+    printer_test("test_expr_load", |ctx| {
+        Box::new(ctx.load(ctx.value(CValue::Local(0)), ctx.get_int_type(IntTy::I32)))
+    });
+}
+
+#[test]
+fn test_expr_load_typed() {
+    printer_test("test_expr_load_typed", |ctx| {
+        // the pointer already has the right type, so it is not cast again
+        let ty = ctx.get_int_type(IntTy::I32);
+        Box::new(ctx.load(ctx.cast(ctx.ptr(ty), ctx.value(CValue::Local(0))), ty))
+    });
+}
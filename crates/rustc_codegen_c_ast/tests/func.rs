@@ -2,7 +2,11 @@
 
 use blessed_test::*;
 use rustc_codegen_c_ast::expr::CValue;
-use rustc_codegen_c_ast::func::{CFunc, CFuncKind};
+use rustc_codegen_c_ast::func::{CFunc, CFuncAttr, CFuncKind};
+use rustc_codegen_c_ast::pretty::{IndentStyle, Print, PrinterCtx};
+use rustc_codegen_c_ast::standard::CStandard;
+use rustc_codegen_c_ast::ty::CTy;
+use rustc_codegen_c_ast::{ModuleArena, ModuleCtx};
 use rustc_type_ir::IntTy;
 
 extern crate rustc_driver;
@@ -24,3 +28,199 @@ fn test_function() {
         Box::new(CFunc::new_unchecked(func))
     });
 }
+
+#[test]
+fn test_function_unused_param() {
+    printer_test("test_function_unused_param", |ctx| {
+        let func = ctx.func(CFuncKind::new(
+            "foo",
+            ctx.get_int_type(IntTy::I32),
+            vec![ctx.get_int_type(IntTy::I32), ctx.get_int_type(IntTy::I32)],
+        ));
+        func.mark_param_unused(1);
+        func.push_stmt(ctx.ret(Some(ctx.value(CValue::Local(0)))));
+        Box::new(CFunc::new_unchecked(func))
+    });
+}
+
+#[test]
+fn test_function_must_use() {
+    printer_test("test_function_must_use", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+        let func = ctx.func(CFuncKind::new("foo", ctx.get_int_type(IntTy::I32), vec![]));
+        func.set_must_use();
+        func.push_stmt(ctx.ret(Some(ctx.value(CValue::Scalar(0)))));
+        module.push_func(CFunc::new_unchecked(func));
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_function_cold() {
+    blessed_test("test_function_cold", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+        let module = ctx.module();
+        module.push_include("stdint.h");
+        module.set_standard(CStandard::Gnu11);
+
+        let cold = ctx.func(CFuncKind::new("fail", ctx.get_int_type(IntTy::I32), vec![]));
+        cold.add_attr(CFuncAttr::Cold);
+        cold.push_stmt(ctx.ret(Some(ctx.value(CValue::Scalar(1)))));
+        module.push_func(CFunc::new_unchecked(cold));
+
+        // the path calling the cold function is hinted as unlikely
+        let func = ctx.func(CFuncKind::new("foo", CTy::Void, vec![CTy::Bool]));
+        let cond = ctx.builtin_expect(ctx.value(CValue::Local(0)), 0);
+        let call = ctx.call(ctx.value(CValue::Func("fail")), vec![]);
+        func.push_stmt(ctx.expr_stmt(ctx.cast(CTy::Void, ctx.logical_and_lazy(cond, call))));
+        module.push_func(CFunc::new_unchecked(func));
+        ctx.to_string()
+    });
+}
+
+#[test]
+fn test_function_phi() {
+    printer_test("test_function_phi", |ctx| {
+        let func = ctx.func(CFuncKind::new("foo", ctx.get_int_type(IntTy::I32), vec![CTy::Bool]));
+        let phi = ctx.phi_local(func, ctx.get_int_type(IntTy::I32));
+        func.push_stmt(ctx.if_stmt(ctx.value(CValue::Local(0)), ctx.goto("bb1"), None));
+        func.push_stmt(ctx.goto("bb2"));
+        for (bb, value) in [("bb1", 1), ("bb2", 2)] {
+            func.push_stmt(ctx.label(bb));
+            for stmt in ctx.phi_jump(&[(phi, ctx.value(CValue::Scalar(value)))], "bb3") {
+                func.push_stmt(stmt);
+            }
+        }
+        func.push_stmt(ctx.label("bb3"));
+        func.push_stmt(ctx.ret(Some(ctx.value(phi))));
+        Box::new(CFunc::new_unchecked(func))
+    });
+}
+
+#[test]
+fn test_function_cleanup() {
+    printer_test("test_function_cleanup", |ctx| {
+        let func = ctx.func(CFuncKind::new("foo", ctx.get_int_type(IntTy::I32), vec![CTy::Bool]));
+        func.push_cleanup(ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("bar")), vec![])));
+        func.push_stmt(ctx.if_stmt(ctx.value(CValue::Local(0)), ctx.goto("bb1"), None));
+        for (bb, value) in [(None, 1), (Some("bb1"), 2)] {
+            if let Some(bb) = bb {
+                func.push_stmt(ctx.label(bb));
+            }
+            for stmt in ctx.ret_via_cleanup(func, Some(ctx.value(CValue::Scalar(value)))) {
+                func.push_stmt(stmt);
+            }
+        }
+        ctx.push_cleanup_block(func);
+        Box::new(CFunc::new_unchecked(func))
+    });
+}
+
+#[test]
+fn test_function_local_static() {
+    printer_test("test_function_local_static", |ctx| {
+        let ty = ctx.get_int_type(IntTy::I32);
+        let func = ctx.func(CFuncKind::new("counter", ty, vec![]));
+        let count = func.next_local_var();
+        func.push_stmt(ctx.local_static(count, ty, ctx.value(CValue::Scalar(0))));
+        func.push_stmt(ctx.expr_stmt(ctx.unary("++", ctx.value(count))));
+        func.push_stmt(ctx.ret(Some(ctx.value(count))));
+        Box::new(CFunc::new_unchecked(func))
+    });
+}
+
+#[test]
+#[should_panic(expected = "the initializer of a local static must be a constant expression")]
+fn test_function_local_static_non_constant() {
+    let module = ModuleArena::new("// blessed test");
+    let ctx = ModuleCtx(&module);
+
+    let ty = ctx.get_int_type(IntTy::I32);
+    let init = ctx.call(ctx.value(CValue::Func("foo")), vec![]);
+    ctx.local_static(CValue::Local(0), ty, init);
+}
+
+#[test]
+#[should_panic(expected = "the initializer of a local static must be a constant expression")]
+fn test_function_local_static_comma() {
+    let module = ModuleArena::new("// blessed test");
+    let ctx = ModuleCtx(&module);
+
+    let ty = ctx.get_int_type(IntTy::I32);
+    let init = ctx.binary(ctx.value(CValue::Scalar(1)), ctx.value(CValue::Scalar(2)), ",");
+    ctx.local_static(CValue::Local(0), ty, init);
+}
+
+#[test]
+fn test_function_noinline() {
+    printer_test("test_function_noinline", |ctx| {
+        let func = ctx.func(CFuncKind::new("foo", CTy::Void, vec![]));
+        func.add_attr(CFuncAttr::NoInline);
+        func.push_stmt(ctx.ret(None));
+        Box::new(CFunc::new_unchecked(func))
+    });
+}
+
+#[test]
+fn test_function_aligned() {
+    printer_test("test_function_aligned", |ctx| {
+        let func = ctx.func(CFuncKind::new("foo", CTy::Void, vec![]));
+        func.add_attr(CFuncAttr::Aligned(16));
+        func.add_attr(CFuncAttr::Aligned(16));
+        func.push_stmt(ctx.ret(None));
+        Box::new(CFunc::new_unchecked(func))
+    });
+}
+
+#[test]
+#[should_panic(expected = "`always_inline` conflicts with `noinline` on `foo`")]
+fn test_function_noinline_always_inline() {
+    let module = ModuleArena::new("// blessed test");
+    let ctx = ModuleCtx(&module);
+
+    let func = ctx.func(CFuncKind::new("foo", CTy::Void, vec![]));
+    func.add_attr(CFuncAttr::NoInline);
+    func.add_attr(CFuncAttr::AlwaysInline);
+}
+
+#[test]
+fn test_function_nonnull_ref_param() {
+    printer_test("test_function_nonnull_ref_param", |ctx| {
+        let int = ctx.get_int_type(IntTy::I32);
+        let func = ctx.func(CFuncKind::new("foo", int, vec![ctx.ref_ptr(int, false)]));
+        func.add_attr(CFuncAttr::NonNull(1));
+        func.push_stmt(ctx.ret(Some(ctx.unary("*", ctx.value(CValue::Local(0))))));
+        Box::new(CFunc::new_unchecked(func))
+    });
+}
+
+#[test]
+fn test_function_mut_slice_param() {
+    printer_test("test_function_mut_slice_param", |ctx| {
+        let (data, len) = ctx.slice_parts(ctx.get_int_type(IntTy::I32), true);
+        let func = ctx.func(CFuncKind::new("foo", len, vec![data, len]));
+        func.push_stmt(ctx.ret(Some(ctx.value(CValue::Local(1)))));
+        Box::new(CFunc::new_unchecked(func))
+    });
+}
+
+#[test]
+fn test_function_tab_indent() {
+    blessed_test("test_function_tab_indent", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        // enough parameters for the signature to wrap, aligned after the `(`
+        let ty = ctx.get_int_type(IntTy::I32);
+        let func = ctx.func(CFuncKind::new("foo", ty, vec![ty; 8]));
+        let assign = ctx.binary(ctx.value(CValue::Local(0)), ctx.value(CValue::Local(1)), "=");
+        func.push_stmt(ctx.expr_stmt(assign));
+        func.push_stmt(ctx.ret(Some(ctx.value(CValue::Local(0)))));
+
+        let mut pp = PrinterCtx::new().with_indent_style(IndentStyle::Tabs);
+        CFunc::new_unchecked(func).print_to(&mut pp);
+        pp.finish()
+    });
+}
@@ -1,13 +1,21 @@
 #![feature(rustc_private)]
 
 use blessed_test::*;
+use run_c::compile_module;
+use rustc_codegen_c_ast::decl::CConstStyle;
 use rustc_codegen_c_ast::expr::CValue;
-use rustc_codegen_c_ast::func::{CFunc, CFuncKind};
-use rustc_type_ir::IntTy;
+use rustc_codegen_c_ast::func::{CFunc, CFuncAttr, CFuncKind};
+use rustc_codegen_c_ast::module::Module;
+use rustc_codegen_c_ast::pretty::IndentStyle;
+use rustc_codegen_c_ast::standard::CStandard;
+use rustc_codegen_c_ast::ty::{CIntNames, CQualifier, CTy, CTyKind};
+use rustc_codegen_c_ast::{ModuleArena, ModuleCtx};
+use rustc_type_ir::{IntTy, UintTy};
 
 extern crate rustc_driver;
 extern crate rustc_type_ir;
 mod blessed_test;
+mod run_c;
 
 #[test]
 fn test_module() {
@@ -30,3 +38,477 @@ fn test_module() {
         Box::new(module.clone())
     });
 }
+
+#[test]
+fn test_module_pragma() {
+    printer_test("test_module_pragma", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+        module.push_pragma("GCC diagnostic ignored \"-Wunused-parameter\"");
+
+        let func = ctx.func(CFuncKind::new("foo", CTy::Void, vec![ctx.get_int_type(IntTy::I32)]));
+        func.push_stmt(ctx.ret(None));
+        module.push_func(CFunc::new_unchecked(func));
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_string_dedup() {
+    printer_test("test_module_string_dedup", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdio.h");
+        module.set_string_dedup_threshold(2);
+
+        let func = ctx.func(CFuncKind::new("foo", CTy::Void, vec![]));
+        let puts = ctx.value(CValue::Func("puts"));
+        for s in ["hello", "hello", "hi"] {
+            func.push_stmt(ctx.expr_stmt(ctx.call(puts, vec![ctx.interned_string(s)])));
+        }
+        module.push_func(CFunc::new_unchecked(func));
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_topological_order() {
+    printer_test("test_module_topological_order", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+        module.set_topological_order(true);
+
+        let caller = ctx.func(CFuncKind::new("foo", CTy::Void, vec![]));
+        caller.push_stmt(ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("bar")), vec![])));
+        module.push_func(CFunc::new_unchecked(caller));
+
+        let callee = ctx.func(CFuncKind::new("bar", CTy::Void, vec![]));
+        callee.push_stmt(ctx.ret(None));
+        module.push_func(CFunc::new_unchecked(callee));
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_const_table() {
+    printer_test("test_module_const_table", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+
+        let func = ctx.func(CFuncKind::new("foo", CTy::Void, vec![]));
+        let bar = ctx.value(CValue::Func("bar"));
+        for _ in 0..2 {
+            let items = (1..=3).map(|i| ctx.value(CValue::Scalar(i))).collect();
+            let table = ctx.const_table(ctx.get_int_type(IntTy::I32), items);
+            func.push_stmt(ctx.expr_stmt(ctx.call(bar, vec![ctx.value(table)])));
+        }
+        module.push_func(CFunc::new_unchecked(func));
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_array_wrapper() {
+    printer_test("test_module_array_wrapper", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+
+        let array = ctx.array_wrapper(ctx.get_int_type(IntTy::I32), 4);
+        assert_eq!(array, ctx.array_wrapper(ctx.get_int_type(IntTy::I32), 4));
+        let func = ctx.func(CFuncKind::new("foo", CTy::Void, vec![array]));
+        func.push_stmt(ctx.ret(None));
+        module.push_func(CFunc::new_unchecked(func));
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_alloc_error_handler() {
+    printer_test("test_module_alloc_error_handler", |ctx| {
+        let module = ctx.module();
+
+        let func = ctx.func(CFuncKind::new("foo", CTy::Void, vec![]));
+        let handler = func.next_local_var();
+        let handler_ty = ctx.ptr(ctx.alloc_error_handler_ty());
+        func.push_stmt(ctx.decl_stmt(ctx.var(
+            handler,
+            handler_ty,
+            Some(ctx.alloc_error_handler()),
+        )));
+        let args = vec![ctx.value(CValue::Scalar(1)), ctx.value(CValue::Scalar(8))];
+        func.push_stmt(ctx.expr_stmt(ctx.call(ctx.alloc_error_handler(), args)));
+        module.push_func(CFunc::new_unchecked(func));
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_extern_printf() {
+    printer_test("test_module_extern_printf", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+
+        let fmt = ctx.ptr(ctx.qualified(CTy::Char, CQualifier::Const));
+        let ty = ctx.variadic_fn_ty(ctx.get_int_type(IntTy::I32), vec![fmt]);
+        let attrs = vec![CFuncAttr::Format { fmt_index: 1, first_arg: 2 }];
+        ctx.declare_extern_with_attrs("rust_log", ty, attrs);
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_visibility() {
+    printer_test("test_module_visibility", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+
+        let ty = ctx.get_int_type(IntTy::I32);
+        let counter = ctx.var(CValue::Global("counter"), ty, Some(ctx.value(CValue::Scalar(0))));
+        module.push_decl(ctx.with_attr(counter, CFuncAttr::Hidden));
+        for (name, hidden) in [("internal", true), ("exported", false)] {
+            let func = ctx.func(CFuncKind::new(name, ty, vec![]));
+            if hidden {
+                func.add_attr(CFuncAttr::Hidden);
+            }
+            func.push_stmt(ctx.ret(Some(ctx.value(CValue::Scalar(0)))));
+            module.push_func(CFunc::new_unchecked(func));
+        }
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_int_names() {
+    for (int_names, name) in [
+        (CIntNames::Stdint, "test_module_int_names_stdint"),
+        (CIntNames::Rust, "test_module_int_names_rust"),
+    ] {
+        blessed_test(name, || {
+            let module = ModuleArena::new("// blessed test");
+            let ctx = ModuleCtx(&module);
+            ctx.module().push_include("stdint.h");
+            ctx.module().set_int_names(int_names);
+
+            let func = ctx.func(CFuncKind::new(
+                "foo",
+                ctx.get_uint_type(UintTy::U8),
+                vec![ctx.get_int_type(IntTy::I32)],
+            ));
+            func.push_stmt(ctx.ret(Some(ctx.cast(func.ty, ctx.value(CValue::Local(0))))));
+            ctx.module().push_func(CFunc::new_unchecked(func));
+            ctx.to_string()
+        });
+    }
+}
+
+#[test]
+fn test_module_int_names_rust_compiles() {
+    // the alias of `usize` needs `size_t`, which `<stdint.h>` does not declare
+    let module = ModuleArena::new("// int names test");
+    let ctx = ModuleCtx(&module);
+    ctx.module().push_include("stdint.h");
+    ctx.module().set_int_names(CIntNames::Rust);
+    assert!(ctx.module().includes.borrow().contains(&"stddef.h"));
+
+    let usize_ty = ctx.get_uint_type(UintTy::Usize);
+    let func = ctx.func(CFuncKind::new("foo", usize_ty, vec![usize_ty]));
+    func.push_stmt(ctx.ret(Some(ctx.value(CValue::Local(0)))));
+    ctx.module().push_func(CFunc::new_unchecked(func));
+
+    let (output, _) = compile_module("int_names_rust", ctx, &["-c"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_module_diff() {
+    let module = ModuleArena::new("// blessed test");
+    let ctx = ModuleCtx(&module);
+
+    let build = |ret| {
+        let module = Module::new("// blessed test");
+        let func = ctx.func(CFuncKind::new("foo", ctx.get_int_type(IntTy::I32), vec![]));
+        func.push_stmt(ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("bar")), vec![])));
+        func.push_stmt(ctx.ret(Some(ctx.value(CValue::Scalar(ret)))));
+        module.push_func(CFunc::new_unchecked(func));
+        module
+    };
+    let (one, two) = (build(1), build(2));
+    assert!(one == build(1));
+    assert!(one != two);
+    assert_eq!(one.diff(&two), vec!["foo body[1]: `return 1;` != `return 2;`"]);
+}
+
+#[test]
+fn test_module_diff_options() {
+    let module = ModuleArena::new("// blessed test");
+    let ctx = ModuleCtx(&module);
+
+    // modules printed differently are not equal, even if their nodes are
+    let build = |cold| {
+        let module = Module::new("// blessed test");
+        let func = ctx.func(CFuncKind::new("foo", ctx.get_int_type(IntTy::I32), vec![]));
+        if cold {
+            func.add_attr(CFuncAttr::Cold);
+        }
+        module.push_func(CFunc::new_unchecked(func));
+        module
+    };
+    let (one, two) = (build(false), build(true));
+    two.standard.set(CStandard::C99);
+    two.indent_style.set(IndentStyle::Tabs);
+    assert!(one != two);
+    assert_eq!(
+        one.diff(&two),
+        vec!["standard: C11 != C99", "indent style: Spaces != Tabs", "foo attrs: [] != [Cold]"]
+    );
+}
+
+#[test]
+fn test_module_enum_fit() {
+    printer_test("test_module_enum_fit", |ctx| {
+        let module = ctx.module();
+        ctx.push_enum("foo", ctx.get_uint_type(UintTy::U8), vec![("A", 0), ("B", 256)]);
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_enum_fit_fails() {
+    // the generated code must be rejected by the C compiler, not silently truncate `B`
+    let module = ModuleArena::new("// enum fit test");
+    let ctx = ModuleCtx(&module);
+    ctx.push_enum("foo", ctx.get_uint_type(UintTy::U8), vec![("A", 0), ("B", 256)]);
+
+    let (output, _) = compile_module("enum_fit", ctx, &["-c"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("foo::B does not fit uint8_t"), "{}", stderr);
+    assert!(!stderr.contains("foo::A"), "{}", stderr);
+}
+
+#[test]
+fn test_module_formatted_fallback() {
+    let module = ModuleArena::new("// clang-format test");
+    let ctx = ModuleCtx(&module);
+    ctx.module().push_include("stdio.h");
+    assert_eq!(ctx.to_formatted_string_with(&[]), ctx.to_string());
+    assert_eq!(ctx.to_formatted_string_with(&["/nonexistent/clang-format"]), ctx.to_string());
+}
+
+#[test]
+fn test_module_no_mangle_static() {
+    printer_test("test_module_no_mangle_static", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+
+        let ty = ctx.get_int_type(IntTy::I32);
+        let init = || Some(ctx.value(CValue::Scalar(1)));
+        ctx.define_static("FOO", ty, init(), true, false);
+        ctx.define_static("_ZN4test3BAR17h0123456789abcdefE", ty, init(), false, false);
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_hidden_static() {
+    printer_test("test_module_hidden_static", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+
+        let ty = ctx.get_int_type(IntTy::I32);
+        let init = || Some(ctx.value(CValue::Scalar(1)));
+        ctx.define_static("FOO", ty, init(), true, true);
+        // a `static` global is already local to the file
+        ctx.define_static("_ZN4test3BAR17h0123456789abcdefE", ty, init(), false, true);
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_const() {
+    printer_test("test_module_const", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+
+        let ty = ctx.get_int_type(IntTy::I32);
+        let size = ctx.define_const("SIZE", 4, CConstStyle::Enum);
+        let min = ctx.define_const("MIN", -1, CConstStyle::Define);
+        let table = CValue::Global("table");
+        module.push_decl(ctx.var(table, ctx.const_arr(ty, ctx.value(size)), None));
+
+        let func = ctx.func(CFuncKind::new("foo", ty, vec![]));
+        func.push_stmt(ctx.ret(Some(ctx.value(min))));
+        module.push_func(CFunc::new_unchecked(func));
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+#[should_panic(expected = "does not fit int")]
+fn test_module_const_enum_overflow() {
+    let module = ModuleArena::new("// blessed test");
+    let ctx = ModuleCtx(&module);
+    ctx.define_const("BIG", 1 << 40, CConstStyle::Enum);
+}
+
+#[test]
+fn test_module_tuple_struct_stable() {
+    let build = |first_other: bool| {
+        let module = ModuleArena::new("// tuple test");
+        let ctx = ModuleCtx(&module);
+        let fields = [ctx.get_int_type(IntTy::I32), ctx.ptr(ctx.get_uint_type(UintTy::U8))];
+        // requesting an unrelated tuple first must not affect the name
+        if first_other {
+            ctx.tuple_struct(&[ctx.get_uint_type(UintTy::U64)]);
+        }
+        let tuple = ctx.tuple_struct(&fields);
+        assert_eq!(ctx.tuple_struct(&fields), tuple);
+        match tuple {
+            CTy::Ref(kind) => match *kind {
+                CTyKind::Struct(tag) => tag.to_string(),
+                _ => panic!("a tuple struct is not a struct type"),
+            },
+            _ => panic!("a tuple struct is not a struct type"),
+        }
+    };
+    let name = build(false);
+    assert!(name.contains("__rust_tuple_"), "{name}");
+    assert_eq!(build(true), name);
+}
+
+#[test]
+#[should_panic(expected = "collides with [UInt(U64)]")]
+fn test_module_tuple_struct_collision() {
+    let tag = {
+        let module = ModuleArena::new("// tuple test");
+        let ctx = ModuleCtx(&module);
+        match ctx.tuple_struct(&[ctx.get_int_type(IntTy::I32)]) {
+            CTy::Ref(kind) => match *kind {
+                CTyKind::Struct(tag) => tag.to_string(),
+                _ => panic!("a tuple struct is not a struct type"),
+            },
+            _ => panic!("a tuple struct is not a struct type"),
+        }
+    };
+
+    // pretend that other field types hash to the same tag
+    let module = ModuleArena::new("// tuple test");
+    let ctx = ModuleCtx(&module);
+    let tuple = ctx.struct_ty(ctx.alloc_str(&tag));
+    ctx.module().tuple_structs.borrow_mut().insert(vec![ctx.get_uint_type(UintTy::U64)], tuple);
+    ctx.tuple_struct(&[ctx.get_int_type(IntTy::I32)]);
+}
+
+#[test]
+#[should_panic(expected = "a zero-sized aggregate has no C struct")]
+fn test_module_tuple_struct_empty() {
+    let module = ModuleArena::new("// tuple test");
+    let ctx = ModuleCtx(&module);
+    ctx.tuple_struct(&[]);
+}
+
+#[test]
+fn test_module_narrowing_conversions() {
+    let module = ModuleArena::new("// lint test");
+    let ctx = ModuleCtx(&module);
+    let (u8_ty, u32_ty) = (ctx.get_uint_type(UintTy::U8), ctx.get_uint_type(UintTy::U32));
+
+    let func = ctx.func(CFuncKind::new("foo", CTy::Void, vec![u32_ty]));
+    let (param, narrow, cast) =
+        (ctx.value(CValue::Local(0)), func.next_local_var(), func.next_local_var());
+    func.push_stmt(ctx.decl_stmt(ctx.var(narrow, u8_ty, None)));
+    func.push_stmt(ctx.expr_stmt(ctx.binary(ctx.value(narrow), param, "=")));
+    // an explicit cast is fine, and so is widening
+    func.push_stmt(ctx.decl_stmt(ctx.var(cast, u8_ty, Some(ctx.cast(u8_ty, param)))));
+    func.push_stmt(ctx.expr_stmt(ctx.binary(param, ctx.value(cast), "=")));
+    func.push_stmt(ctx.ret(None));
+    ctx.module().push_func(CFunc::new_unchecked(func));
+
+    assert_eq!(
+        ctx.module().narrowing_conversions(),
+        vec!["foo: `(_1 = _0)` narrows uint32_t to uint8_t"]
+    );
+}
+
+#[test]
+fn test_module_split_header() {
+    blessed_test("test_module_split_header", || {
+        let module = ModuleArena::new("// split test");
+        let ctx = ModuleCtx(&module);
+        ctx.module().push_include("stdint.h");
+
+        let ty = ctx.get_int_type(IntTy::I32);
+        for name in ["answer", "main"] {
+            let func = ctx.func(CFuncKind::new(name, ty, vec![]));
+            func.push_stmt(ctx.ret(Some(ctx.value(CValue::Scalar(0)))));
+            ctx.module().push_func(CFunc::new_unchecked(func));
+        }
+        ctx.to_header_string("__RUST_FOO_H")
+    });
+}
+
+#[test]
+#[should_panic(expected = "cannot be shared through a header")]
+fn test_module_split_header_decls() {
+    let module = ModuleArena::new("// split test");
+    let ctx = ModuleCtx(&module);
+    let ty = ctx.get_int_type(IntTy::I32);
+    ctx.module().push_decl(ctx.struct_decl("foo", vec![(ty, "a")]));
+    ctx.to_header_string("__RUST_FOO_H");
+}
+
+#[test]
+fn test_module_local_include() {
+    printer_test("test_module_local_include", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+        module.push_local_include("foo.h");
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_local_include_int_names() {
+    blessed_test("test_module_local_include_int_names", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+        ctx.module().push_include("stdint.h");
+        ctx.module().set_int_names(CIntNames::Rust);
+        ctx.module().push_local_include("foo.h");
+        ctx.to_string()
+    });
+}
+
+#[test]
+fn test_module_error() {
+    printer_test("test_module_error", |ctx| {
+        let module = ctx.module();
+        module.push_include("stdint.h");
+        module.push_error("inline assembly `asm!(\"nop\")` at src/main.rs:3:5");
+        Box::new(module.clone())
+    });
+}
+
+#[test]
+fn test_module_write_to() {
+    for indent_style in [IndentStyle::Spaces, IndentStyle::Tabs] {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+        ctx.module().push_include("stdint.h");
+        ctx.module().set_indent_style(indent_style);
+
+        let ty = ctx.get_int_type(IntTy::I32);
+        ctx.module().push_decl(ctx.struct_decl("foo", vec![(ty, "a"), (ty, "b")]));
+        for name in ["bar", "baz"] {
+            let func = ctx.func(CFuncKind::new(name, ty, vec![ty]));
+            let cond = ctx.value(CValue::Local(0));
+            let then = ctx.ret(Some(ctx.value(CValue::Scalar(1))));
+            func.push_stmt(ctx.if_stmt(cond, then, None));
+            func.push_stmt(ctx.ret(Some(ctx.value(CValue::Scalar(0)))));
+            ctx.module().push_func(CFunc::new_unchecked(func));
+        }
+
+        let mut streamed = vec![];
+        ctx.write_to(&mut streamed).unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), ctx.to_string());
+    }
+}
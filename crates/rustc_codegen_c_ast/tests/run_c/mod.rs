@@ -0,0 +1,36 @@
+#![allow(dead_code)] // each test file only uses some of the helpers
+
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Output};
+
+use rustc_codegen_c_ast::ModuleCtx;
+
+/// Compile a module with `$CC`, `clang` by default, and `args`, in a fresh
+/// directory unique to the test `name` and this process.
+///
+/// Returns the output of the compiler and the path of the compiled file.
+pub fn compile_module(name: &str, ctx: ModuleCtx, args: &[&str]) -> (Output, PathBuf) {
+    let dir =
+        std::env::temp_dir().join(format!("rustc_codegen_c_ast_{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.c"), ctx.to_string()).unwrap();
+
+    let cc = std::env::var("CC").unwrap_or("clang".to_string());
+    let output = Command::new(cc)
+        .args(args)
+        .arg(dir.join("main.c"))
+        .arg("-o")
+        .arg(dir.join("main"))
+        .output()
+        .expect("failed to run the C compiler");
+    (output, dir.join("main"))
+}
+
+/// Compile a module with a `main` function, see [`compile_module`], then run
+/// it and get its exit status.
+pub fn run_module(name: &str, ctx: ModuleCtx, args: &[&str]) -> ExitStatus {
+    let (output, main) = compile_module(name, ctx, args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    Command::new(main).status().unwrap()
+}
@@ -2,6 +2,10 @@
 
 use blessed_test::*;
 use rustc_codegen_c_ast::expr::CValue;
+use rustc_codegen_c_ast::pretty::{Print, PrinterCtx};
+use rustc_codegen_c_ast::standard::CStandard;
+use rustc_codegen_c_ast::stmt::CStmtKind;
+use rustc_codegen_c_ast::{ModuleArena, ModuleCtx};
 use rustc_type_ir::IntTy;
 
 extern crate rustc_driver;
@@ -29,6 +33,30 @@ fn test_stmt_decl() {
     });
 }
 
+#[test]
+fn test_stmt_decl_vla() {
+    blessed_test("test_stmt_decl_vla", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        let ty = ctx.vla(ctx.get_int_type(IntTy::I32), ctx.value(CValue::Local(0)));
+        let mut pp = PrinterCtx::with_standard(CStandard::Gnu11);
+        ctx.decl_stmt(ctx.var(CValue::Local(1), ty, None)).print_to(&mut pp);
+        pp.finish()
+    });
+}
+
+#[test]
+#[should_panic(expected = "variable length arrays are not available in c11")]
+fn test_stmt_decl_vla_c11() {
+    let module = ModuleArena::new("// blessed test");
+    let ctx = ModuleCtx(&module);
+
+    let ty = ctx.vla(ctx.get_int_type(IntTy::I32), ctx.value(CValue::Local(0)));
+    let mut pp = PrinterCtx::with_standard(CStandard::C11);
+    ctx.decl_stmt(ctx.var(CValue::Local(1), ty, None)).print_to(&mut pp);
+}
+
 #[test]
 fn test_stmt_block() {
     printer_test("test_stmt_block", |ctx| {
@@ -42,10 +70,253 @@ fn test_stmt_block() {
 
 #[test]
 fn test_stmt_ret() {
-    printer_test("test_stmt_if", |ctx| {
+    printer_test("test_stmt_ret", |ctx| {
         let callee = ctx.value(CValue::Func("foo"));
         let args = vec![ctx.value(CValue::Scalar(1)), ctx.value(CValue::Scalar(2))];
         let expr = ctx.call(callee, args);
         Box::new(ctx.ret(Some(expr)))
     });
 }
+
+#[test]
+fn test_stmt_if() {
+    printer_test("test_stmt_if", |ctx| {
+        let foo = ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("foo")), vec![]));
+        Box::new(ctx.if_stmt(ctx.value(CValue::Local(0)), foo, None))
+    });
+}
+
+#[test]
+fn test_stmt_if_else() {
+    printer_test("test_stmt_if_else", |ctx| {
+        let foo = ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("foo")), vec![]));
+        let bar = ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("bar")), vec![]));
+        Box::new(ctx.if_stmt(ctx.value(CValue::Local(0)), foo, Some(bar)))
+    });
+}
+
+#[test]
+fn test_stmt_else_if() {
+    printer_test("test_stmt_else_if", |ctx| {
+        let call = |name| ctx.expr_stmt(ctx.call(ctx.value(CValue::Func(name)), vec![]));
+        let inner = ctx.if_stmt(ctx.value(CValue::Local(1)), call("bar"), Some(call("baz")));
+        Box::new(ctx.if_stmt(ctx.value(CValue::Local(0)), call("foo"), Some(inner)))
+    });
+}
+
+#[test]
+fn test_stmt_ret_cast() {
+    printer_test("test_stmt_ret_cast", |ctx| {
+        let ret_ty = ctx.get_int_type(IntTy::I64);
+        let ty = ctx.get_int_type(IntTy::I32);
+        Box::new(ctx.ret_as(ret_ty, ty, ctx.value(CValue::Local(0))))
+    });
+}
+
+#[test]
+fn test_stmt_ret_same_type() {
+    printer_test("test_stmt_ret_same_type", |ctx| {
+        let ty = ctx.get_int_type(IntTy::I64);
+        Box::new(ctx.ret_as(ty, ty, ctx.value(CValue::Local(0))))
+    });
+}
+
+#[test]
+fn test_stmt_break_label() {
+    printer_test("test_stmt_break_label", |ctx| {
+        let outer = ctx.loop_label("outer");
+        let foo = ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("foo")), vec![]));
+        Box::new(ctx.compound(vec![
+            foo,
+            ctx.break_to(outer),
+            ctx.continue_target(outer),
+            ctx.ret(None),
+            ctx.break_target(outer),
+            ctx.ret(None),
+        ]))
+    });
+}
+
+#[test]
+fn test_stmt_label_empty() {
+    printer_test("test_stmt_label_empty", |ctx| {
+        let foo = ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("foo")), vec![]));
+        Box::new(ctx.compound(vec![foo, ctx.label("end"), ctx.empty()]))
+    });
+}
+
+#[test]
+fn test_stmt_label_goto() {
+    printer_test("test_stmt_label_goto", |ctx| {
+        let foo = ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("foo")), vec![]));
+        let body = ctx.compound(vec![ctx.label("bb1"), foo, ctx.goto("bb1")]);
+        Box::new(ctx.compound(vec![
+            ctx.label("bb0"),
+            ctx.while_stmt(ctx.value(CValue::Local(0)), body),
+            ctx.goto("bb0"),
+        ]))
+    });
+}
+
+#[test]
+fn test_stmt_hook() {
+    blessed_test("test_stmt_hook", || {
+        let module = ModuleArena::new("// blessed test");
+        let ctx = ModuleCtx(&module);
+
+        let foo = ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("foo")), vec![]));
+        let stmt = ctx.compound(vec![foo, ctx.ret(None)]);
+        let mut pp = PrinterCtx::new().with_stmt_hook(|stmt| {
+            let counted = !matches!(stmt, CStmtKind::Compound(_));
+            counted.then(|| "__rust_counter++;".to_string())
+        });
+        stmt.print_to(&mut pp);
+        pp.finish()
+    });
+}
+
+#[test]
+fn test_stmt_seq() {
+    let module = ModuleArena::new("// blessed test");
+    let ctx = ModuleCtx(&module);
+
+    let call = |i| {
+        ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("foo")), vec![ctx.value(CValue::Scalar(i))]))
+    };
+    let print = |stmt: &dyn Print| {
+        let mut pp = PrinterCtx::new();
+        stmt.print_to(&mut pp);
+        pp.finish()
+    };
+    let seq = ctx.seq((0..3).map(call));
+    let compound = ctx.compound(vec![call(0), call(1), call(2)]);
+    assert_eq!(print(&seq), print(&compound));
+}
+
+#[test]
+fn test_stmt_comment() {
+    printer_test("test_stmt_comment", |ctx| {
+        let foo = ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("foo")), vec![]));
+        Box::new(ctx.compound(vec![
+            ctx.comment("bb2[3]"),
+            foo,
+            ctx.comment("bb2[4]"),
+            ctx.ret(None),
+        ]))
+    });
+}
+
+#[test]
+fn test_stmt_comment_only() {
+    printer_test("test_stmt_comment_only", |ctx| {
+        Box::new(ctx.compound(vec![ctx.comment("bb0[0]")]))
+    });
+}
+
+#[test]
+fn test_stmt_cond() {
+    printer_test("test_stmt_cond", |ctx| {
+        let cond = ctx.value(CValue::Local(0));
+        let call = ctx.call(ctx.value(CValue::Func("foo")), vec![]);
+        let ret = ctx.ret(Some(ctx.value(CValue::Scalar(1))));
+        let stmt = ctx.cond(cond, |then| then.push(ctx.expr_stmt(call)), |els| els.push(ret));
+
+        let manual = ctx.if_stmt(
+            cond,
+            ctx.compound(vec![ctx.expr_stmt(call)]),
+            Some(ctx.compound(vec![ret])),
+        );
+        assert_eq!(stmt, manual);
+        // no else branch is built if the closure pushes nothing
+        assert_eq!(
+            ctx.cond(cond, |then| then.push(ret), |_| {}),
+            ctx.if_stmt(cond, ctx.compound(vec![ret]), None)
+        );
+        Box::new(stmt)
+    });
+}
+
+#[test]
+fn test_stmt_store() {
+    printer_test("test_stmt_store", |ctx| {
+        let (ptr, value) = (ctx.value(CValue::Local(0)), ctx.value(CValue::Local(1)));
+        Box::new(ctx.store(ptr, value, ctx.get_int_type(IntTy::I32)))
+    });
+}
+
+#[test]
+fn test_stmt_while_nested() {
+    printer_test("test_stmt_while_nested", |ctx| {
+        let dec = |i| {
+            let local = ctx.value(CValue::Local(i));
+            let rhs = ctx.binary(local, ctx.value(CValue::Scalar(1)), "-");
+            ctx.expr_stmt(ctx.binary(local, rhs, "="))
+        };
+        let inner = ctx.while_stmt(ctx.value(CValue::Local(1)), dec(1));
+        Box::new(ctx.while_stmt(ctx.value(CValue::Local(0)), ctx.compound(vec![inner, dec(0)])))
+    });
+}
+
+#[test]
+fn test_stmt_while_empty() {
+    printer_test("test_stmt_while_empty", |ctx| {
+        Box::new(ctx.while_stmt(ctx.value(CValue::Local(0)), ctx.compound(vec![])))
+    });
+}
+
+#[test]
+fn test_stmt_for() {
+    printer_test("test_stmt_for", |ctx| {
+        let i = ctx.value(CValue::Local(1));
+        let one = ctx.value(CValue::Scalar(1));
+        let init = ctx.var(
+            CValue::Local(1),
+            ctx.get_int_type(IntTy::I32),
+            Some(ctx.value(CValue::Scalar(0))),
+        );
+        let cond = ctx.binary(i, ctx.value(CValue::Local(0)), "<");
+        let step = ctx.binary(i, ctx.binary(i, one, "+"), "=");
+        let body = ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("foo")), vec![i]));
+        Box::new(ctx.for_stmt(
+            Some(ctx.decl_stmt(init)),
+            Some(cond),
+            Some(step),
+            ctx.compound(vec![body]),
+        ))
+    });
+}
+
+#[test]
+fn test_stmt_for_empty() {
+    printer_test("test_stmt_for_empty", |ctx| {
+        let body = ctx.expr_stmt(ctx.call(ctx.value(CValue::Func("foo")), vec![]));
+        Box::new(ctx.for_stmt(None, None, None, ctx.compound(vec![body])))
+    });
+}
+
+#[test]
+fn test_stmt_break_continue() {
+    printer_test("test_stmt_break_continue", |ctx| {
+        let call = |name| ctx.expr_stmt(ctx.call(ctx.value(CValue::Func(name)), vec![]));
+        let body = vec![
+            ctx.if_stmt(ctx.value(CValue::Local(1)), ctx.continue_stmt(), None),
+            call("foo"),
+            ctx.if_stmt(ctx.value(CValue::Local(2)), ctx.break_stmt(), None),
+        ];
+        Box::new(ctx.while_stmt(ctx.value(CValue::Local(0)), ctx.compound(body)))
+    });
+}
+
+#[test]
+fn test_stmt_switch() {
+    printer_test("test_stmt_switch", |ctx| {
+        let call = |name| ctx.expr_stmt(ctx.call(ctx.value(CValue::Func(name)), vec![]));
+        let cases = vec![
+            (0, ctx.compound(vec![call("foo"), ctx.break_stmt()])),
+            (1, ctx.compound(vec![call("bar"), call("baz"), ctx.break_stmt()])),
+            (-1, ctx.ret(Some(ctx.value(CValue::Scalar(2))))),
+        ];
+        let default = ctx.compound(vec![call("qux"), ctx.break_stmt()]);
+        Box::new(ctx.switch(ctx.value(CValue::Local(0)), cases, Some(default)))
+    });
+}
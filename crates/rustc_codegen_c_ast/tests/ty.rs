@@ -0,0 +1,50 @@
+#![feature(rustc_private)]
+
+use rustc_codegen_c_ast::expr::CValue;
+use rustc_codegen_c_ast::pretty::{Print, PrinterCtx};
+use rustc_codegen_c_ast::ty::{CFloatTy, CQualifier, CTy};
+use rustc_codegen_c_ast::{ModuleArena, ModuleCtx};
+use rustc_type_ir::{FloatTy, IntTy};
+
+extern crate rustc_driver;
+extern crate rustc_type_ir;
+
+#[test]
+fn test_ty_interned() {
+    let module = ModuleArena::new("// test");
+    let ctx = ModuleCtx(&module);
+
+    let i32 = ctx.get_int_type(IntTy::I32);
+    assert_eq!(ctx.ptr(i32), ctx.ptr(i32));
+    assert_eq!(ctx.arr(ctx.ptr(i32), 4), ctx.arr(ctx.ptr(i32), 4));
+    assert_eq!(
+        ctx.ptr(ctx.qualified(CTy::Char, CQualifier::Const)),
+        ctx.ptr(ctx.qualified(CTy::Char, CQualifier::Const))
+    );
+    assert_eq!(
+        ctx.vla(i32, ctx.value(CValue::Local(0))),
+        ctx.vla(i32, ctx.value(CValue::Local(0)))
+    );
+
+    assert_ne!(ctx.ptr(i32), ctx.ptr(CTy::Char));
+    assert_ne!(ctx.arr(i32, 4), ctx.arr(i32, 5));
+}
+
+#[test]
+fn test_ty_float() {
+    let module = ModuleArena::new("// test");
+    let ctx = ModuleCtx(&module);
+
+    let f32 = ctx.get_float_type(FloatTy::F32);
+    let f64 = ctx.get_float_type(FloatTy::F64);
+    assert_eq!(f32, CTy::Float(CFloatTy::F32));
+    assert_eq!(f64, CTy::Float(CFloatTy::F64));
+
+    let print = |decl: &dyn Print| {
+        let mut printer = PrinterCtx::new();
+        decl.print_to(&mut printer);
+        printer.finish()
+    };
+    assert_eq!(print(&ctx.var(CValue::Local(0), f32, None)), "float _0;");
+    assert_eq!(print(&ctx.var(CValue::Local(1), ctx.ptr(f64), None)), "double *_1;");
+}
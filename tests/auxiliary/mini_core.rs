@@ -25,6 +25,12 @@ unsafe impl<'a, T: ?Sized> Copy for &'a T {}
 unsafe impl<T: ?Sized> Copy for *const T {}
 unsafe impl<T: ?Sized> Copy for *mut T {}
 
+#[lang = "receiver"]
+pub trait Receiver {}
+
+impl<T: ?Sized> Receiver for &T {}
+impl<T: ?Sized> Receiver for &mut T {}
+
 pub mod libc {
     #[link(name = "c")]
     extern "C" {
@@ -37,3 +43,118 @@ pub mod libc {
         pub fn strncpy(dst: *mut u8, src: *const u8, size: usize);
     }
 }
+
+#[lang = "add"]
+pub trait Add<Rhs = Self> {
+    type Output;
+
+    fn add(self, rhs: Rhs) -> Self::Output;
+}
+
+#[lang = "sub"]
+pub trait Sub<Rhs = Self> {
+    type Output;
+
+    fn sub(self, rhs: Rhs) -> Self::Output;
+}
+
+#[lang = "mul"]
+pub trait Mul<Rhs = Self> {
+    type Output;
+
+    fn mul(self, rhs: Rhs) -> Self::Output;
+}
+
+/// Implement an arithmetic operator trait for the integer types with the
+/// built-in operator
+macro_rules! impl_binop {
+    ($trait:ident, $method:ident, $op:tt) => {
+        impl_binop!($trait, $method, $op, u8 u16 u32 u64 usize i8 i16 i32 isize);
+    };
+    ($trait:ident, $method:ident, $op:tt, $($ty:ty)*) => {
+        $(
+            impl $trait for $ty {
+                type Output = $ty;
+
+                fn $method(self, rhs: $ty) -> $ty {
+                    self $op rhs
+                }
+            }
+        )*
+    };
+}
+
+impl_binop!(Add, add, +);
+impl_binop!(Sub, sub, -);
+impl_binop!(Mul, mul, *);
+
+#[lang = "eq"]
+pub trait PartialEq<Rhs: ?Sized = Self> {
+    fn eq(&self, other: &Rhs) -> bool;
+    fn ne(&self, other: &Rhs) -> bool;
+}
+
+#[lang = "partial_ord"]
+pub trait PartialOrd<Rhs: ?Sized = Self>: PartialEq<Rhs> {
+    fn lt(&self, other: &Rhs) -> bool;
+    fn le(&self, other: &Rhs) -> bool;
+    fn gt(&self, other: &Rhs) -> bool;
+    fn ge(&self, other: &Rhs) -> bool;
+}
+
+/// Implement the comparison traits for the integer types with the built-in
+/// operators
+macro_rules! impl_cmp {
+    ($($ty:ty)*) => {
+        $(
+            impl PartialEq for $ty {
+                fn eq(&self, other: &$ty) -> bool {
+                    *self == *other
+                }
+
+                fn ne(&self, other: &$ty) -> bool {
+                    *self != *other
+                }
+            }
+
+            impl PartialOrd for $ty {
+                fn lt(&self, other: &$ty) -> bool {
+                    *self < *other
+                }
+
+                fn le(&self, other: &$ty) -> bool {
+                    *self <= *other
+                }
+
+                fn gt(&self, other: &$ty) -> bool {
+                    *self > *other
+                }
+
+                fn ge(&self, other: &$ty) -> bool {
+                    *self >= *other
+                }
+            }
+        )*
+    };
+}
+
+impl_cmp!(u8 u16 u32 u64 usize i8 i16 i32 isize);
+
+pub mod arch {
+    #[rustc_builtin_macro]
+    pub macro asm("assembly template", $(operands,)* $(options($(option),*))?) {
+        /* compiler built-in */
+    }
+
+    #[rustc_builtin_macro]
+    pub macro global_asm("assembly template", $(operands,)* $(options($(option),*))?) {
+        /* compiler built-in */
+    }
+}
+
+pub mod intrinsics {
+    extern "rust-intrinsic" {
+        #[rustc_safe_intrinsic]
+        pub fn abort() -> !;
+    }
+}
@@ -21,6 +21,8 @@ int32_t main() { return 0; }
 
 int64_t foo(uint8_t _0, uint16_t _1, uint32_t _2)
 {
-  int64_t _3 = __rust_utos(uint64_t, int64_t, (int64_t) _0, INT64_MAX);
+  (void) _1;
+  (void) _2;
+  int64_t _3 = __rust_utos(uint64_t, int64_t, (uint64_t) _0, INT64_MAX);
   return _3;
 }
@@ -0,0 +1,21 @@
+//! Test that `#[cold]` functions are marked `cold`
+
+//@ aux-build:mini_core.rs
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+// CHECK: __attribute__((cold)) int32_t foo(int32_t _0);
+#[cold]
+#[no_mangle]
+pub fn foo(x: i32) -> i32 {
+    x
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    0
+}
@@ -0,0 +1,19 @@
+//! Test that the generated C is printed to stdout with `RUSTC_CODEGEN_C_EMIT`,
+//! the captured output being file checked instead of the generated file
+
+//@ aux-build:mini_core.rs
+//@ rustc-env: RUSTC_CODEGEN_C_EMIT=stdout
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+// CHECK: // file: emit_stdout{{.*}}.c
+// CHECK-LABEL: int32_t main
+// CHECK: return 0;
+#[no_mangle]
+pub fn main() -> i32 {
+    0
+}
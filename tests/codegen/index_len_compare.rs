@@ -0,0 +1,22 @@
+//! Test that `isize` is a signed type, so that comparing an index to a length
+//! is a signed comparison
+
+//@ aux-build:mini_core.rs
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+// CHECK-LABEL: _Bool in_bounds(intptr_t _0, size_t _1)
+// CHECK: (_0 < _2)
+#[no_mangle]
+pub fn in_bounds(index: isize, len: usize) -> bool {
+    index < len as isize
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    0
+}
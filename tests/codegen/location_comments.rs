@@ -0,0 +1,27 @@
+//! Test that each statement is annotated with its MIR location when location
+//! comments are enabled, including the first statement of every function
+
+//@ aux-build:mini_core.rs
+//@ rustc-env: RUSTC_CODEGEN_C_LOCATION_COMMENTS=1
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+// CHECK-LABEL: int32_t first(){{$}}
+// CHECK: // bb0[0]
+// CHECK: return
+#[no_mangle]
+pub fn first() -> i32 {
+    0
+}
+
+// CHECK-LABEL: int32_t main(){{$}}
+// CHECK: // bb0[0]
+// CHECK: return
+#[no_mangle]
+pub fn main() -> i32 {
+    0
+}
@@ -0,0 +1,22 @@
+//! Test that `#[must_use]` functions are marked `warn_unused_result`
+
+//@ aux-build:mini_core.rs
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+// CHECK: __attribute__((warn_unused_result)) int32_t foo(int32_t _0);
+// CHECK-NOT: __attribute__((warn_unused_result)) int32_t main
+#[must_use]
+#[no_mangle]
+pub fn foo(x: i32) -> i32 {
+    x
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    0
+}
@@ -0,0 +1,21 @@
+//! Test that `#[inline(never)]` functions are marked `noinline`
+
+//@ aux-build:mini_core.rs
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+// CHECK: __attribute__((noinline)) int32_t foo(int32_t _0);
+#[inline(never)]
+#[no_mangle]
+pub fn foo(x: i32) -> i32 {
+    x
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    0
+}
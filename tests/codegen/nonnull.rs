@@ -0,0 +1,23 @@
+//! Test that the pointers lowered from references are marked `nonnull`
+
+//@ aux-build:mini_core.rs
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+// CHECK-LABEL: __attribute__((nonnull(1), nonnull(3))) int32_t foo(const int32_t *_0,
+// CHECK-NEXT: int32_t _1,
+// CHECK-NEXT: int32_t *restrict _2,
+// CHECK-NEXT: size_t _3)
+#[no_mangle]
+pub fn foo(_x: &i32, _y: i32, _z: &mut [i32]) -> i32 {
+    0
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    0
+}
@@ -0,0 +1,24 @@
+//! Test that a unique slice is passed as a `restrict` data pointer and a length
+
+//@ aux-build:mini_core.rs
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+// CHECK-LABEL: foo(int32_t *restrict _0,
+// CHECK-NEXT: size_t _1,
+// CHECK-NEXT: const int32_t *_2,
+// CHECK-NEXT: size_t _3)
+// CHECK: return 0;
+#[no_mangle]
+pub fn foo(_x: &mut [i32], _y: &[i32]) -> i32 {
+    0
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    0
+}
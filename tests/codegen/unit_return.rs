@@ -0,0 +1,23 @@
+//! Test that a function returning `()` is a `void` function, and that
+//! zero-sized arguments are neither declared nor passed
+
+//@ aux-build:mini_core.rs
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+// CHECK-LABEL: void side_effect(int32_t _0){{$}}
+// CHECK: return;
+#[no_mangle]
+pub fn side_effect(_x: i32, _unit: ()) {}
+
+// CHECK-LABEL: int32_t main(){{$}}
+// CHECK: side_effect(1);
+#[no_mangle]
+pub fn main() -> i32 {
+    side_effect(1, ());
+    0
+}
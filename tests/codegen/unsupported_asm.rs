@@ -0,0 +1,25 @@
+//! Test that global and inline assembly, which C cannot express, are reported
+//! with an `#error` at their source location instead of panicking the backend.
+//! Compiling the C fails on purpose, the generated file is checked regardless.
+
+//@ aux-build:mini_core.rs
+//@ build-fail
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+use mini_core::arch::{asm, global_asm};
+
+// CHECK-DAG: #error "unsupported: global assembly at {{.*}}unsupported_asm.rs:[[@LINE+1]]:
+global_asm!("nop");
+
+// CHECK-DAG: #error "unsupported: inline assembly at {{.*}}unsupported_asm.rs:[[@LINE+4]]:
+// CHECK-LABEL: int32_t main
+#[no_mangle]
+pub fn main() -> i32 {
+    unsafe { asm!("nop") };
+    0
+}
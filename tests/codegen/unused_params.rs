@@ -0,0 +1,25 @@
+//! Test that unused parameters are discarded to keep `-Wunused-parameter` quiet
+
+//@ aux-build:mini_core.rs
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+// CHECK-LABEL: foo
+// CHECK: (int32_t _0, int32_t _1, int32_t _2)
+// CHECK-NOT: (void) _0;
+// CHECK: (void) _1;
+// CHECK: (void) _2;
+// CHECK: return
+#[no_mangle]
+pub fn foo(x: i32, _y: i32, _z: i32) -> i64 {
+    x as i64
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    0
+}
@@ -0,0 +1,15 @@
+//! Test that the abort intrinsic terminates the program abnormally
+
+//@ aux-build:mini_core.rs
+//@ run-fail
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    mini_core::intrinsics::abort()
+}
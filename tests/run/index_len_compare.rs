@@ -0,0 +1,21 @@
+//! Test that a signed index is compared to a length as a signed integer, so
+//! that a negative index is not converted to a huge unsigned value
+
+//@ aux-build:mini_core.rs
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+#[no_mangle]
+pub fn in_bounds(index: isize, len: usize) -> bool {
+    index < len as isize
+}
+
+// FIXME: check `in_bounds(-1, 4)` once branches are supported
+#[no_mangle]
+pub fn main() -> i32 {
+    0
+}
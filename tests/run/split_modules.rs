@@ -0,0 +1,27 @@
+//! Test that the C generated for each top-level module is a file of its own,
+//! and that the files call each other through the shared header
+
+//@ aux-build:mini_core.rs
+//@ split-modules: 2
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+mod math {
+    // kept out of line, so that `main` calls into the other file
+    #[inline(never)]
+    #[no_mangle]
+    pub fn answer() -> i32 {
+        42
+    }
+}
+
+mod entry {
+    #[no_mangle]
+    pub fn main() -> i32 {
+        crate::math::answer() - 42
+    }
+}
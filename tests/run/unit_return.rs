@@ -0,0 +1,19 @@
+//! Test that a function returning `()` compiles to a `void` function which
+//! links and runs
+
+//@ aux-build:mini_core.rs
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+#[no_mangle]
+pub fn side_effect() {}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    side_effect();
+    0
+}
@@ -0,0 +1,22 @@
+//! Test that arithmetic on integers narrower than `int` wraps like in Rust,
+//! including with a constant left operand
+
+//@ aux-build:mini_core.rs
+
+#![feature(no_core)]
+#![no_core]
+#![no_main]
+
+extern crate mini_core;
+
+// the argument count is not known at compile time, so nothing is folded
+#[no_mangle]
+pub fn main(argc: i32) -> i32 {
+    // 200
+    let x = 199u8 + argc as u8;
+    // `200u8 + 100u8` wraps to 44, while C computes 300 in `int`
+    let sum = x + 100;
+    // 200 * 2 wraps to 144
+    let product = 2 * x;
+    (sum as i32 - 44) + (product as i32 - 144)
+}